@@ -0,0 +1,99 @@
+//! Schema inference over a large in-memory sample, one row at a time
+//! versus spread across threads, and a full scan of a file read normally
+//! versus memory-mapped
+//!
+//! The file scan uses a generated file unless `INFERENCE_BENCH_FILE` names
+//! one, e.g. a multi-GB export.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use csv_sql_loader::parser::{CsvOptions, CsvParser};
+use csv_sql_loader::schema::{InferenceConfig, TableSchema};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+const ROWS: usize = 200_000;
+const COLUMNS: usize = 20;
+
+fn sample() -> (Vec<String>, Vec<(u64, Vec<String>)>) {
+    let names = (0..COLUMNS).map(|i| format!("c{}", i)).collect();
+    let rows = (0..ROWS)
+        .map(|i| {
+            let row = (0..COLUMNS)
+                .map(|c| match c % 5 {
+                    0 => i.to_string(),
+                    1 => format!("{}.{:02}", i % 1000, i % 100),
+                    2 => format!("2024-{:02}-{:02}", i % 12 + 1, i % 28 + 1),
+                    3 => if i % 7 == 0 { String::new() } else { "true".to_string() },
+                    _ => format!("name {}", i),
+                })
+                .collect();
+            (i as u64 + 2, row)
+        })
+        .collect();
+    (names, rows)
+}
+
+fn bench_inference(c: &mut Criterion) {
+    let (names, rows) = sample();
+    let config = InferenceConfig::default();
+    let mut group = c.benchmark_group("infer_200k_rows");
+    group.sample_size(10);
+
+    group.bench_function("serial", |b| {
+        b.iter(|| {
+            let mut schema = TableSchema::new("bench".to_string(), names.clone());
+            for (_, row) in &rows {
+                schema.update_row_with(row, &config).unwrap();
+            }
+            schema
+        })
+    });
+
+    group.bench_function("parallel", |b| {
+        b.iter(|| {
+            let mut schema = TableSchema::new("bench".to_string(), names.clone());
+            schema.update_rows_with(&rows, &config).unwrap();
+            schema
+        })
+    });
+
+    group.finish();
+}
+
+fn bench_file_scan(c: &mut Criterion) {
+    let (_dir, path) = match std::env::var_os("INFERENCE_BENCH_FILE") {
+        Some(path) => (None, PathBuf::from(path)),
+        None => {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("bench.csv");
+            let (names, rows) = sample();
+            let mut out = BufWriter::new(std::fs::File::create(&path).unwrap());
+            writeln!(out, "{}", names.join(",")).unwrap();
+            for (_, row) in &rows {
+                writeln!(out, "{}", row.join(",")).unwrap();
+            }
+            out.flush().unwrap();
+            (Some(dir), path)
+        }
+    };
+
+    let config = InferenceConfig::new(0, true);
+    let mut group = c.benchmark_group("infer_file_full_scan");
+    group.sample_size(10);
+
+    for mmap in [false, true] {
+        let name = if mmap { "mmap" } else { "read" };
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let options = CsvOptions { mmap, ..Default::default() };
+                let mut parser = CsvParser::from_path_with(&path, options).unwrap();
+                parser.infer_schema("bench".to_string(), &config).unwrap()
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_inference, bench_file_scan);
+criterion_main!(benches);
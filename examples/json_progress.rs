@@ -0,0 +1,52 @@
+//! Load a CSV file, reporting progress as JSON lines on stderr
+//!
+//! Usage: cargo run --example json_progress -- <CSV_FILE> <CONNECTION_STRING>
+
+use csv_sql_loader::progress::ProgressSink;
+use csv_sql_loader::{LoadStats, LoaderBuilder, LoaderError};
+use serde_json::json;
+use std::sync::Arc;
+
+struct JsonLines;
+
+impl ProgressSink for JsonLines {
+    fn on_batch(&self, rows_loaded: u64, total: Option<u64>) {
+        eprintln!("{}", json!({ "event": "batch", "rows": rows_loaded, "total": total }));
+    }
+
+    fn on_finish(&self, stats: &LoadStats) {
+        eprintln!(
+            "{}",
+            json!({
+                "event": "finish",
+                "rows": stats.rows_loaded,
+                "rejected": stats.rows_rejected,
+                "elapsed_s": stats.elapsed.as_secs_f64(),
+                "rows_per_sec": stats.throughput,
+            })
+        );
+    }
+
+    fn on_error(&self, error: &LoaderError) {
+        eprintln!("{}", json!({ "event": "error", "message": error.to_string() }));
+    }
+}
+
+#[tokio::main]
+async fn main() -> csv_sql_loader::Result<()> {
+    let mut args = std::env::args().skip(1);
+    let (Some(csv_file), Some(connection_string)) = (args.next(), args.next()) else {
+        eprintln!("usage: json_progress <CSV_FILE> <CONNECTION_STRING>");
+        std::process::exit(2);
+    };
+
+    LoaderBuilder::new(connection_string)
+        .create_table(true)
+        .count_rows(true)
+        .progress(Arc::new(JsonLines))
+        .build()?
+        .load(csv_file)
+        .await?;
+
+    Ok(())
+}
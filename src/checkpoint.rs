@@ -0,0 +1,128 @@
+//! Restart point for long, append-style loads
+//!
+//! The checkpoint counts CSV data records, not lines: a quoted field that
+//! spans several lines is still one record, and skipping on resume goes
+//! through the CSV reader so it lands on the same record boundary. Rows
+//! rejected in skip mode count as done, since they went to the error file.
+
+use crate::errors::{LoaderError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct State {
+    rows: u64,
+}
+
+/// Tracks the rows committed in file order and saves them after each batch
+///
+/// Parallel workers finish batches out of order, so only the unbroken run
+/// of finished rows from the start is recorded.
+#[derive(Debug)]
+pub struct Checkpoint {
+    path: PathBuf,
+    rows: u64,
+    /// Finished row ranges past the first gap, keyed by start
+    pending: BTreeMap<u64, u64>,
+}
+
+impl Checkpoint {
+    /// Read the checkpoint at `path`, starting from zero if it doesn't exist
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let rows = match fs::read_to_string(path) {
+            Ok(json) => {
+                let state: State = serde_json::from_str(&json).map_err(|e| {
+                    LoaderError::ConfigError(format!("Invalid checkpoint file {}: {}", path.display(), e))
+                })?;
+                state.rows
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => 0,
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            rows,
+            pending: BTreeMap::new(),
+        })
+    }
+
+    /// Data rows committed so far, counted from the start of the input
+    pub fn rows(&self) -> u64 {
+        self.rows
+    }
+
+    /// Record a committed batch covering `rows`, saving if the committed
+    /// run grew
+    pub fn commit(&mut self, rows: Range<u64>) -> Result<()> {
+        self.pending.insert(rows.start, rows.end);
+
+        let before = self.rows;
+        while let Some(end) = self.pending.remove(&self.rows) {
+            self.rows = end;
+        }
+
+        if self.rows != before {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// Write the checkpoint, replacing the old one in a single rename
+    fn save(&self) -> Result<()> {
+        let json = serde_json::to_string(&State { rows: self.rows })
+            .expect("checkpoint state serializes");
+        let tmp = self.path.with_extension("tmp");
+        fs::write(&tmp, json)?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+
+    /// Delete the checkpoint once the load is complete
+    pub fn remove(self) -> Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_checkpoint_commits_in_order() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("state.json");
+
+        let mut checkpoint = Checkpoint::open(&path).unwrap();
+        assert_eq!(checkpoint.rows(), 0);
+
+        // A later batch finishing first isn't recorded until the gap fills
+        checkpoint.commit(10..20).unwrap();
+        assert_eq!(checkpoint.rows(), 0);
+        assert!(!path.exists());
+
+        checkpoint.commit(0..10).unwrap();
+        assert_eq!(checkpoint.rows(), 20);
+        assert_eq!(Checkpoint::open(&path).unwrap().rows(), 20);
+
+        checkpoint.remove().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_invalid_checkpoint() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        fs::write(&path, "{\"lines\": 3}").unwrap();
+
+        assert!(Checkpoint::open(&path).is_err());
+    }
+}
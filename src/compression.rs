@@ -0,0 +1,160 @@
+//! Transparent decompression of gzip, zstd and bzip2 input
+//!
+//! The codec comes from the file extension, falling back to the leading
+//! magic bytes, unless one is forced with `--compression`.
+
+use crate::errors::{LoaderError, Result};
+use bzip2::read::MultiBzDecoder;
+use flate2::read::MultiGzDecoder;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+/// Followed by the block size, '1' to '9'
+const BZIP2_MAGIC: &[u8] = b"BZh";
+
+/// Compression of an input file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum Compression {
+    /// Pick by extension, then by magic bytes
+    #[default]
+    Auto,
+    /// Read the file as-is
+    None,
+    Gzip,
+    Zstd,
+    Bz2,
+}
+
+impl Compression {
+    /// Codec named by a `.gz`, `.zst` or `.bz2` extension
+    pub fn from_extension(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "gz" | "gzip" => Some(Compression::Gzip),
+            "zst" | "zstd" => Some(Compression::Zstd),
+            "bz2" => Some(Compression::Bz2),
+            _ => None,
+        }
+    }
+
+    /// Codec whose magic bytes start `head`, [`Compression::None`] for
+    /// plain data
+    pub fn from_magic(head: &[u8]) -> Self {
+        if head.starts_with(GZIP_MAGIC) {
+            Compression::Gzip
+        } else if head.starts_with(ZSTD_MAGIC) {
+            Compression::Zstd
+        } else if head.starts_with(BZIP2_MAGIC) && head.get(3).is_some_and(|b| (b'1'..=b'9').contains(b)) {
+            Compression::Bz2
+        } else {
+            Compression::None
+        }
+    }
+}
+
+/// Whether `path` is read through a decoder, peeking at its first bytes if
+/// the extension doesn't say
+pub fn is_compressed(path: &Path, compression: Compression) -> Result<bool> {
+    Ok(match compression {
+        Compression::None => false,
+        Compression::Auto if Compression::from_extension(path).is_none() => {
+            let file = File::open(path).map_err(|_| LoaderError::FileNotFound(path.display().to_string()))?;
+            let mut head = Vec::with_capacity(4);
+            file.take(4).read_to_end(&mut head)?;
+            Compression::from_magic(&head) != Compression::None
+        }
+        _ => true,
+    })
+}
+
+/// Open a file, decompressing it as `compression` says
+pub fn open<P: AsRef<Path>>(path: P, compression: Compression) -> Result<Box<dyn Read + Send>> {
+    let path = path.as_ref();
+    let file = File::open(path).map_err(|_| LoaderError::FileNotFound(path.display().to_string()))?;
+    let mut file = BufReader::new(file);
+
+    let compression = match compression {
+        Compression::Auto => match Compression::from_extension(path) {
+            Some(compression) => compression,
+            None => Compression::from_magic(file.fill_buf()?),
+        },
+        compression => compression,
+    };
+
+    decoder(file, compression)
+}
+
+/// Wrap `reader` in the decoder for `compression`
+pub fn decoder<R: BufRead + Send + 'static>(reader: R, compression: Compression) -> Result<Box<dyn Read + Send>> {
+    Ok(match compression {
+        Compression::Auto | Compression::None => Box::new(reader),
+        Compression::Gzip => Box::new(MultiGzDecoder::new(reader)),
+        Compression::Zstd => Box::new(zstd::Decoder::with_buffer(reader)?),
+        Compression::Bz2 => Box::new(MultiBzDecoder::new(reader)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    const DATA: &str = "id,name\n1,alice\n2,bob\n";
+
+    fn compress(compression: Compression) -> Vec<u8> {
+        match compression {
+            Compression::Gzip => {
+                let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(DATA.as_bytes()).unwrap();
+                encoder.finish().unwrap()
+            }
+            Compression::Zstd => zstd::encode_all(DATA.as_bytes(), 0).unwrap(),
+            Compression::Bz2 => {
+                let mut encoder = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+                encoder.write_all(DATA.as_bytes()).unwrap();
+                encoder.finish().unwrap()
+            }
+            _ => DATA.as_bytes().to_vec(),
+        }
+    }
+
+    fn read_all(mut reader: Box<dyn Read + Send>) -> String {
+        let mut text = String::new();
+        reader.read_to_string(&mut text).unwrap();
+        text
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        for (compression, extension) in [
+            (Compression::Gzip, "gz"),
+            (Compression::Zstd, "zst"),
+            (Compression::Bz2, "bz2"),
+            (Compression::None, "csv"),
+        ] {
+            let named = dir.path().join(format!("data.csv.{}", extension));
+            let bare = dir.path().join(format!("data_{}", extension));
+            std::fs::write(&named, compress(compression)).unwrap();
+            std::fs::write(&bare, compress(compression)).unwrap();
+
+            // By extension, by magic bytes, and forced
+            assert_eq!(read_all(open(&named, Compression::Auto).unwrap()), DATA, "{:?}", compression);
+            assert_eq!(read_all(open(&bare, Compression::Auto).unwrap()), DATA, "{:?}", compression);
+            assert_eq!(read_all(open(&bare, compression).unwrap()), DATA, "{:?}", compression);
+        }
+    }
+
+    #[test]
+    fn test_detection() {
+        assert_eq!(Compression::from_extension(Path::new("a.CSV.GZ")), Some(Compression::Gzip));
+        assert_eq!(Compression::from_extension(Path::new("a.csv")), None);
+        assert_eq!(Compression::from_magic(b"BZh91AY"), Compression::Bz2);
+        assert_eq!(Compression::from_magic(b"BZhours,x"), Compression::None);
+        assert_eq!(Compression::from_magic(b"id,name"), Compression::None);
+        assert_eq!(Compression::from_magic(b""), Compression::None);
+    }
+}
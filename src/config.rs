@@ -0,0 +1,236 @@
+//! Loader settings read from a TOML file
+//!
+//! Keys mirror the long command-line flags with dashes replaced by
+//! underscores; a few repeatable flags also accept a plural spelling.
+//! Every key is optional; flags given on the command line take precedence.
+
+use crate::errors::{LoaderError, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Settings from a `--config` file
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub table: Option<String>,
+    pub dsn_env: Option<String>,
+    pub connect_timeout: Option<u64>,
+    pub connect_retries: Option<usize>,
+    pub statement_timeout: Option<u64>,
+    pub app_name: Option<String>,
+    pub search_path: Option<Vec<String>>,
+    pub batch_size: Option<usize>,
+    pub batch_bytes: Option<usize>,
+    pub sample_size: Option<usize>,
+    pub sample_strategy: Option<String>,
+    pub buffer_sample: Option<bool>,
+    pub max_int_type: Option<String>,
+    pub delimiter: Option<String>,
+    pub fixed_width: Option<String>,
+    pub no_header: Option<bool>,
+    pub dedup_headers: Option<bool>,
+    pub skip_rows: Option<usize>,
+    pub limit: Option<u64>,
+    pub comment_char: Option<char>,
+    pub quote_char: Option<char>,
+    pub escape_char: Option<char>,
+    pub encoding: Option<String>,
+    pub compression: Option<String>,
+    pub mmap: Option<bool>,
+    pub trim: Option<bool>,
+    pub skip_blank_rows: Option<bool>,
+    pub flexible: Option<bool>,
+    pub on_extra_fields: Option<String>,
+    pub include_columns: Option<Vec<String>>,
+    pub exclude_columns: Option<Vec<String>>,
+    pub lowercase: Option<Vec<String>>,
+    pub uppercase: Option<Vec<String>>,
+    #[serde(alias = "null_token")]
+    pub null_tokens: Option<Vec<String>>,
+    pub null_marker: Option<String>,
+    pub bool_true: Option<Vec<String>>,
+    pub bool_false: Option<Vec<String>>,
+    #[serde(alias = "date_format")]
+    pub date_formats: Option<Vec<String>>,
+    #[serde(alias = "timestamp_format")]
+    pub timestamp_formats: Option<Vec<String>>,
+    pub keep_leading_zeros: Option<bool>,
+    pub decimal_separator: Option<char>,
+    pub thousands_separator: Option<char>,
+    pub no_inet: Option<bool>,
+    pub no_hex: Option<bool>,
+    pub reject_nonfinite: Option<bool>,
+    pub profile: Option<bool>,
+    pub strip_symbols: Option<bool>,
+    pub percent_as_fraction: Option<bool>,
+    pub create_table: Option<bool>,
+    pub strict_existence: Option<bool>,
+    pub swap: Option<bool>,
+    pub rename_to: Option<String>,
+    pub drop_table: Option<bool>,
+    pub truncate: Option<bool>,
+    pub restart_identity: Option<bool>,
+    pub parallelism: Option<usize>,
+    pub channel_depth: Option<u64>,
+    pub copy_format: Option<String>,
+    pub copy_wire_format: Option<String>,
+    pub method: Option<String>,
+    pub atomic: Option<bool>,
+    pub atomic_with_savepoints: Option<bool>,
+    pub freeze: Option<bool>,
+    pub union_schema: Option<bool>,
+    pub force_text: Option<bool>,
+    pub max_retries: Option<usize>,
+    pub retry_jitter: Option<String>,
+    pub on_error: Option<String>,
+    pub max_errors: Option<usize>,
+    pub isolate_errors: Option<bool>,
+    pub error_file: Option<PathBuf>,
+    pub checkpoint_file: Option<PathBuf>,
+    pub ledger_table: Option<String>,
+    pub on_duplicate_file: Option<String>,
+    pub ledger_cheap: Option<bool>,
+    pub force: Option<bool>,
+    pub sanitize_columns: Option<bool>,
+    pub no_quote_identifiers: Option<bool>,
+    pub varchar: Option<bool>,
+    pub varchar_padding: Option<f64>,
+    pub detect_pk: Option<bool>,
+    pub pk_column: Option<String>,
+    pub all_nullable: Option<bool>,
+    pub no_nulls: Option<bool>,
+    pub verify_count: Option<bool>,
+    pub index: Option<Vec<String>>,
+    pub index_detect: Option<bool>,
+    pub check_db: Option<bool>,
+    pub metrics_file: Option<PathBuf>,
+    pub strict_types: Option<bool>,
+    pub use_table_order: Option<bool>,
+    pub target_columns: Option<Vec<String>>,
+    pub map_file: Option<PathBuf>,
+    pub schema_in: Option<PathBuf>,
+    pub schema_out: Option<PathBuf>,
+    pub ddl_out: Option<PathBuf>,
+    pub report: Option<PathBuf>,
+    pub min_confidence: Option<f64>,
+    pub strict: Option<bool>,
+    pub count_rows: Option<bool>,
+    pub progress_format: Option<String>,
+    /// Column name to SQL type overrides
+    #[serde(default, alias = "column_type")]
+    pub column_types: BTreeMap<String, String>,
+    /// Value transforms by column, comma-separated
+    #[serde(default)]
+    pub transform: BTreeMap<String, String>,
+    /// Type names columns are bound as with `method = "insert"`
+    #[serde(default)]
+    pub bind_type: BTreeMap<String, String>,
+    /// CSV column to table column mappings
+    #[serde(default)]
+    pub map: BTreeMap<String, String>,
+    /// CSV column renames
+    #[serde(default)]
+    pub rename: BTreeMap<String, String>,
+    /// Column DEFAULT values for a created table
+    #[serde(default, alias = "default")]
+    pub defaults: BTreeMap<String, String>,
+    /// Labels added to every metric
+    #[serde(default)]
+    pub metrics_labels: BTreeMap<String, String>,
+    /// Extra connection parameters
+    #[serde(default, alias = "conn_option")]
+    pub conn_options: BTreeMap<String, String>,
+}
+
+impl Config {
+    /// Read and parse a config file
+    ///
+    /// Unknown keys are rejected so typos don't go unnoticed.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path).map_err(|_| {
+            LoaderError::FileNotFound(path.display().to_string())
+        })?;
+
+        toml::from_str(&contents).map_err(|e| {
+            LoaderError::ConfigError(format!("{}: {}", path.display(), e.to_string().trim_end()))
+        })
+    }
+}
+
+/// Parse a config value naming a command-line enum variant
+pub fn parse_enum<T: clap::ValueEnum>(key: &str, value: &str) -> Result<T> {
+    T::from_str(value, true).map_err(|_| {
+        let allowed: Vec<String> = T::value_variants()
+            .iter()
+            .filter_map(|v| v.to_possible_value().map(|p| p.get_name().to_string()))
+            .collect();
+        LoaderError::ConfigError(format!(
+            "Invalid {} '{}' in config (expected one of: {})",
+            key,
+            value,
+            allowed.join(", ")
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::CopyFormat;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn write_config(contents: &str) -> NamedTempFile {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(contents.as_bytes()).unwrap();
+        file
+    }
+
+    #[test]
+    fn test_config_from_file() {
+        let file = write_config(r#"
+            table = "events"
+            batch_size = 50000
+            null_tokens = ["NA", "-"]
+
+            [column_types]
+            zip = "TEXT"
+
+            [map]
+            "Full Name" = "name"
+        "#);
+
+        let config = Config::from_file(file.path()).unwrap();
+        assert_eq!(config.table.as_deref(), Some("events"));
+        assert_eq!(config.batch_size, Some(50000));
+        assert_eq!(config.null_tokens.unwrap(), vec!["NA", "-"]);
+        assert_eq!(config.column_types["zip"], "TEXT");
+        assert_eq!(config.map["Full Name"], "name");
+        assert_eq!(config.sample_size, None);
+    }
+
+    #[test]
+    fn test_config_unknown_key() {
+        let file = write_config("table = \"t\"\nbatchsize = 10\n");
+
+        let err = Config::from_file(file.path()).unwrap_err().to_string();
+        assert!(err.contains("unknown field `batchsize`"), "{}", err);
+        assert!(err.contains("line 2"), "{}", err);
+    }
+
+    #[test]
+    fn test_config_missing_file() {
+        let err = Config::from_file("/nonexistent/loader.toml").unwrap_err();
+        assert!(matches!(err, LoaderError::FileNotFound(_)));
+    }
+
+    #[test]
+    fn test_parse_enum() {
+        assert_eq!(parse_enum::<CopyFormat>("copy_format", "Binary").unwrap(), CopyFormat::Binary);
+        let err = parse_enum::<CopyFormat>("copy_format", "csv").unwrap_err().to_string();
+        assert!(err.contains("text, binary"), "{}", err);
+    }
+}
@@ -2,102 +2,575 @@
 
 use crate::errors::{LoaderError, Result};
 use crate::db::CopyLoader;
-use std::time::Duration;
-use tokio::time::sleep;
+use crate::db::connection::with_savepoint;
+use crate::db::retry::{is_retryable, Backoff};
+use crate::reject::RejectedRow;
+use futures_util::future::try_join_all;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::future::Future;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+pub use crate::db::retry::RetryJitter;
+
+/// Shared flag asking a load to stop once the batches in flight finish
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// What to do when a batch fails after all retries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum OnError {
+    /// Abort the whole load
+    #[default]
+    Abort,
+    /// Load the good rows of the batch and reject the failing ones
+    Skip,
+}
 
 /// Batch processor configuration
 #[derive(Debug, Clone)]
 pub struct BatchConfig {
-    #[allow(dead_code)]
     pub batch_size: usize,
+    /// Also end a batch once its fields add up to this many bytes
+    pub batch_bytes: Option<usize>,
     pub max_retries: usize,
     pub initial_backoff: Duration,
     pub max_backoff: Duration,
+    pub retry_jitter: RetryJitter,
+    pub on_error: OnError,
+    /// Maximum rejected rows tolerated in skip mode before aborting
+    pub max_errors: usize,
+    /// Wrap each load in a savepoint so a failure doesn't abort the
+    /// enclosing transaction
+    pub savepoints: bool,
+    /// When a batch fails, find the offending row and put its line and
+    /// contents in the error
+    pub isolate_errors: bool,
+    /// Log each batch's size and load time, and keep the times for
+    /// [`BatchProcessor::timings`]
+    pub timings: bool,
 }
 
 impl Default for BatchConfig {
     fn default() -> Self {
         Self {
             batch_size: 10_000,
+            batch_bytes: None,
             max_retries: 3,
             initial_backoff: Duration::from_secs(1),
             max_backoff: Duration::from_secs(60),
+            retry_jitter: RetryJitter::None,
+            on_error: OnError::Abort,
+            max_errors: 1000,
+            savepoints: false,
+            isolate_errors: false,
+            timings: false,
+        }
+    }
+}
+
+impl BatchConfig {
+    /// Retry limit and backoff for failed batches
+    pub fn backoff(&self) -> Backoff {
+        Backoff {
+            max_retries: self.max_retries,
+            initial: self.initial_backoff,
+            max: self.max_backoff,
+            jitter: self.retry_jitter,
+        }
+    }
+}
+
+/// Rows to load together, with the source line of each row
+#[derive(Debug, Default)]
+pub struct Batch {
+    pub rows: Vec<Vec<String>>,
+    pub lines: Vec<u64>,
+    /// Number of rows in the batches before this one
+    pub first_row: u64,
+}
+
+impl Batch {
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Positions of this batch's rows among all rows batched
+    pub fn row_range(&self) -> Range<u64> {
+        self.first_row..self.first_row + self.len() as u64
+    }
+
+    /// Field contents plus a separator per field, as counted for
+    /// `batch_bytes`
+    pub fn bytes(&self) -> usize {
+        self.rows.iter().map(|row| row.iter().map(|field| field.len() + 1).sum::<usize>()).sum()
+    }
+}
+
+/// Sizes and load times of completed batches
+#[derive(Debug, Clone, Default)]
+pub struct BatchTimings {
+    latencies: Vec<Duration>,
+    rows: u64,
+    bytes: u64,
+}
+
+impl BatchTimings {
+    pub fn record(&mut self, rows: usize, bytes: usize, elapsed: Duration) {
+        self.latencies.push(elapsed);
+        self.rows += rows as u64;
+        self.bytes += bytes as u64;
+    }
+
+    pub fn len(&self) -> usize {
+        self.latencies.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.latencies.is_empty()
+    }
+
+    /// Nearest-rank percentile of batch load times, `p` from 0 to 100
+    pub fn percentile(&self, p: f64) -> Option<Duration> {
+        let mut sorted = self.latencies.clone();
+        sorted.sort();
+        let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+        sorted.get(rank.clamp(1, sorted.len().max(1)) - 1).copied()
+    }
+
+    /// One-line summary, e.g. for the end of a verbose load
+    pub fn summary(&self) -> String {
+        if self.is_empty() {
+            return "No batches timed".to_string();
         }
+        let count = self.len() as u64;
+        let p = |p: f64| self.percentile(p).unwrap_or_default();
+        format!(
+            "Batch load times over {} batches (avg {} rows, {} bytes): p50 {:.1?}, p95 {:.1?}, p99 {:.1?}, max {:.1?}",
+            count,
+            self.rows / count,
+            self.bytes / count,
+            p(50.0),
+            p(95.0),
+            p(99.0),
+            p(100.0)
+        )
     }
 }
 
+/// Result of processing a single batch
+#[derive(Debug, Default)]
+pub struct BatchOutcome {
+    pub loaded: u64,
+    pub rejected: Vec<RejectedRow>,
+}
+
 /// Batch processor
 pub struct BatchProcessor {
     config: BatchConfig,
+    rejected_total: AtomicUsize,
+    retries_total: AtomicUsize,
+    timings: Mutex<BatchTimings>,
+    cancel: CancelToken,
+    /// Source of retry jitter
+    rng: Mutex<StdRng>,
 }
 
 impl BatchProcessor {
     pub fn new(config: BatchConfig) -> Self {
-        Self { config }
+        Self::with_rng(config, StdRng::from_entropy())
     }
 
-    /// Process a batch with retry logic
-    pub async fn process_batch(
+    /// Create a processor drawing retry jitter from `rng`
+    pub fn with_rng(config: BatchConfig, rng: StdRng) -> Self {
+        Self {
+            config,
+            rejected_total: AtomicUsize::new(0),
+            retries_total: AtomicUsize::new(0),
+            timings: Mutex::new(BatchTimings::default()),
+            cancel: CancelToken::new(),
+            rng: Mutex::new(rng),
+        }
+    }
+
+    /// Stop taking new batches once `cancel` is triggered
+    pub fn with_cancel(mut self, cancel: CancelToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// Total rows rejected so far
+    pub fn rejected_total(&self) -> usize {
+        self.rejected_total.load(Ordering::Relaxed)
+    }
+
+    /// Count `count` rows rejected before reaching the processor, such as
+    /// lines the parser dropped, against `max_errors`
+    pub fn count_rejected(&self, count: usize) -> Result<()> {
+        let rejected = self.rejected_total.fetch_add(count, Ordering::Relaxed) + count;
+        if count > 0 && rejected > self.config.max_errors {
+            return Err(LoaderError::TooManyErrors {
+                rejected,
+                max: self.config.max_errors,
+            });
+        }
+        Ok(())
+    }
+
+    /// Total retries after transient failures so far
+    pub fn retries_total(&self) -> usize {
+        self.retries_total.load(Ordering::Relaxed)
+    }
+
+    /// Load times of the batches completed so far, kept with `timings`
+    pub fn timings(&self) -> BatchTimings {
+        self.timings.lock().unwrap().clone()
+    }
+
+    /// Process batches concurrently, one worker per loader
+    ///
+    /// Batches arrive on a bounded channel fed by a producer (usually
+    /// `BatchIterator::feed` on a parser thread), so parsing overlaps with
+    /// loading. `on_outcome` is called with the batch's row range and outcome
+    /// as each batch completes. The first error from either the producer or a
+    /// worker stops all remaining work and is returned; dropping the receiver
+    /// tells the producer to stop.
+    pub async fn process_parallel<F>(
         &self,
-        loader: &CopyLoader<'_>,
-        batch: Vec<Vec<String>>,
-    ) -> Result<u64> {
-        let mut retries = 0;
-        let mut backoff = self.config.initial_backoff;
-
-        loop {
-            match loader.load_batch(&batch).await {
-                Ok(count) => return Ok(count),
-                Err(e) => {
-                    if retries >= self.config.max_retries {
-                        return Err(LoaderError::BatchError {
-                            retries,
-                            message: e.to_string(),
-                        });
-                    }
+        loaders: &[CopyLoader<'_>],
+        batches: mpsc::Receiver<Result<Batch>>,
+        on_outcome: F,
+    ) -> Result<()>
+    where
+        F: FnMut(Range<u64>, BatchOutcome) -> Result<()>,
+    {
+        let rx = tokio::sync::Mutex::new(batches);
+        let on_outcome = Mutex::new(on_outcome);
+
+        let workers = loaders.iter().map(|loader| async {
+            loop {
+                let next = rx.lock().await.recv().await;
+                let Some(batch) = next.filter(|_| !self.cancel.is_cancelled()) else {
+                    return Ok::<(), LoaderError>(());
+                };
+
+                let batch = batch?;
+                let rows = batch.row_range();
+                let outcome = self.process_batch(loader, batch).await?;
+                (on_outcome.lock().unwrap())(rows, outcome)?;
+            }
+        });
+
+        try_join_all(workers).await?;
+        Ok(())
+    }
+
+    /// Process a batch, falling back to row-by-row loading in skip mode
+    ///
+    /// With `timings`, the time taken, including retries and any
+    /// row-by-row fallback, is logged and recorded.
+    pub async fn process_batch(&self, loader: &CopyLoader<'_>, batch: Batch) -> Result<BatchOutcome> {
+        if !self.config.timings {
+            return self.run_batch(loader, batch).await;
+        }
 
-                    tracing::warn!(
-                        "Batch failed (attempt {}/{}): {}. Retrying in {:?}...",
-                        retries + 1,
-                        self.config.max_retries,
-                        e,
-                        backoff
-                    );
+        let (rows, bytes, first_line) = (batch.len(), batch.bytes(), batch.lines.first().copied());
+        let started = Instant::now();
+        let outcome = self.run_batch(loader, batch).await?;
+        let elapsed = started.elapsed();
 
-                    sleep(backoff).await;
+        tracing::debug!(
+            "Batch of {} rows ({} bytes) from line {} loaded in {:.1?}",
+            rows,
+            bytes,
+            first_line.unwrap_or_default(),
+            elapsed
+        );
+        self.timings.lock().unwrap().record(rows, bytes, elapsed);
+        Ok(outcome)
+    }
 
-                    retries += 1;
-                    backoff = std::cmp::min(backoff * 2, self.config.max_backoff);
+    async fn run_batch(&self, loader: &CopyLoader<'_>, batch: Batch) -> Result<BatchOutcome> {
+        match self.load_with_retry(loader, &batch).await {
+            Ok(loaded) => Ok(BatchOutcome {
+                loaded,
+                rejected: Vec::new(),
+            }),
+            Err(e) if self.config.on_error == OnError::Skip => {
+                tracing::warn!("Batch failed ({}), retrying row by row", e);
+                self.load_row_by_row(loader, batch).await
+            }
+            Err(e) if self.config.isolate_errors => Err(self.isolate_failure(loader, &batch, e).await),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Load each row individually, collecting the ones that fail
+    async fn load_row_by_row(&self, loader: &CopyLoader<'_>, batch: Batch) -> Result<BatchOutcome> {
+        let mut outcome = BatchOutcome::default();
+
+        for (row, line) in batch.rows.into_iter().zip(batch.lines) {
+            match self.load(loader, std::slice::from_ref(&row)).await {
+                Ok(count) => outcome.loaded += count,
+                Err(e) => {
+                    self.count_rejected(1)?;
+                    outcome.rejected.push(RejectedRow {
+                        line,
+                        row,
+                        error: e.to_string(),
+                    });
                 }
             }
         }
+
+        Ok(outcome)
+    }
+
+    /// Point a permanent batch error at the row that caused it
+    ///
+    /// The server names the row for text COPY; otherwise the batch is
+    /// bisected with trial loads that are always rolled back, so nothing
+    /// from the failed batch stays loaded.
+    async fn isolate_failure(&self, loader: &CopyLoader<'_>, batch: &Batch, error: LoaderError) -> LoaderError {
+        let (known_line, message) = match &error {
+            LoaderError::AtLine { line, source } => match source.as_ref() {
+                LoaderError::PermanentBatchError(message) => (Some(*line), message.clone()),
+                _ => return error,
+            },
+            LoaderError::PermanentBatchError(message) => (None, message.clone()),
+            _ => return error,
+        };
+
+        let index = match known_line {
+            Some(line) => batch.lines.iter().position(|&l| l == line),
+            None => {
+                tracing::info!("Isolating the failing row among {} rows", batch.len());
+                self.bisect(loader, &batch.rows).await
+            }
+        };
+        let Some(index) = index else {
+            return error;
+        };
+
+        LoaderError::PermanentBatchError(format!("{}; row: {:?}", message, batch.rows[index]))
+            .at_line(batch.lines[index])
+    }
+
+    /// Index of the first row that fails to load, if a single row does
+    ///
+    /// Returns `None` when rows only fail together, e.g. duplicate keys
+    /// within the batch.
+    async fn bisect(&self, loader: &CopyLoader<'_>, rows: &[Vec<String>]) -> Option<usize> {
+        // rows[lo..hi] is known to fail
+        let (mut lo, mut hi) = (0, rows.len());
+        while hi - lo > 1 {
+            let mid = lo + (hi - lo) / 2;
+            if !self.trial_load(loader, &rows[lo..mid]).await {
+                hi = mid;
+            } else if !self.trial_load(loader, &rows[mid..hi]).await {
+                lo = mid;
+            } else {
+                return None;
+            }
+        }
+        (hi > lo).then_some(lo)
+    }
+
+    /// Whether `rows` would load, rolling the attempt back either way
+    async fn trial_load(&self, loader: &CopyLoader<'_>, rows: &[Vec<String>]) -> bool {
+        let (begin, rollback) = if self.config.savepoints {
+            ("SAVEPOINT isolate_row", "ROLLBACK TO SAVEPOINT isolate_row; RELEASE SAVEPOINT isolate_row")
+        } else {
+            ("BEGIN", "ROLLBACK")
+        };
+        if loader.client().batch_execute(begin).await.is_err() {
+            return false;
+        }
+        let loaded = loader.load_batch(rows).await.is_ok();
+        let _ = loader.client().batch_execute(rollback).await;
+        loaded
+    }
+
+    /// Load a batch, retrying with exponential backoff
+    ///
+    /// The final error carries the source line of the failing row when the
+    /// server points at one.
+    async fn load_with_retry(&self, loader: &CopyLoader<'_>, batch: &Batch) -> Result<u64> {
+        self.retry(|| self.load(loader, &batch.rows)).await.map_err(|(retries, e)| {
+            let line = copy_error_line(&e, &batch.lines);
+            let error = match e {
+                // Already says what went wrong
+                LoaderError::Timeout(_) => e,
+                e if is_retryable(&e) => LoaderError::BatchError {
+                    retries,
+                    message: e.to_string(),
+                },
+                e => LoaderError::PermanentBatchError(e.to_string()),
+            };
+            match line {
+                Some(line) => error.at_line(line),
+                None => error,
+            }
+        })
+    }
+
+    /// COPY rows, inside a savepoint when configured
+    async fn load(&self, loader: &CopyLoader<'_>, rows: &[Vec<String>]) -> Result<u64> {
+        if self.config.savepoints {
+            with_savepoint(loader.client(), || loader.load_batch(rows)).await
+        } else {
+            loader.load_batch(rows).await
+        }
+    }
+
+    /// Run `attempt` until it succeeds, fails permanently, or the retries
+    /// run out
+    ///
+    /// On failure, returns the number of retries made and the last error.
+    async fn retry<T, F, Fut>(&self, attempt: F) -> std::result::Result<T, (usize, LoaderError)>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let on_retry = |retry: usize, e: &LoaderError, delay: Duration| {
+            tracing::warn!(
+                "Batch failed (attempt {}/{}): {}. Retrying in {:?}...",
+                retry,
+                self.config.max_retries,
+                e,
+                delay
+            );
+            self.retries_total.fetch_add(1, Ordering::Relaxed);
+        };
+        self.config.backoff().retry(&self.rng, is_retryable, on_retry, attempt).await
     }
 }
 
+/// Source line of the row a failed COPY points at, if the server said
+///
+/// The server reports the 1-based row within the COPY stream in its error
+/// context, e.g. `COPY people, line 3, column id: "abc"`.
+fn copy_error_line(error: &LoaderError, lines: &[u64]) -> Option<u64> {
+    let LoaderError::DatabaseError(e) = error else {
+        return None;
+    };
+    let context = e.as_db_error()?.where_()?;
+    let row: usize = context
+        .split(", line ")
+        .nth(1)?
+        .split(|c: char| !c.is_ascii_digit())
+        .next()?
+        .parse()
+        .ok()?;
+
+    lines.get(row.checked_sub(1)?).copied()
+}
+
 /// Batch iterator - splits records into batches
 pub struct BatchIterator<I> {
     iter: I,
     batch_size: usize,
+    max_bytes: Option<usize>,
+    cancel: CancelToken,
+    /// Rows batched so far
+    rows: u64,
 }
 
 impl<I> BatchIterator<I> {
     pub fn new(iter: I, batch_size: usize) -> Self {
-        Self { iter, batch_size }
+        Self {
+            iter,
+            batch_size,
+            max_bytes: None,
+            cancel: CancelToken::new(),
+            rows: 0,
+        }
+    }
+
+    /// Stop producing batches once `cancel` is triggered
+    pub fn with_cancel(mut self, cancel: CancelToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// End batches early once their rows reach `max_bytes`, so memory stays
+    /// bounded however wide rows are. A single oversized row still forms
+    /// its own batch.
+    pub fn with_max_bytes(mut self, max_bytes: Option<usize>) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+}
+
+impl<I> BatchIterator<I>
+where
+    I: Iterator<Item = Result<(u64, Vec<String>)>>,
+{
+    /// Send every batch into `tx`, blocking while the channel is full
+    ///
+    /// Stops after the first error has been sent or once the receiver is
+    /// dropped. Must be called from outside the async runtime.
+    pub fn feed(self, tx: mpsc::Sender<Result<Batch>>) {
+        for batch in self {
+            let failed = batch.is_err();
+            if tx.blocking_send(batch).is_err() || failed {
+                break;
+            }
+        }
     }
 }
 
 impl<I> Iterator for BatchIterator<I>
 where
-    I: Iterator<Item = Result<Vec<String>>>,
+    I: Iterator<Item = Result<(u64, Vec<String>)>>,
 {
-    type Item = Result<Vec<Vec<String>>>;
+    type Item = Result<Batch>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let mut batch = Vec::with_capacity(self.batch_size);
+        if self.cancel.is_cancelled() {
+            return None;
+        }
+
+        let mut batch = Batch {
+            rows: Vec::with_capacity(self.batch_size),
+            lines: Vec::with_capacity(self.batch_size),
+            first_row: self.rows,
+        };
 
+        let mut bytes = 0;
         for _ in 0..self.batch_size {
             match self.iter.next() {
-                Some(Ok(row)) => batch.push(row),
+                Some(Ok((line, row))) => {
+                    // Field contents plus a separator per field
+                    bytes += row.iter().map(|field| field.len() + 1).sum::<usize>();
+                    batch.rows.push(row);
+                    batch.lines.push(line);
+                    if self.max_bytes.is_some_and(|max| bytes >= max) {
+                        break;
+                    }
+                }
                 Some(Err(e)) => return Some(Err(e)),
                 None => break,
             }
@@ -106,6 +579,7 @@ where
         if batch.is_empty() {
             None
         } else {
+            self.rows += batch.len() as u64;
             Some(Ok(batch))
         }
     }
@@ -117,12 +591,12 @@ mod tests {
 
     #[test]
     fn test_batch_iterator() {
-        let data: Vec<Result<Vec<String>>> = vec![
-            Ok(vec!["1".to_string()]),
-            Ok(vec!["2".to_string()]),
-            Ok(vec!["3".to_string()]),
-            Ok(vec!["4".to_string()]),
-            Ok(vec!["5".to_string()]),
+        let data: Vec<Result<(u64, Vec<String>)>> = vec![
+            Ok((2, vec!["1".to_string()])),
+            Ok((3, vec!["2".to_string()])),
+            Ok((5, vec!["3".to_string()])),
+            Ok((6, vec!["4".to_string()])),
+            Ok((7, vec!["5".to_string()])),
         ];
 
         let mut batches = BatchIterator::new(data.into_iter(), 2);
@@ -132,17 +606,201 @@ mod tests {
 
         let batch2 = batches.next().unwrap().unwrap();
         assert_eq!(batch2.len(), 2);
+        assert_eq!(batch2.lines, vec![5, 6]);
+
+        assert_eq!(batch2.row_range(), 2..4);
 
         let batch3 = batches.next().unwrap().unwrap();
         assert_eq!(batch3.len(), 1);
+        assert_eq!(batch3.row_range(), 4..5);
 
         assert!(batches.next().is_none());
     }
 
+    #[test]
+    fn test_batch_iterator_max_bytes() {
+        let rows = |width: usize, count: u64| -> Vec<Result<(u64, Vec<String>)>> {
+            (0..count).map(|line| Ok((line, vec!["x".repeat(width), "y".to_string()]))).collect()
+        };
+
+        // Narrow rows fill batches up to the row limit
+        let narrow: Vec<usize> = BatchIterator::new(rows(8, 10).into_iter(), 4)
+            .with_max_bytes(Some(1_000))
+            .map(|batch| batch.unwrap().len())
+            .collect();
+        assert_eq!(narrow, vec![4, 4, 2]);
+
+        // Wide rows (1002 bytes each) end batches on size first
+        let wide: Vec<usize> = BatchIterator::new(rows(1_000, 10).into_iter(), 4)
+            .with_max_bytes(Some(2_500))
+            .map(|batch| batch.unwrap().len())
+            .collect();
+        assert_eq!(wide, vec![3, 3, 3, 1]);
+
+        // A row larger than the limit still goes out on its own
+        let oversized: Vec<usize> = BatchIterator::new(rows(5_000, 2).into_iter(), 4)
+            .with_max_bytes(Some(1_000))
+            .map(|batch| batch.unwrap().len())
+            .collect();
+        assert_eq!(oversized, vec![1, 1]);
+    }
+
+    #[test]
+    fn test_batch_iterator_cancel() {
+        let data = (0..10).map(|line| Ok((line, vec![line.to_string()])));
+        let cancel = CancelToken::new();
+        let mut batches = BatchIterator::new(data, 2).with_cancel(cancel.clone());
+
+        assert_eq!(batches.next().unwrap().unwrap().lines, vec![0, 1]);
+        cancel.cancel();
+        assert!(batches.next().is_none());
+    }
+
+    #[test]
+    fn test_feed_stops_after_error() {
+        let data: Vec<Result<(u64, Vec<String>)>> = vec![
+            Ok((2, vec!["1".to_string()])),
+            Err(LoaderError::TypeConversionError("bad row".to_string())),
+            Ok((4, vec!["3".to_string()])),
+        ];
+
+        let (tx, mut rx) = mpsc::channel(1);
+        let producer = std::thread::spawn(move || BatchIterator::new(data.into_iter(), 1).feed(tx));
+
+        assert_eq!(rx.blocking_recv().unwrap().unwrap().lines, vec![2]);
+        assert!(rx.blocking_recv().unwrap().is_err());
+        assert!(rx.blocking_recv().is_none());
+        producer.join().unwrap();
+    }
+
+    #[test]
+    fn test_feed_stops_when_receiver_dropped() {
+        let data = (0..100u64).map(|i| Ok((i, vec![i.to_string()])));
+
+        let (tx, rx) = mpsc::channel(1);
+        let producer = std::thread::spawn(move || BatchIterator::new(data, 1).feed(tx));
+        drop(rx);
+
+        producer.join().unwrap();
+    }
+
+    /// Retry an operation that fails twice, returning the virtual time slept
+    async fn time_two_failures(jitter: RetryJitter, seed: u64) -> Duration {
+        let config = BatchConfig {
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(1),
+            retry_jitter: jitter,
+            ..Default::default()
+        };
+        let processor = BatchProcessor::with_rng(config, StdRng::seed_from_u64(seed));
+        let attempts = AtomicUsize::new(0);
+
+        let start = tokio::time::Instant::now();
+        let result = processor.retry(|| async {
+            if attempts.fetch_add(1, Ordering::Relaxed) < 2 {
+                Err(LoaderError::ConnectionError("reset".to_string()))
+            } else {
+                Ok(())
+            }
+        }).await;
+
+        assert!(result.is_ok());
+        assert_eq!(attempts.load(Ordering::Relaxed), 3);
+        start.elapsed()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retry_jitter_bounds() {
+        // Backoffs are 100ms then 200ms
+        let total = Duration::from_millis(300);
+        assert_eq!(time_two_failures(RetryJitter::None, 0).await, total);
+
+        for seed in 0..20 {
+            let full = time_two_failures(RetryJitter::Full, seed).await;
+            assert!(full <= total, "full jitter slept {:?}", full);
+
+            let equal = time_two_failures(RetryJitter::Equal, seed).await;
+            assert!(equal >= total / 2 && equal <= total, "equal jitter slept {:?}", equal);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_permanent_error_not_retried() {
+        let processor = BatchProcessor::new(BatchConfig::default());
+        let attempts = AtomicUsize::new(0);
+
+        let result: std::result::Result<(), _> = processor.retry(|| async {
+            attempts.fetch_add(1, Ordering::Relaxed);
+            Err(LoaderError::TypeConversionError("bad value".to_string()))
+        }).await;
+
+        assert_eq!(result.unwrap_err().0, 0);
+        assert_eq!(attempts.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_batch_timings() {
+        let mut timings = BatchTimings::default();
+        assert_eq!(timings.percentile(50.0), None);
+        assert_eq!(timings.summary(), "No batches timed");
+
+        for ms in 1..=100 {
+            timings.record(10, 200, Duration::from_millis(ms));
+        }
+        assert_eq!(timings.percentile(50.0), Some(Duration::from_millis(50)));
+        assert_eq!(timings.percentile(99.0), Some(Duration::from_millis(99)));
+        assert_eq!(timings.percentile(0.0), Some(Duration::from_millis(1)));
+        assert_eq!(
+            timings.summary(),
+            "Batch load times over 100 batches (avg 10 rows, 200 bytes): p50 50.0ms, p95 95.0ms, p99 99.0ms, max 100.0ms"
+        );
+
+        let batch = Batch {
+            rows: vec![vec!["ab".to_string(), "".to_string()]],
+            ..Default::default()
+        };
+        assert_eq!(batch.bytes(), 4);
+    }
+
     #[test]
     fn test_default_batch_config() {
         let config = BatchConfig::default();
         assert_eq!(config.batch_size, 10_000);
         assert_eq!(config.max_retries, 3);
+        assert_eq!(config.on_error, OnError::Abort);
+        assert_eq!(config.max_errors, 1000);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_isolate_errors() {
+        use crate::db::copy::CopyOptions;
+        use crate::db::{DbConnection, LoadMethod};
+        use crate::schema::TableSchema;
+
+        let conn = DbConnection::connect("postgresql://localhost/test").await.unwrap();
+        let mut schema = TableSchema::new("isolate_errors_test".to_string(), vec!["n".to_string()]);
+        let rows: Vec<Vec<String>> = ["1", "2", "3", "-4", "5"].iter().map(|v| vec![v.to_string()]).collect();
+        for row in &rows {
+            schema.update_row(row).unwrap();
+        }
+        schema.finalize();
+
+        conn.drop_table(&schema.table_name).await.unwrap();
+        conn.create_table(&schema.to_create_table_sql()).await.unwrap();
+        conn.execute("ALTER TABLE isolate_errors_test ADD CHECK (n > 0)").await.unwrap();
+
+        // INSERT errors don't say which row failed, so this has to bisect
+        let options = CopyOptions { method: LoadMethod::Insert, ..Default::default() };
+        let loader = CopyLoader::with_options(conn.client(), &schema, options);
+        let processor = BatchProcessor::new(BatchConfig { isolate_errors: true, ..Default::default() });
+        let batch = Batch { rows, lines: vec![2, 3, 4, 5, 6], first_row: 0 };
+
+        let err = processor.process_batch(&loader, batch).await.unwrap_err();
+        assert!(matches!(err, LoaderError::AtLine { line: 5, .. }), "{}", err);
+        assert!(err.to_string().contains("row: [\"-4\"]"), "{}", err);
+        assert_eq!(conn.row_count("isolate_errors_test").await.unwrap(), 0);
+
+        conn.drop_table(&schema.table_name).await.unwrap();
     }
 }
@@ -0,0 +1,258 @@
+//! PostgreSQL binary COPY encoding
+//!
+//! Rows are encoded as the binary COPY stream: a fixed header, then each
+//! tuple as a field count followed by length-prefixed field values, and a
+//! `-1` trailer. Values are converted using the column's `SqlType`.
+
+use crate::db::copy::{encode_value, CopyOptions};
+use crate::errors::{LoaderError, Result};
+use crate::types::SqlType;
+use bytes::{BufMut, BytesMut};
+use chrono::{NaiveDate, Timelike};
+use std::net::IpAddr;
+
+/// Binary COPY signature, followed by the flags and extension length fields
+const HEADER: &[u8] = b"PGCOPY\n\xff\r\n\0\0\0\0\0\0\0\0\0";
+
+/// Whether a column type can be sent in binary form
+pub fn supports(sql_type: &SqlType) -> bool {
+    // NUMERIC's binary form is base-10000 digit groups and arrays need
+    // element OIDs; neither is worth it here
+    !matches!(sql_type, SqlType::Numeric | SqlType::Array(_))
+}
+
+/// Whether binary COPY pays off: every type is supported and at least one
+/// column is something other than plain text
+pub fn is_worthwhile(types: &[SqlType]) -> bool {
+    types.iter().all(supports)
+        && types.iter().any(|t| !matches!(t, SqlType::Text | SqlType::Varchar { .. }))
+}
+
+/// Encode rows as a complete binary COPY payload
+pub fn rows_to_binary(rows: &[Vec<String>], types: &[SqlType], options: &CopyOptions) -> Result<BytesMut> {
+    let mut buf = BytesMut::with_capacity(HEADER.len() + rows.len() * types.len() * 8 + 2);
+    buf.put_slice(HEADER);
+
+    for row in rows {
+        if row.len() != types.len() {
+            return Err(LoaderError::TypeConversionError(format!(
+                "Row has {} columns but expected {}",
+                row.len(),
+                types.len()
+            )));
+        }
+
+        buf.put_i16(types.len() as i16);
+        for (value, sql_type) in row.iter().zip(types) {
+            match encode_value(value, sql_type, options) {
+                None => buf.put_i32(-1),
+                Some(value) => encode_field(&mut buf, &value, sql_type, options)?,
+            }
+        }
+    }
+
+    // File trailer
+    buf.put_i16(-1);
+    Ok(buf)
+}
+
+/// Parse a boolean the way Postgres reads boolean input
+///
+/// `encode_value` has already mapped configured tokens to t/f.
+pub(crate) fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "t" | "yes" | "y" | "on" | "1" => Some(true),
+        "false" | "f" | "no" | "n" | "off" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+/// Postgres binary timestamps and dates count from 2000-01-01
+fn pg_epoch() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2000, 1, 1).unwrap()
+}
+
+/// Append one length-prefixed, non-null field
+fn encode_field(buf: &mut BytesMut, value: &str, sql_type: &SqlType, options: &CopyOptions) -> Result<()> {
+    let invalid = || LoaderError::TypeConversionError(format!(
+        "Invalid {} value: {}",
+        sql_type.to_sql(),
+        value
+    ));
+
+    match sql_type {
+        SqlType::Boolean => {
+            let parsed = parse_bool(value).ok_or_else(invalid)?;
+            buf.put_i32(1);
+            buf.put_u8(parsed as u8);
+        }
+        SqlType::SmallInt => {
+            buf.put_i32(2);
+            buf.put_i16(value.parse().map_err(|_| invalid())?);
+        }
+        SqlType::Integer => {
+            buf.put_i32(4);
+            buf.put_i32(value.parse().map_err(|_| invalid())?);
+        }
+        SqlType::BigInt => {
+            buf.put_i32(8);
+            buf.put_i64(value.parse().map_err(|_| invalid())?);
+        }
+        SqlType::Real => {
+            buf.put_i32(4);
+            buf.put_f32(value.parse().map_err(|_| invalid())?);
+        }
+        SqlType::DoublePrecision => {
+            buf.put_i32(8);
+            buf.put_f64(value.parse().map_err(|_| invalid())?);
+        }
+        SqlType::Timestamp => {
+            let parsed = SqlType::parse_timestamp_column(value, &options.date_formats, &options.timestamp_formats)
+                .ok_or_else(invalid)?;
+            let micros = (parsed - pg_epoch().and_hms_opt(0, 0, 0).unwrap())
+                .num_microseconds()
+                .ok_or_else(invalid)?;
+            buf.put_i32(8);
+            buf.put_i64(micros);
+        }
+        SqlType::TimestampTz => {
+            let parsed = SqlType::parse_timestamptz_column(value, &options.date_formats, &options.timestamp_formats)
+                .ok_or_else(invalid)?;
+            let micros = (parsed.naive_utc() - pg_epoch().and_hms_opt(0, 0, 0).unwrap())
+                .num_microseconds()
+                .ok_or_else(invalid)?;
+            buf.put_i32(8);
+            buf.put_i64(micros);
+        }
+        SqlType::Date => {
+            let parsed = SqlType::parse_date(value, &options.date_formats).ok_or_else(invalid)?;
+            buf.put_i32(4);
+            buf.put_i32((parsed - pg_epoch()).num_days() as i32);
+        }
+        SqlType::Time => {
+            let parsed = SqlType::parse_time(value).ok_or_else(invalid)?;
+            let micros = parsed.num_seconds_from_midnight() as i64 * 1_000_000
+                + (parsed.nanosecond() / 1_000) as i64;
+            buf.put_i32(8);
+            buf.put_i64(micros);
+        }
+        SqlType::Inet | SqlType::Cidr => {
+            let (address, prefix, network) = SqlType::parse_inet(value).ok_or_else(invalid)?;
+            let is_cidr = *sql_type == SqlType::Cidr;
+            if is_cidr && !network {
+                return Err(invalid());
+            }
+            // Family (PGSQL_AF_INET/INET6), prefix bits, cidr flag, address length
+            let (family, octets) = match address {
+                IpAddr::V4(v4) => (2, v4.octets().to_vec()),
+                IpAddr::V6(v6) => (3, v6.octets().to_vec()),
+            };
+            buf.put_i32(4 + octets.len() as i32);
+            buf.put_slice(&[family, prefix, is_cidr as u8, octets.len() as u8]);
+            buf.put_slice(&octets);
+        }
+        SqlType::Null | SqlType::Text | SqlType::Varchar { .. } => {
+            buf.put_i32(value.len() as i32);
+            buf.put_slice(value.as_bytes());
+        }
+        SqlType::Numeric | SqlType::Array(_) => return Err(invalid()),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn test_is_worthwhile() {
+        assert!(is_worthwhile(&[SqlType::Integer, SqlType::Text]));
+        assert!(!is_worthwhile(&[SqlType::Text, SqlType::Varchar { len: 8 }]));
+        assert!(!is_worthwhile(&[SqlType::Integer, SqlType::Numeric]));
+        assert!(!is_worthwhile(&[SqlType::Integer, SqlType::Array(Box::new(SqlType::Integer))]));
+    }
+
+    #[test]
+    fn test_encode_header_and_trailer() {
+        let buf = rows_to_binary(&[], &[SqlType::Integer], &CopyOptions::default()).unwrap();
+
+        assert_eq!(&buf[..11], b"PGCOPY\n\xff\r\n\0");
+        assert_eq!(buf.len(), HEADER.len() + 2);
+        assert_eq!(&buf[buf.len() - 2..], &[0xff, 0xff]);
+    }
+
+    #[test]
+    fn test_encode_row() {
+        let types = [SqlType::SmallInt, SqlType::Text, SqlType::Boolean, SqlType::Date];
+        let rows = [row(&["7", "hi", "true", "2000-01-02"])];
+
+        let buf = rows_to_binary(&rows, &types, &CopyOptions::default()).unwrap();
+        let tuple = &buf[HEADER.len()..buf.len() - 2];
+
+        assert_eq!(
+            tuple,
+            &[
+                0, 4, // field count
+                0, 0, 0, 2, 0, 7, // smallint 7
+                0, 0, 0, 2, b'h', b'i', // text
+                0, 0, 0, 1, 1, // true
+                0, 0, 0, 4, 0, 0, 0, 1, // one day after the epoch
+            ]
+        );
+    }
+
+    #[test]
+    fn test_encode_null_and_invalid() {
+        let types = [SqlType::Integer];
+
+        let buf = rows_to_binary(&[row(&[""])], &types, &CopyOptions::default()).unwrap();
+        assert_eq!(&buf[HEADER.len()..buf.len() - 2], &[0, 1, 0xff, 0xff, 0xff, 0xff]);
+
+        assert!(rows_to_binary(&[row(&["abc"])], &types, &CopyOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_encode_timestamp() {
+        let types = [SqlType::Timestamp, SqlType::TimestampTz];
+        let rows = [row(&["2000-01-01 00:00:01", "2000-01-01T01:00:00+01:00"])];
+
+        let buf = rows_to_binary(&rows, &types, &CopyOptions::default()).unwrap();
+        let tuple = &buf[HEADER.len()..buf.len() - 2];
+
+        assert_eq!(&tuple[2..14], &[0, 0, 0, 8, 0, 0, 0, 0, 0, 0x0f, 0x42, 0x40]);
+        assert_eq!(&tuple[14..], &[0, 0, 0, 8, 0, 0, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_encode_widened_timestamps() {
+        let types = [SqlType::Timestamp, SqlType::TimestampTz];
+        let rows = [row(&["2000-01-02", "2000-01-01 00:00:01"])];
+
+        let buf = rows_to_binary(&rows, &types, &CopyOptions::default()).unwrap();
+        let tuple = &buf[HEADER.len()..buf.len() - 2];
+
+        // A date is midnight, and a naive timestamp is UTC
+        assert_eq!(&tuple[2..14], &[0, 0, 0, 8, 0, 0, 0, 0x14, 0x1d, 0xd7, 0x60, 0]);
+        assert_eq!(&tuple[14..], &[0, 0, 0, 8, 0, 0, 0, 0, 0, 0x0f, 0x42, 0x40]);
+    }
+
+    #[test]
+    fn test_encode_inet() {
+        let types = [SqlType::Inet, SqlType::Cidr];
+        let rows = [row(&["192.168.0.1", "10.0.0.0/8"])];
+
+        let buf = rows_to_binary(&rows, &types, &CopyOptions::default()).unwrap();
+        let tuple = &buf[HEADER.len()..buf.len() - 2];
+
+        assert_eq!(&tuple[2..14], &[0, 0, 0, 8, 2, 32, 0, 4, 192, 168, 0, 1]);
+        assert_eq!(&tuple[14..], &[0, 0, 0, 8, 2, 8, 1, 4, 10, 0, 0, 0]);
+
+        // A CIDR value can't have host bits set
+        assert!(rows_to_binary(&[row(&["10.0.0.1/8"])], &[SqlType::Cidr], &CopyOptions::default()).is_err());
+    }
+}
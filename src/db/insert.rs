@@ -0,0 +1,572 @@
+//! Multi-row INSERT loading for roles without COPY privileges
+//!
+//! Each batch becomes one `INSERT ... VALUES (...), (...)` statement with a
+//! bind parameter per cell. The statement is prepared with every parameter
+//! type given, from [`param_type`], so the server never infers one, and
+//! every placeholder carries an explicit cast, so the server converts the
+//! bound value to the real column type on assignment.
+//!
+//! A column can be bound as text and cast to a named type instead, for
+//! types the loader doesn't know, such as domains and enums.
+
+use crate::db::binary::parse_bool;
+use crate::db::copy::{encode_value, CopyOptions};
+use crate::errors::{LoaderError, Result};
+use crate::types::SqlType;
+use tokio_postgres::types::{ToSql, Type};
+use tokio_postgres::Client;
+
+/// Postgres accepts at most this many bind parameters per statement
+pub const MAX_PARAMETERS: usize = 65_535;
+
+type Param = Box<dyn ToSql + Sync + Send>;
+
+/// How one column's values are bound
+#[derive(Debug, Clone, PartialEq)]
+pub struct Binding {
+    /// Type the values are parsed as
+    pub sql_type: SqlType,
+    /// Type name to bind the values as text and cast them to, overriding
+    /// the parameter type `sql_type` binds as
+    pub cast: Option<String>,
+}
+
+impl Binding {
+    pub fn new(sql_type: SqlType) -> Self {
+        Self { sql_type, cast: None }
+    }
+
+    /// Parameter type the statement is prepared with
+    pub fn param_type(&self) -> Type {
+        match &self.cast {
+            Some(_) => Type::TEXT,
+            None => param_type(&self.sql_type),
+        }
+    }
+}
+
+/// Parameter type each column type is bound as
+///
+/// Types tokio-postgres has no Rust type for are bound as text and cast by
+/// the server; see [`placeholder`].
+pub fn param_type(sql_type: &SqlType) -> Type {
+    match sql_type {
+        SqlType::Boolean => Type::BOOL,
+        SqlType::SmallInt => Type::INT2,
+        SqlType::Integer => Type::INT4,
+        SqlType::BigInt => Type::INT8,
+        SqlType::Real => Type::FLOAT4,
+        SqlType::DoublePrecision => Type::FLOAT8,
+        SqlType::Timestamp => Type::TIMESTAMP,
+        SqlType::TimestampTz => Type::TIMESTAMPTZ,
+        SqlType::Date => Type::DATE,
+        SqlType::Time => Type::TIME,
+        _ => Type::TEXT,
+    }
+}
+
+/// Check a type name given to cast a bound column to, which is spliced
+/// into the statement
+///
+/// Names may be schema-qualified, quoted, and carry a `(n)` or `(n, m)`
+/// modifier and array brackets, like `money`, `app.email` or
+/// `varchar(20)[]`. Anything else is refused.
+pub fn validate_cast_type(name: &str) -> Result<()> {
+    if parse_cast_type(name).is_none() {
+        return Err(LoaderError::ConfigError(format!("Invalid type name to bind as: '{}'", name)));
+    }
+    Ok(())
+}
+
+/// Match `[schema.]name[(n[, m])][[]]`, returning `None` if `name` isn't
+/// entirely that
+fn parse_cast_type(name: &str) -> Option<()> {
+    let mut rest = parse_identifier(name)?;
+    if let Some(after) = rest.strip_prefix('.') {
+        rest = parse_identifier(after)?;
+    }
+    if let Some(after) = rest.strip_prefix('(') {
+        rest = parse_modifier(after.trim_start())?;
+        if let Some(after) = rest.strip_prefix(',') {
+            rest = parse_modifier(after.trim_start())?;
+        }
+        rest = rest.strip_prefix(')')?;
+    }
+    let rest = rest.strip_prefix("[]").unwrap_or(rest);
+    rest.is_empty().then_some(())
+}
+
+/// Strip a plain or double-quoted identifier from the front of `s`
+fn parse_identifier(s: &str) -> Option<&str> {
+    if let Some(quoted) = s.strip_prefix('"') {
+        // A doubled quote stands for one inside the name
+        let mut chars = quoted.char_indices().peekable();
+        let mut empty = true;
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' if chars.peek().is_some_and(|&(_, next)| next == '"') => {
+                    chars.next();
+                }
+                '"' if !empty => return Some(&quoted[i + 1..]),
+                '"' => return None,
+                c if c.is_control() => return None,
+                _ => {}
+            }
+            empty = false;
+        }
+        return None;
+    }
+
+    let first = s.chars().next()?;
+    if !(first.is_ascii_alphabetic() || first == '_') {
+        return None;
+    }
+    let end = s.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(s.len());
+    Some(&s[end..])
+}
+
+/// Strip a type modifier number and the spaces after it from the front of `s`
+fn parse_modifier(s: &str) -> Option<&str> {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    if end == 0 {
+        return None;
+    }
+    Some(s[end..].trim_start())
+}
+
+/// Largest batch whose INSERT stays within the parameter limit
+pub fn max_rows(columns: usize) -> usize {
+    (MAX_PARAMETERS / columns.max(1)).max(1)
+}
+
+/// Insert `rows` into `table` with a single statement
+///
+/// `table` and `columns` must already be quoted as needed.
+pub async fn insert_rows(
+    client: &Client,
+    table: &str,
+    columns: &[String],
+    bindings: &[Binding],
+    rows: &[Vec<String>],
+    options: &CopyOptions,
+) -> Result<u64> {
+    if rows.len() * bindings.len() > MAX_PARAMETERS {
+        return Err(LoaderError::ConfigError(format!(
+            "A batch of {} rows with {} columns exceeds the {} parameters one INSERT can bind",
+            rows.len(),
+            bindings.len(),
+            MAX_PARAMETERS
+        )));
+    }
+
+    let mut params: Vec<Param> = Vec::with_capacity(rows.len() * bindings.len());
+    for row in rows {
+        if row.len() != bindings.len() {
+            return Err(LoaderError::TypeConversionError(format!(
+                "Row has {} columns but expected {}",
+                row.len(),
+                bindings.len()
+            )));
+        }
+
+        for (value, binding) in row.iter().zip(bindings) {
+            params.push(to_param(value, binding, options)?);
+        }
+    }
+
+    let statement = insert_statement(table, columns, bindings, rows.len());
+    let param_types: Vec<Type> = bindings.iter().map(Binding::param_type).cycle().take(params.len()).collect();
+    let statement = client.prepare_typed(&statement, &param_types).await?;
+    let refs: Vec<&(dyn ToSql + Sync)> = params.iter().map(|p| p.as_ref() as &(dyn ToSql + Sync)).collect();
+    Ok(client.execute(&statement, &refs).await?)
+}
+
+/// Build `INSERT INTO table (columns) VALUES ($1::type, ...), ...`
+fn insert_statement(table: &str, columns: &[String], bindings: &[Binding], rows: usize) -> String {
+    let mut n = 0;
+    let tuples: Vec<String> = (0..rows)
+        .map(|_| {
+            let placeholders: Vec<String> = bindings
+                .iter()
+                .map(|binding| {
+                    n += 1;
+                    match &binding.cast {
+                        Some(name) => format!("${}::text::{}", n, name),
+                        None => placeholder(n, &binding.sql_type),
+                    }
+                })
+                .collect();
+            format!("({})", placeholders.join(", "))
+        })
+        .collect();
+
+    format!(
+        "INSERT INTO {} ({}) VALUES {}",
+        table,
+        columns.join(", "),
+        tuples.join(", ")
+    )
+}
+
+/// Placeholder for parameter `n`, cast to the column's type
+///
+/// Types tokio-postgres has no Rust type for are bound as text and cast by
+/// the server. Text stays uncast so VARCHAR lengths are checked on
+/// assignment instead of silently truncated.
+fn placeholder(n: usize, sql_type: &SqlType) -> String {
+    match sql_type {
+        SqlType::Null | SqlType::Text | SqlType::Varchar { .. } => format!("${}::text", n),
+        SqlType::Numeric | SqlType::Inet | SqlType::Cidr | SqlType::Array(_) => {
+            format!("${}::text::{}", n, sql_type.to_sql())
+        }
+        _ => format!("${}::{}", n, sql_type.to_sql()),
+    }
+}
+
+/// Convert a cell to a bind parameter of its binding's [`Binding::param_type`]
+fn to_param(value: &str, binding: &Binding, options: &CopyOptions) -> Result<Param> {
+    let sql_type = &binding.sql_type;
+    let Some(value) = encode_value(value, sql_type, options) else {
+        return Ok(match binding.cast {
+            Some(_) => Box::new(None::<String>),
+            None => null_param(sql_type),
+        });
+    };
+    if binding.cast.is_some() {
+        return Ok(Box::new(value.into_owned()));
+    }
+    let invalid = || LoaderError::TypeConversionError(format!(
+        "Invalid {} value: {}",
+        sql_type.to_sql(),
+        value
+    ));
+
+    Ok(match sql_type {
+        SqlType::Boolean => Box::new(parse_bool(&value).ok_or_else(invalid)?),
+        SqlType::SmallInt => Box::new(value.parse::<i16>().map_err(|_| invalid())?),
+        SqlType::Integer => Box::new(value.parse::<i32>().map_err(|_| invalid())?),
+        SqlType::BigInt => Box::new(value.parse::<i64>().map_err(|_| invalid())?),
+        SqlType::Real => Box::new(value.parse::<f32>().map_err(|_| invalid())?),
+        SqlType::DoublePrecision => Box::new(value.parse::<f64>().map_err(|_| invalid())?),
+        // Same parsing as binary COPY, so widened columns take dates and
+        // naive values there too
+        SqlType::Timestamp => Box::new(
+            SqlType::parse_timestamp_column(&value, &options.date_formats, &options.timestamp_formats)
+                .ok_or_else(invalid)?,
+        ),
+        SqlType::TimestampTz => Box::new(
+            SqlType::parse_timestamptz_column(&value, &options.date_formats, &options.timestamp_formats)
+                .ok_or_else(invalid)?,
+        ),
+        SqlType::Date => Box::new(SqlType::parse_date(&value, &options.date_formats).ok_or_else(invalid)?),
+        SqlType::Time => Box::new(SqlType::parse_time(&value).ok_or_else(invalid)?),
+        _ => Box::new(value.into_owned()),
+    })
+}
+
+/// NULL bound with the same Rust type as the column's non-null values
+fn null_param(sql_type: &SqlType) -> Param {
+    match sql_type {
+        SqlType::Boolean => Box::new(None::<bool>),
+        SqlType::SmallInt => Box::new(None::<i16>),
+        SqlType::Integer => Box::new(None::<i32>),
+        SqlType::BigInt => Box::new(None::<i64>),
+        SqlType::Real => Box::new(None::<f32>),
+        SqlType::DoublePrecision => Box::new(None::<f64>),
+        SqlType::Timestamp => Box::new(None::<chrono::NaiveDateTime>),
+        SqlType::TimestampTz => Box::new(None::<chrono::DateTime<chrono::FixedOffset>>),
+        SqlType::Date => Box::new(None::<chrono::NaiveDate>),
+        SqlType::Time => Box::new(None::<chrono::NaiveTime>),
+        _ => Box::new(None::<String>),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_statement() {
+        let columns = vec!["\"id\"".to_string(), "\"price\"".to_string(), "\"email\"".to_string()];
+        let bindings = vec![
+            Binding::new(SqlType::Integer),
+            Binding::new(SqlType::Numeric),
+            Binding { cast: Some("app.email".to_string()), ..Binding::new(SqlType::Text) },
+        ];
+
+        assert_eq!(
+            insert_statement("\"items\"", &columns, &bindings, 2),
+            "INSERT INTO \"items\" (\"id\", \"price\", \"email\") VALUES \
+             ($1::INTEGER, $2::text::NUMERIC, $3::text::app.email), ($4::INTEGER, $5::text::NUMERIC, $6::text::app.email)"
+        );
+        assert_eq!(placeholder(1, &SqlType::Varchar { len: 4 }), "$1::text");
+    }
+
+    #[test]
+    fn test_params_match_their_declared_types() {
+        let options = CopyOptions::default();
+        let cases = [
+            (SqlType::SmallInt, "42", Type::INT2),
+            (SqlType::Integer, "-7", Type::INT4),
+            (SqlType::BigInt, "9000000000", Type::INT8),
+            (SqlType::Real, "1.5", Type::FLOAT4),
+            (SqlType::DoublePrecision, "2.25", Type::FLOAT8),
+            (SqlType::Boolean, "yes", Type::BOOL),
+            (SqlType::Text, "hello", Type::TEXT),
+            (SqlType::Date, "2024-03-01", Type::DATE),
+            (SqlType::Timestamp, "2024-03-01 10:30:00", Type::TIMESTAMP),
+            (SqlType::TimestampTz, "2024-03-01T10:30:00+02:00", Type::TIMESTAMPTZ),
+            (SqlType::Time, "10:30:00", Type::TIME),
+            (SqlType::Numeric, "19.99", Type::TEXT),
+        ];
+
+        for (sql_type, value, expected) in cases {
+            let binding = Binding::new(sql_type.clone());
+            assert_eq!(binding.param_type(), expected, "{:?}", sql_type);
+            for value in [value, ""] {
+                let param = to_param(value, &binding, &options).unwrap();
+                let mut buf = bytes::BytesMut::new();
+                assert!(param.to_sql_checked(&expected, &mut buf).is_ok(), "{:?} {:?}", sql_type, value);
+            }
+        }
+
+        // Overridden columns bind as text, whatever their inferred type
+        let binding = Binding { cast: Some("app.amount".to_string()), ..Binding::new(SqlType::Integer) };
+        assert_eq!(binding.param_type(), Type::TEXT);
+        let param = to_param("0x1F", &binding, &options).unwrap();
+        assert!(param.to_sql_checked(&Type::TEXT, &mut bytes::BytesMut::new()).is_ok());
+    }
+
+    #[test]
+    fn test_validate_cast_type() {
+        let valid = [
+            "money",
+            "app.email",
+            "varchar(20)[]",
+            "\"Mixed Case\"",
+            "numeric(10, 2)",
+            "\"app\".\"Odd \"\"Name\"\"\"",
+            "int4[]",
+        ];
+        for name in valid {
+            assert!(validate_cast_type(name).is_ok(), "{}", name);
+        }
+        let invalid = [
+            "",
+            "int; DROP TABLE t",
+            "text'",
+            "int -- x",
+            "int, pg_sleep(5)",
+            "numeric(pg_sleep(5))",
+            "int)::text",
+            "a.b.c",
+            "\"\"",
+            "\"unterminated",
+            " int",
+            "numeric(10,)",
+            "numeric(1, 2, 3)",
+            "text[][",
+            "1int",
+        ];
+        for name in invalid {
+            assert!(validate_cast_type(name).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_to_param_types() {
+        let options = CopyOptions::default();
+        let param = |value: &str, sql_type: SqlType| to_param(value, &Binding::new(sql_type), &options);
+
+        assert!(param("42", SqlType::SmallInt).is_ok());
+        assert!(param("0x1F", SqlType::Integer).is_ok());
+        assert!(param("-Infinity", SqlType::DoublePrecision).is_ok());
+        assert!(param("2024-03-01", SqlType::Date).is_ok());
+        assert!(param("", SqlType::BigInt).is_ok());
+        assert!(param("abc", SqlType::Integer).is_err());
+        assert!(param("70000", SqlType::SmallInt).is_err());
+        assert!(param("maybe", SqlType::Boolean).is_err());
+
+        // Widened columns take a date as midnight and a naive value as UTC
+        assert!(param("2024-03-01", SqlType::Timestamp).is_ok());
+        assert!(param("2024-03-01", SqlType::TimestampTz).is_ok());
+        assert!(param("2024-03-01 10:30:00", SqlType::TimestampTz).is_ok());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_insert_mixed_timestamps() {
+        use crate::db::{CopyLoader, DbConnection, LoadMethod};
+        use crate::schema::TableSchema;
+
+        let conn = DbConnection::connect("postgresql://localhost/test").await.unwrap();
+
+        let mut schema = TableSchema::new(
+            "insert_mixed_timestamps".to_string(),
+            vec!["i".to_string(), "ts".to_string(), "tz".to_string()],
+        );
+        schema.finalize();
+        schema.columns[0].sql_type = SqlType::Integer;
+        schema.columns[1].sql_type = SqlType::Timestamp;
+        schema.columns[2].sql_type = SqlType::TimestampTz;
+        let rows = vec![
+            ["1", "2024-01-15 10:30:00", "2024-01-15T10:30:00+02:00"],
+            ["2", "2024-01-16", "2024-01-16 10:00:00"],
+            ["3", "17/01/2024", "17/01/2024"],
+        ]
+        .into_iter()
+        .map(|r| r.iter().map(|v| v.to_string()).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+
+        conn.drop_table(&schema.table_name).await.unwrap();
+        conn.create_table(&schema.to_create_table_sql()).await.unwrap();
+
+        let options = CopyOptions {
+            method: LoadMethod::Insert,
+            date_formats: vec!["%d/%m/%Y".to_string()],
+            ..Default::default()
+        };
+        let loader = CopyLoader::with_options(conn.client(), &schema, options);
+        assert_eq!(loader.load_batch(&rows).await.unwrap(), 3);
+
+        let loaded: Vec<(String, String)> = conn.client()
+            .query(
+                "SELECT ts::text, (tz AT TIME ZONE 'UTC')::text FROM insert_mixed_timestamps ORDER BY i",
+                &[],
+            )
+            .await
+            .unwrap()
+            .iter()
+            .map(|row| (row.get(0), row.get(1)))
+            .collect();
+        assert_eq!(
+            loaded,
+            [
+                ("2024-01-15 10:30:00", "2024-01-15 08:30:00"),
+                ("2024-01-16 00:00:00", "2024-01-16 10:00:00"),
+                ("2024-01-17 00:00:00", "2024-01-17 00:00:00"),
+            ]
+            .map(|(ts, tz)| (ts.to_string(), tz.to_string()))
+        );
+
+        conn.drop_table(&schema.table_name).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_insert_round_trip() {
+        use crate::db::{CopyLoader, DbConnection, LoadMethod};
+        use crate::schema::TableSchema;
+
+        let conn = DbConnection::connect("postgresql://localhost/test").await.unwrap();
+
+        let mut schema = TableSchema::new(
+            "insert_round_trip".to_string(),
+            ["i", "n", "b", "d", "tz", "ip", "tags", "s"].iter().map(|c| c.to_string()).collect(),
+        );
+        let rows = vec![
+            ["42", "19.99", "true", "2024-01-15", "2024-01-15T10:30:00+02:00", "10.0.0.1", "{a,b}", "hi"],
+            ["-7", "", "false", "1999-12-31", "", "::1", "{}", "it's"],
+            ["3", "0.5", "", "", "2024-01-15 10:30:00Z", "", "", ""],
+        ]
+        .into_iter()
+        .map(|r| r.iter().map(|v| v.to_string()).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+        for row in &rows {
+            schema.update_row(row).unwrap();
+        }
+        schema.finalize();
+
+        conn.drop_table(&schema.table_name).await.unwrap();
+        conn.create_table(&schema.to_create_table_sql()).await.unwrap();
+
+        let options = CopyOptions {
+            method: LoadMethod::Insert,
+            ..Default::default()
+        };
+        let loader = CopyLoader::with_options(conn.client(), &schema, options);
+        assert_eq!(loader.load_batch(&rows).await.unwrap(), 3);
+
+        let row = conn.client()
+            .query_one(
+                "SELECT pg_typeof(i)::text, n::text, b, d::text, tz AT TIME ZONE 'UTC', host(ip), tags::text, s \
+                 FROM insert_round_trip WHERE i = 42",
+                &[],
+            )
+            .await
+            .unwrap();
+        assert_eq!(row.get::<_, String>(0), "smallint");
+        assert_eq!(row.get::<_, String>(1), "19.99");
+        assert!(row.get::<_, bool>(2));
+        assert_eq!(row.get::<_, String>(3), "2024-01-15");
+        assert_eq!(
+            row.get::<_, chrono::NaiveDateTime>(4).to_string(),
+            "2024-01-15 08:30:00"
+        );
+        assert_eq!(row.get::<_, String>(5), "10.0.0.1");
+        assert_eq!(row.get::<_, String>(6), "{a,b}");
+        assert_eq!(row.get::<_, String>(7), "hi");
+
+        let nulls = conn.client()
+            .query_one("SELECT count(*) FROM insert_round_trip WHERE n IS NULL OR b IS NULL", &[])
+            .await
+            .unwrap();
+        assert_eq!(nulls.get::<_, i64>(0), 2);
+
+        conn.drop_table(&schema.table_name).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_insert_bind_type_override() {
+        use crate::db::{CopyLoader, DbConnection, LoadMethod};
+        use crate::schema::TableSchema;
+
+        let conn = DbConnection::connect("postgresql://localhost/test").await.unwrap();
+        let client = conn.client();
+        client
+            .batch_execute(
+                "DROP TABLE IF EXISTS insert_bind_type; DROP TYPE IF EXISTS insert_bind_mood; \
+                 CREATE TYPE insert_bind_mood AS ENUM ('happy', 'sad'); \
+                 CREATE TABLE insert_bind_type (id INTEGER, mood insert_bind_mood)",
+            )
+            .await
+            .unwrap();
+
+        let mut schema = TableSchema::new("insert_bind_type".to_string(), vec!["id".to_string(), "mood".to_string()]);
+        let rows = vec![vec!["1".to_string(), "happy".to_string()], vec!["2".to_string(), String::new()]];
+        for row in &rows {
+            schema.update_row(row).unwrap();
+        }
+        schema.finalize();
+
+        let options = CopyOptions { method: LoadMethod::Insert, ..Default::default() };
+        // Text has no assignment cast to an enum
+        let plain = CopyLoader::with_options(client, &schema, options.clone());
+        assert!(plain.load_batch(&rows).await.is_err());
+
+        let loader = CopyLoader::with_options(client, &schema, options)
+            .with_bind_types(&[("mood".to_string(), "insert_bind_mood".to_string())]);
+        assert_eq!(loader.load_batch(&rows).await.unwrap(), 2);
+
+        let row = client
+            .query_one("SELECT mood::text, (SELECT count(*) FROM insert_bind_type WHERE mood IS NULL) \
+                        FROM insert_bind_type WHERE id = 1", &[])
+            .await
+            .unwrap();
+        assert_eq!(row.get::<_, String>(0), "happy");
+        assert_eq!(row.get::<_, i64>(1), 1);
+
+        client
+            .batch_execute("DROP TABLE insert_bind_type; DROP TYPE insert_bind_mood")
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn test_max_rows() {
+        assert_eq!(max_rows(3), 21_845);
+        assert_eq!(max_rows(0), MAX_PARAMETERS);
+        assert_eq!(max_rows(100_000), 1);
+    }
+}
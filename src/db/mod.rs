@@ -2,8 +2,11 @@
 
 pub mod connection;
 pub mod copy;
+pub mod binary;
+pub mod insert;
 pub mod batch;
+pub mod retry;
 
-pub use connection::DbConnection;
-pub use copy::CopyLoader;
+pub use connection::{resolve_connection_string, DbConnection};
+pub use copy::{CopyFormat, CopyLoader, CopyOptions, LoadMethod, ValueTransform, WireFormat};
 pub use batch::BatchProcessor;
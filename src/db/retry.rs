@@ -0,0 +1,153 @@
+//! Retrying transient failures with exponential backoff
+//!
+//! Shared by batch loads and the initial connection, which differ only in
+//! what they count as transient and how they report a retry.
+
+use crate::errors::LoaderError;
+use rand::rngs::StdRng;
+use rand::Rng;
+use std::error::Error as _;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::sleep;
+use tokio_postgres::error::SqlState;
+
+/// Randomization applied to the retry backoff so parallel workers that fail
+/// together don't retry in lockstep
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum RetryJitter {
+    /// Sleep for exactly the backoff
+    #[default]
+    None,
+    /// Sleep for a random duration between zero and the backoff
+    Full,
+    /// Sleep for half the backoff plus a random share of the other half
+    Equal,
+}
+
+/// How many times to retry, and how long to wait before each retry
+///
+/// The wait starts at `initial` and doubles after every retry, up to `max`.
+/// The default makes no retries.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    pub max_retries: usize,
+    pub initial: Duration,
+    pub max: Duration,
+    pub jitter: RetryJitter,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            max_retries: 0,
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(60),
+            jitter: RetryJitter::None,
+        }
+    }
+}
+
+impl Backoff {
+    /// Run `attempt` until it succeeds, fails with an error `retryable`
+    /// rejects, or the retries run out
+    ///
+    /// `on_retry` is told the retry number, the error and the delay before
+    /// each retry. On failure, returns the number of retries made and the
+    /// last error.
+    pub async fn retry<T, F, Fut>(
+        &self,
+        rng: &Mutex<StdRng>,
+        retryable: impl Fn(&LoaderError) -> bool,
+        mut on_retry: impl FnMut(usize, &LoaderError, Duration),
+        mut attempt: F,
+    ) -> std::result::Result<T, (usize, LoaderError)>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = crate::errors::Result<T>>,
+    {
+        let mut retries = 0;
+        let mut backoff = self.initial;
+
+        loop {
+            match attempt().await {
+                Ok(value) => return Ok(value),
+                Err(e) => {
+                    if retries >= self.max_retries || !retryable(&e) {
+                        return Err((retries, e));
+                    }
+
+                    let delay = self.jittered(backoff, rng);
+                    retries += 1;
+                    on_retry(retries, &e, delay);
+                    sleep(delay).await;
+                    backoff = std::cmp::min(backoff * 2, self.max);
+                }
+            }
+        }
+    }
+
+    /// Apply the configured jitter to a backoff
+    fn jittered(&self, backoff: Duration, rng: &Mutex<StdRng>) -> Duration {
+        let random_up_to = |limit: Duration| {
+            let nanos = rng.lock().unwrap().gen_range(0..=limit.as_nanos() as u64);
+            Duration::from_nanos(nanos)
+        };
+
+        match self.jitter {
+            RetryJitter::None => backoff,
+            RetryJitter::Full => random_up_to(backoff),
+            RetryJitter::Equal => backoff / 2 + random_up_to(backoff - backoff / 2),
+        }
+    }
+}
+
+/// Whether an error may go away if the operation is tried again
+///
+/// Server errors are classified by SQLSTATE; lost connections and I/O
+/// failures, including refused connections and failed DNS lookups, are
+/// retried, anything about the data or credentials is not.
+pub fn is_retryable(error: &LoaderError) -> bool {
+    match error {
+        LoaderError::DatabaseError(e) => match e.code() {
+            Some(code) => is_retryable_sqlstate(code),
+            None => e.is_closed() || e.source().is_some_and(|s| s.is::<std::io::Error>()),
+        },
+        LoaderError::IoError(_) | LoaderError::ConnectionError(_) => true,
+        LoaderError::AtLine { source, .. } => is_retryable(source),
+        _ => false,
+    }
+}
+
+/// Transient SQLSTATEs: connection exceptions, serialization failures,
+/// deadlocks, resource exhaustion and server shutdown or startup
+fn is_retryable_sqlstate(code: &SqlState) -> bool {
+    let code = code.code();
+    code.starts_with("08")
+        || code.starts_with("53")
+        || matches!(code, "40001" | "40P01" | "57P01" | "57P02" | "57P03")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retryable_sqlstates() {
+        for code in ["08006", "08001", "40001", "40P01", "53300", "57P01", "57P03"] {
+            assert!(is_retryable_sqlstate(&SqlState::from_code(code)), "{}", code);
+        }
+        for code in ["22P02", "23505", "42703", "42P01", "22003", "57014", "28P01", "28000"] {
+            assert!(!is_retryable_sqlstate(&SqlState::from_code(code)), "{}", code);
+        }
+    }
+
+    #[test]
+    fn test_retryable_errors() {
+        let io = std::io::Error::new(std::io::ErrorKind::ConnectionReset, "reset");
+        assert!(is_retryable(&LoaderError::IoError(io)));
+        assert!(!is_retryable(&LoaderError::TypeConversionError("x".to_string())));
+        assert!(!is_retryable(&LoaderError::TypeConversionError("x".to_string()).at_line(3)));
+    }
+}
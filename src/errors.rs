@@ -7,8 +7,8 @@ pub enum LoaderError {
     #[error("CSV parsing error: {0}")]
     CsvError(#[from] csv::Error),
 
-    #[error("Database error: {0}")]
-    DatabaseError(#[from] tokio_postgres::Error),
+    #[error("Database error: {}", db_error_message(.0))]
+    DatabaseError(#[source] tokio_postgres::Error),
 
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
@@ -19,12 +19,21 @@ pub enum LoaderError {
     #[error("Schema inference failed: {0}")]
     SchemaInferenceError(String),
 
+    #[error("Schema file error: {0}")]
+    SchemaFileError(String),
+
     #[error("Type conversion error: {0}")]
     TypeConversionError(String),
 
     #[error("Batch processing failed after {retries} retries: {message}")]
     BatchError { retries: usize, message: String },
 
+    #[error("Batch failed with a non-retryable error: {0}")]
+    PermanentBatchError(String),
+
+    #[error("Too many rejected rows: {rejected} exceeds the limit of {max}")]
+    TooManyErrors { rejected: usize, max: usize },
+
     #[error("Connection error: {0}")]
     ConnectionError(String),
 
@@ -34,8 +43,77 @@ pub enum LoaderError {
     #[error("File not found: {0}")]
     FileNotFound(String),
 
+    #[error("Timed out: {0}")]
+    Timeout(String),
+
     #[error("Empty CSV file")]
     EmptyFile,
+
+    #[error("Row count check failed: loaded {expected} rows but the table gained {actual}")]
+    CountMismatch { expected: u64, actual: i64 },
+
+    #[error("{path} was already loaded into '{table}' at {loaded_at}; use --force to load it again")]
+    DuplicateFile { path: String, table: String, loaded_at: String },
+
+    #[error("Interrupted; {rows_loaded} rows remain loaded")]
+    Interrupted { rows_loaded: u64 },
+
+    #[error("line {line}: {source}")]
+    AtLine {
+        line: u64,
+        #[source]
+        source: Box<LoaderError>,
+    },
+
+    #[error("{path}: {source}")]
+    InFile {
+        path: String,
+        #[source]
+        source: Box<LoaderError>,
+    },
+}
+
+impl LoaderError {
+    /// Attach the source line number the error relates to
+    pub fn at_line(self, line: u64) -> Self {
+        LoaderError::AtLine {
+            line,
+            source: Box::new(self),
+        }
+    }
+
+    /// Attach the file the error relates to
+    pub fn in_file(self, path: &std::path::Path) -> Self {
+        LoaderError::InFile {
+            path: path.display().to_string(),
+            source: Box::new(self),
+        }
+    }
+}
+
+impl From<tokio_postgres::Error> for LoaderError {
+    /// Statements cancelled by `statement_timeout` become [`LoaderError::Timeout`]
+    fn from(error: tokio_postgres::Error) -> Self {
+        if let Some(db) = error.as_db_error() {
+            if *db.code() == tokio_postgres::error::SqlState::QUERY_CANCELED
+                && db.message().contains("statement timeout")
+            {
+                return LoaderError::Timeout(db.message().to_string());
+            }
+        }
+        LoaderError::DatabaseError(error)
+    }
+}
+
+/// Render a database error including the server's message, if any
+///
+/// `tokio_postgres::Error`'s own `Display` only says "db error" for
+/// server-side failures, which hides the useful part.
+fn db_error_message(error: &tokio_postgres::Error) -> String {
+    match error.as_db_error() {
+        Some(db) => format!("{}: {}", db.severity(), db.message()),
+        None => error.to_string(),
+    }
 }
 
 pub type Result<T> = std::result::Result<T, LoaderError>;
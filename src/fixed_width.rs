@@ -0,0 +1,179 @@
+//! Fixed-width input, where each column is a byte range of the line
+//!
+//! Columns are named in the spec rather than read from a header, so a
+//! header line, if present, is skipped. Fields are trimmed of the padding
+//! around them and feed the same inference and loading as CSV fields.
+
+use crate::errors::{LoaderError, Result};
+use std::io::BufRead;
+use std::ops::Range;
+
+/// A named column and the bytes of each line it covers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixedWidthColumn {
+    pub name: String,
+    /// Byte offsets, end exclusive
+    pub range: Range<usize>,
+}
+
+/// Parse a spec such as `id:0-10,name:10-30`
+///
+/// Ranges are 0-based byte offsets with an exclusive end.
+pub fn parse_spec(spec: &str) -> Result<Vec<FixedWidthColumn>> {
+    let invalid = |part: &str, why: &str| {
+        LoaderError::ConfigError(format!("Invalid fixed-width column '{}': {}", part, why))
+    };
+
+    let mut columns: Vec<FixedWidthColumn> = Vec::new();
+    for part in spec.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+        let (name, range) = part
+            .rsplit_once(':')
+            .ok_or_else(|| invalid(part, "expected NAME:START-END"))?;
+        let (start, end) = range
+            .split_once('-')
+            .ok_or_else(|| invalid(part, "expected NAME:START-END"))?;
+        let start: usize = start.trim().parse().map_err(|_| invalid(part, "start is not a number"))?;
+        let end: usize = end.trim().parse().map_err(|_| invalid(part, "end is not a number"))?;
+
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(invalid(part, "missing column name"));
+        }
+        if end <= start {
+            return Err(invalid(part, "end must be past start"));
+        }
+        if columns.iter().any(|c| c.name == name) {
+            return Err(invalid(part, "column named twice"));
+        }
+
+        columns.push(FixedWidthColumn {
+            name: name.to_string(),
+            range: start..end,
+        });
+    }
+
+    if columns.is_empty() {
+        return Err(LoaderError::ConfigError("--fixed-width names no columns".to_string()));
+    }
+    Ok(columns)
+}
+
+/// Reads fixed-width lines and slices them into fields
+pub struct FixedWidthReader {
+    source: Box<dyn BufRead + Send>,
+    columns: Vec<FixedWidthColumn>,
+    comment: Option<u8>,
+    /// Lines read so far
+    line: u64,
+    bytes: u64,
+    buf: Vec<u8>,
+}
+
+impl FixedWidthReader {
+    pub fn new(source: Box<dyn BufRead + Send>, columns: Vec<FixedWidthColumn>, comment: Option<u8>) -> Self {
+        Self {
+            source,
+            columns,
+            comment,
+            line: 0,
+            bytes: 0,
+            buf: Vec::new(),
+        }
+    }
+
+    /// Column names from the spec
+    pub fn headers(&self) -> Vec<String> {
+        self.columns.iter().map(|c| c.name.clone()).collect()
+    }
+
+    /// Bytes read so far
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes
+    }
+
+    /// Discard the next line whatever it holds, e.g. a header
+    pub fn skip_line(&mut self) -> Result<()> {
+        self.read_line()?;
+        Ok(())
+    }
+
+    /// Next record with its 1-based line number, skipping blank and
+    /// comment lines
+    ///
+    /// Columns past the end of a short line are empty.
+    pub fn read_record(&mut self) -> Result<Option<(u64, Vec<String>)>> {
+        loop {
+            if !self.read_line()? {
+                return Ok(None);
+            }
+            if self.buf.is_empty() || self.comment.is_some_and(|c| self.buf.first() == Some(&c)) {
+                continue;
+            }
+
+            let line = &self.buf;
+            let fields = self
+                .columns
+                .iter()
+                .map(|column| {
+                    let start = column.range.start.min(line.len());
+                    let end = column.range.end.min(line.len());
+                    String::from_utf8_lossy(&line[start..end]).trim().to_string()
+                })
+                .collect();
+            return Ok(Some((self.line, fields)));
+        }
+    }
+
+    /// Read a line into `buf` without its terminator
+    fn read_line(&mut self) -> Result<bool> {
+        self.buf.clear();
+        let read = self.source.read_until(b'\n', &mut self.buf)?;
+        if read == 0 {
+            return Ok(false);
+        }
+
+        self.line += 1;
+        self.bytes += read as u64;
+        if self.buf.ends_with(b"\n") {
+            self.buf.pop();
+        }
+        if self.buf.ends_with(b"\r") {
+            self.buf.pop();
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_parse_spec() {
+        let columns = parse_spec("id:0-4, name:4-12").unwrap();
+        assert_eq!(columns[0], FixedWidthColumn { name: "id".to_string(), range: 0..4 });
+        assert_eq!(columns[1].range, 4..12);
+
+        assert!(parse_spec("id:4-2").is_err());
+        assert!(parse_spec("id:0-4,id:4-8").is_err());
+        assert!(parse_spec("id:a-4").is_err());
+        assert!(parse_spec("id").is_err());
+        assert!(parse_spec("").is_err());
+    }
+
+    #[test]
+    fn test_read_records() {
+        let input = "1   alice   \r\n\n# note\n22  bo\n";
+        let mut reader = FixedWidthReader::new(
+            Box::new(Cursor::new(input.as_bytes().to_vec())),
+            parse_spec("id:0-4,name:4-12").unwrap(),
+            Some(b'#'),
+        );
+
+        assert_eq!(reader.read_record().unwrap(), Some((1, vec!["1".to_string(), "alice".to_string()])));
+        assert_eq!(reader.read_record().unwrap(), Some((4, vec!["22".to_string(), "bo".to_string()])));
+        assert_eq!(reader.read_record().unwrap(), None);
+        assert_eq!(reader.bytes_read(), input.len() as u64);
+    }
+}
@@ -0,0 +1,126 @@
+//! Load ledger: a table recording which files were loaded into which table
+//!
+//! Before loading, each file's fingerprint is looked up in the ledger, and
+//! a file already loaded into the same table is refused or skipped. A file
+//! is recorded once it has loaded, in the load's transaction when it has
+//! one, so a rolled-back load leaves no entry.
+//!
+//! The check and the record are separate statements, so two loads of the
+//! same file running at once can both pass the check; the ledger only
+//! guards against loading a file again after an earlier load finished.
+
+use crate::errors::{LoaderError, Result};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+/// What to do with a file the ledger says was already loaded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum OnDuplicateFile {
+    /// Fail before loading anything
+    #[default]
+    Error,
+    /// Warn and leave the file out of the load
+    Skip,
+}
+
+/// Ledger settings for a load
+#[derive(Debug, Clone)]
+pub struct LedgerOptions {
+    /// Table holding the ledger, created if missing
+    pub table: String,
+    pub on_duplicate: OnDuplicateFile,
+    /// Fingerprint files by size and modification time instead of hashing
+    /// their contents
+    pub cheap: bool,
+    /// Load files the ledger has seen anyway, recording them again
+    pub force: bool,
+}
+
+impl LedgerOptions {
+    pub fn new(table: impl Into<String>) -> Self {
+        Self {
+            table: table.into(),
+            on_duplicate: OnDuplicateFile::Error,
+            cheap: false,
+            force: false,
+        }
+    }
+}
+
+/// What identifies a loaded file in the ledger
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileFingerprint {
+    /// File name, without its directory
+    pub name: String,
+    /// Size in bytes, as stored, before any decompression
+    pub size: u64,
+    /// `sha256:` and the hex digest of the contents, or `mtime:` and the
+    /// modification time in nanoseconds since the epoch
+    pub digest: String,
+}
+
+impl FileFingerprint {
+    /// Fingerprint `path`, hashing its contents unless `cheap`
+    ///
+    /// Cheap and hashed fingerprints of the same file never match, so a
+    /// ledger should be used one way or the other.
+    pub fn of(path: &Path, cheap: bool) -> Result<Self> {
+        let file = File::open(path).map_err(|_| LoaderError::FileNotFound(path.display().to_string()))?;
+        let metadata = file.metadata()?;
+        let name = path
+            .file_name()
+            .map_or_else(|| path.display().to_string(), |name| name.to_string_lossy().into_owned());
+
+        let digest = if cheap {
+            let modified = metadata.modified()?.duration_since(UNIX_EPOCH).unwrap_or_default();
+            format!("mtime:{}", modified.as_nanos())
+        } else {
+            format!("sha256:{}", sha256_hex(file)?)
+        };
+
+        Ok(Self { name, size: metadata.len(), digest })
+    }
+}
+
+fn sha256_hex(mut reader: impl Read) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        match reader.read(&mut buffer)? {
+            0 => break,
+            n => hasher.update(&buffer[..n]),
+        }
+    }
+    Ok(hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_fingerprints() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("orders.csv");
+        std::fs::write(&path, "id\n1\n").unwrap();
+
+        let hashed = FileFingerprint::of(&path, false).unwrap();
+        assert_eq!(hashed.name, "orders.csv");
+        assert_eq!(hashed.size, 5);
+        // sha256 of "id\n1\n"
+        assert_eq!(hashed.digest, "sha256:7cde7fb64fd82bd152710cf238e017b9ab46c0592483edc067ba4f6c75fac108");
+        assert_eq!(FileFingerprint::of(&path, false).unwrap(), hashed);
+
+        let cheap = FileFingerprint::of(&path, true).unwrap();
+        assert!(cheap.digest.starts_with("mtime:"));
+        assert_eq!(cheap.size, 5);
+
+        let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"2\n").unwrap();
+        assert_ne!(FileFingerprint::of(&path, false).unwrap(), hashed);
+    }
+}
@@ -0,0 +1,28 @@
+//! CSV-SQL Streaming Loader
+//! High-performance library for streaming CSV files into PostgreSQL
+//!
+//! [`Loader`] runs a whole load the way the CLI does. The underlying
+//! modules are public for callers that want to compose parsing, inference
+//! and COPY themselves.
+
+pub mod errors;
+pub mod types;
+pub mod schema;
+pub mod profile;
+pub mod parser;
+pub mod compression;
+pub mod fixed_width;
+pub mod quoting;
+pub mod db;
+pub mod progress;
+pub mod reject;
+pub mod report;
+pub mod config;
+pub mod checkpoint;
+pub mod ledger;
+pub mod metrics;
+mod loader;
+
+pub use db::batch::CancelToken;
+pub use errors::{LoaderError, Result};
+pub use loader::{LoadStats, Loader, LoaderBuilder};
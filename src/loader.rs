@@ -0,0 +1,2769 @@
+//! End-to-end load of a CSV file into a table
+
+use crate::checkpoint::Checkpoint;
+use crate::db::batch::{BatchConfig, BatchIterator, CancelToken, OnError};
+use crate::db::connection::ConnectOptions;
+use crate::db::{insert, BatchProcessor, CopyFormat, CopyLoader, CopyOptions, DbConnection, LoadMethod, ValueTransform, WireFormat};
+use crate::errors::{LoaderError, Result};
+use crate::ledger::{FileFingerprint, LedgerOptions, OnDuplicateFile};
+use crate::metrics;
+use crate::parser::{self, ColumnSelection, CsvOptions, CsvParser, RaggedRows};
+use crate::progress::{JsonProgress, NoProgress, ProgressFormat, ProgressSink, ProgressTracker};
+use crate::reject::RejectWriter;
+use crate::report::{column_warnings, ColumnWarning, InferenceReport};
+use crate::schema::{create_index_sql, index_names, split_table_name, DdlOptions, InferenceConfig, SampleStrategy, TableSchema};
+use crate::types::{IntegerCap, SqlType};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+/// Print a status line to stdout unless the loader is quiet
+macro_rules! status {
+    ($loader:expr) => {
+        if !$loader.quiet {
+            println!();
+        }
+    };
+    ($loader:expr, $($arg:tt)*) => {
+        if !$loader.quiet {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// Comment marking a `--swap` staging table as the loader's own, so one
+/// left by an interrupted swap can be dropped without touching user tables
+const SWAP_STAGING_COMMENT: &str = "csv-sql-loader --swap staging table";
+
+/// Summary of a finished load
+#[derive(Debug, Clone, Default)]
+pub struct LoadStats {
+    pub table_name: String,
+    /// CSV files loaded
+    pub files: usize,
+    pub rows_loaded: u64,
+    /// Rows rejected in skip mode
+    pub rows_rejected: usize,
+    /// Short rows padded with NULLs in flexible mode
+    pub rows_padded: u64,
+    /// Long rows truncated in flexible mode
+    pub rows_truncated: u64,
+    /// Rows skipped because a checkpoint recorded them as loaded
+    pub rows_skipped: u64,
+    /// CSV bytes read while loading, counted after decompression
+    pub bytes_read: u64,
+    /// Batch attempts retried after transient failures
+    pub retries: usize,
+    pub elapsed: Duration,
+    /// Rows per second
+    pub throughput: f64,
+    /// CSV bytes read per second, steadier than rows when widths vary
+    pub bytes_per_sec: f64,
+    /// Indexes created after the load, by name
+    pub indexes_created: Vec<String>,
+    /// Time spent creating them, on top of `elapsed`
+    pub index_time: Duration,
+}
+
+/// Loads CSV files into PostgreSQL; build one with [`LoaderBuilder`]
+#[derive(Clone)]
+pub struct Loader {
+    connection_string: String,
+    connect_options: ConnectOptions,
+    table: Option<String>,
+    csv_options: CsvOptions,
+    selection: Option<ColumnSelection>,
+    inference: InferenceConfig,
+    copy_options: CopyOptions,
+    batch_config: BatchConfig,
+    ddl_options: DdlOptions,
+    parallelism: usize,
+    channel_depth: usize,
+    create_table: bool,
+    strict_existence: bool,
+    swap: bool,
+    rename_to: Option<String>,
+    drop_table: bool,
+    truncate: bool,
+    restart_identity: bool,
+    atomic: bool,
+    union_schema: bool,
+    column_types: Vec<(String, SqlType)>,
+    transforms: Vec<(String, ValueTransform)>,
+    bind_types: Vec<(String, String)>,
+    all_nullable: bool,
+    no_nulls: bool,
+    verify_count: bool,
+    column_map: Vec<(String, String)>,
+    renames: Vec<(String, String)>,
+    use_table_order: bool,
+    target_columns: Vec<String>,
+    indexes: Vec<String>,
+    index_detect: bool,
+    sanitize_columns: bool,
+    primary_key: Option<String>,
+    schema_in: Option<PathBuf>,
+    force_text: bool,
+    schema_out: Option<PathBuf>,
+    ddl_out: Option<PathBuf>,
+    report: Option<PathBuf>,
+    min_confidence: Option<f64>,
+    strict: bool,
+    strict_types: bool,
+    error_file: Option<PathBuf>,
+    checkpoint_file: Option<PathBuf>,
+    ledger: Option<LedgerOptions>,
+    limit: Option<u64>,
+    metrics_file: Option<PathBuf>,
+    metrics_labels: Vec<(String, String)>,
+    count_rows: bool,
+    dry_run: bool,
+    check_db: bool,
+    quiet: bool,
+    verbose: bool,
+    progress_format: ProgressFormat,
+    /// Progress receiver; defaults to a terminal progress bar
+    progress: Option<Arc<dyn ProgressSink>>,
+    cancel: CancelToken,
+}
+
+/// Builder for [`Loader`]
+///
+/// ```no_run
+/// # async fn example() -> csv_sql_loader::Result<()> {
+/// let stats = csv_sql_loader::LoaderBuilder::new("postgresql://localhost/db")
+///     .table("people")
+///     .batch_size(50_000)
+///     .create_table(true)
+///     .build()?
+///     .load("people.csv")
+///     .await?;
+/// println!("{} rows", stats.rows_loaded);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct LoaderBuilder {
+    loader: Loader,
+}
+
+impl LoaderBuilder {
+    /// Start a loader for the database at `connection_string`
+    pub fn new(connection_string: impl Into<String>) -> Self {
+        Self {
+            loader: Loader {
+                connection_string: connection_string.into(),
+                connect_options: ConnectOptions::default(),
+                table: None,
+                csv_options: CsvOptions::default(),
+                selection: None,
+                inference: InferenceConfig::default(),
+                copy_options: CopyOptions::default(),
+                batch_config: BatchConfig::default(),
+                ddl_options: DdlOptions::default(),
+                parallelism: 1,
+                channel_depth: 4,
+                create_table: false,
+                strict_existence: false,
+                swap: false,
+                rename_to: None,
+                drop_table: false,
+                truncate: false,
+                restart_identity: false,
+                atomic: false,
+                union_schema: false,
+                column_types: Vec::new(),
+                transforms: Vec::new(),
+                bind_types: Vec::new(),
+                all_nullable: false,
+                no_nulls: false,
+                verify_count: false,
+                column_map: Vec::new(),
+                renames: Vec::new(),
+                use_table_order: false,
+                target_columns: Vec::new(),
+                indexes: Vec::new(),
+                index_detect: false,
+                sanitize_columns: false,
+                primary_key: None,
+                schema_in: None,
+                force_text: false,
+                schema_out: None,
+                ddl_out: None,
+                report: None,
+                min_confidence: None,
+                strict: false,
+                strict_types: false,
+                error_file: None,
+                checkpoint_file: None,
+                ledger: None,
+                limit: None,
+                metrics_file: None,
+                metrics_labels: Vec::new(),
+                count_rows: false,
+                dry_run: false,
+                check_db: false,
+                quiet: false,
+                verbose: false,
+                progress_format: ProgressFormat::Bar,
+                progress: None,
+                cancel: CancelToken::new(),
+            },
+        }
+    }
+
+    /// Target table, optionally schema-qualified (default: the file stem)
+    pub fn table(mut self, table: impl Into<String>) -> Self {
+        self.loader.table = Some(table.into());
+        self
+    }
+
+    /// Rows per COPY batch
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.loader.batch_config.batch_size = batch_size;
+        self
+    }
+
+    /// End a batch early once its rows reach this many bytes
+    pub fn batch_bytes(mut self, batch_bytes: usize) -> Self {
+        self.loader.batch_config.batch_bytes = Some(batch_bytes);
+        self
+    }
+
+    /// Rename the table once the load is done
+    ///
+    /// A schema-qualified name must keep the table's schema.
+    pub fn rename_to(mut self, name: impl Into<String>) -> Self {
+        self.loader.rename_to = Some(name.into());
+        self
+    }
+
+    /// Create the table when it doesn't exist, even with `drop_table`
+    ///
+    /// Without it a missing table is still created, unless
+    /// `strict_existence`, `drop_table` or `truncate` is set.
+    pub fn create_table(mut self, create_table: bool) -> Self {
+        self.loader.create_table = create_table;
+        self
+    }
+
+    /// Fail if the table doesn't exist instead of creating it
+    pub fn strict_existence(mut self, strict_existence: bool) -> Self {
+        self.loader.strict_existence = strict_existence;
+        self
+    }
+
+    /// Load into a new `<table>_new` created like the existing table, then
+    /// replace the existing table with it in one transaction
+    ///
+    /// The new table copies the old one's column types, defaults,
+    /// constraints and indexes; foreign keys, grants and triggers are not
+    /// carried over. A `<table>_new` the loader didn't make is an error.
+    pub fn swap(mut self, swap: bool) -> Self {
+        self.loader.swap = swap;
+        self
+    }
+
+    /// Drop the table before loading
+    pub fn drop_table(mut self, drop_table: bool) -> Self {
+        self.loader.drop_table = drop_table;
+        self
+    }
+
+    /// Empty the existing table before loading
+    pub fn truncate(mut self, truncate: bool, restart_identity: bool) -> Self {
+        self.loader.truncate = truncate;
+        self.loader.restart_identity = restart_identity;
+        self
+    }
+
+    /// Number of concurrent connections loading batches
+    pub fn parallelism(mut self, parallelism: usize) -> Self {
+        self.loader.parallelism = parallelism;
+        self
+    }
+
+    /// Parsed batches buffered ahead of the loaders
+    pub fn channel_depth(mut self, channel_depth: usize) -> Self {
+        self.loader.channel_depth = channel_depth;
+        self
+    }
+
+    /// Load everything in one transaction
+    pub fn atomic(mut self, atomic: bool) -> Self {
+        self.loader.atomic = atomic;
+        self
+    }
+
+    /// Load in one transaction with a savepoint per batch
+    pub fn atomic_with_savepoints(mut self, enabled: bool) -> Self {
+        self.loader.batch_config.savepoints = enabled;
+        self
+    }
+
+    /// With several files, infer every file and combine the schemas
+    /// instead of requiring identical columns
+    pub fn union_schema(mut self, union_schema: bool) -> Self {
+        self.loader.union_schema = union_schema;
+        self
+    }
+
+    /// CSV dialect and layout
+    pub fn csv_options(mut self, options: CsvOptions) -> Self {
+        self.loader.csv_options = options;
+        self
+    }
+
+    /// Load only some of the CSV columns
+    pub fn select_columns(mut self, selection: ColumnSelection) -> Self {
+        self.loader.selection = Some(selection);
+        self
+    }
+
+    /// Type inference settings; `has_headers` follows the CSV options
+    pub fn inference(mut self, config: InferenceConfig) -> Self {
+        self.loader.inference = config;
+        self
+    }
+
+    /// How values are encoded for COPY
+    pub fn copy_options(mut self, options: CopyOptions) -> Self {
+        self.loader.copy_options = options;
+        self
+    }
+
+    /// Batch size, retries and error handling; replaces earlier
+    /// `batch_size` and `atomic_with_savepoints` settings
+    pub fn batch_config(mut self, config: BatchConfig) -> Self {
+        self.loader.batch_config = config;
+        self
+    }
+
+    /// Generated DDL settings
+    pub fn ddl_options(mut self, options: DdlOptions) -> Self {
+        self.loader.ddl_options = options;
+        self
+    }
+
+    /// Override a column's inferred type
+    pub fn column_type(mut self, name: impl Into<String>, sql_type: SqlType) -> Self {
+        self.loader.column_types.push((name.into(), sql_type));
+        self
+    }
+
+    /// Rewrite a column's values as they're loaded (repeatable; a column's
+    /// transforms run in the order given)
+    ///
+    /// Inference runs first and sees the original values.
+    pub fn transform(mut self, name: impl Into<String>, transform: ValueTransform) -> Self {
+        self.loader.transforms.push((name.into(), transform));
+        self
+    }
+
+    /// In insert mode, bind a loaded column's values as text cast to the
+    /// named type, e.g. a domain or enum, instead of as its inferred type
+    pub fn bind_type(mut self, name: impl Into<String>, type_name: impl Into<String>) -> Self {
+        self.loader.bind_types.push((name.into(), type_name.into()));
+        self
+    }
+
+    /// Make every column nullable, whatever the sample showed
+    pub fn all_nullable(mut self, all_nullable: bool) -> Self {
+        self.loader.all_nullable = all_nullable;
+        self
+    }
+
+    /// Make every column NOT NULL, whatever the sample showed
+    pub fn no_nulls(mut self, no_nulls: bool) -> Self {
+        self.loader.no_nulls = no_nulls;
+        self
+    }
+
+    /// Count the table's rows before and after loading and fail unless it
+    /// gained exactly the rows reported loaded
+    ///
+    /// Other sessions writing to the table at the same time will trip it.
+    pub fn verify_count(mut self, verify_count: bool) -> Self {
+        self.loader.verify_count = verify_count;
+        self
+    }
+
+    /// Create an index on each of these table columns once the data is
+    /// loaded
+    pub fn indexes(mut self, columns: Vec<String>) -> Self {
+        self.loader.indexes = columns;
+        self
+    }
+
+    /// Also index columns that look like foreign keys or, from profiling
+    /// the sample, have high cardinality
+    pub fn index_detect(mut self, index_detect: bool) -> Self {
+        self.loader.index_detect = index_detect;
+        self
+    }
+
+    /// Load CSV columns into differently named table columns
+    pub fn column_map(mut self, map: Vec<(String, String)>) -> Self {
+        self.loader.column_map = map;
+        self
+    }
+
+    /// Rename CSV header columns on the way in, as `(old, new)` pairs
+    ///
+    /// Include/exclude lists still use the CSV names; everything after,
+    /// including `--map`, sees the new names.
+    pub fn renames(mut self, renames: Vec<(String, String)>) -> Self {
+        self.loader.renames = renames;
+        self
+    }
+
+    /// Load columns in the existing table's order, naming each one in the
+    /// COPY, and fail if the table lacks a CSV column
+    pub fn use_table_order(mut self, use_table_order: bool) -> Self {
+        self.loader.use_table_order = use_table_order;
+        self
+    }
+
+    /// COPY into just these table columns, letting the rest take their
+    /// DEFAULT
+    ///
+    /// CSV columns (after any include/exclude selection) feed them by
+    /// position, or by name with `column_map`.
+    pub fn target_columns(mut self, columns: Vec<String>) -> Self {
+        self.loader.target_columns = columns;
+        self
+    }
+
+    /// Rewrite column names into safe lowercase identifiers
+    pub fn sanitize_columns(mut self, sanitize: bool) -> Self {
+        self.loader.sanitize_columns = sanitize;
+        self
+    }
+
+    /// Use this column as the primary key of a created table
+    pub fn primary_key(mut self, name: impl Into<String>) -> Self {
+        self.loader.primary_key = Some(name.into());
+        self
+    }
+
+    /// Skip inference and use a schema JSON file
+    pub fn schema_in(mut self, path: impl Into<PathBuf>) -> Self {
+        self.loader.schema_in = Some(path.into());
+        self
+    }
+
+    /// Skip inference and make every column nullable TEXT
+    pub fn force_text(mut self, enabled: bool) -> Self {
+        self.loader.force_text = enabled;
+        self
+    }
+
+    /// Write the inferred schema to a JSON file
+    pub fn schema_out(mut self, path: impl Into<PathBuf>) -> Self {
+        self.loader.schema_out = Some(path.into());
+        self
+    }
+
+    /// Write the CREATE TABLE statement to a file
+    pub fn ddl_out(mut self, path: impl Into<PathBuf>) -> Self {
+        self.loader.ddl_out = Some(path.into());
+        self
+    }
+
+    /// Write a JSON inference report
+    pub fn report(mut self, path: impl Into<PathBuf>) -> Self {
+        self.loader.report = Some(path.into());
+        self
+    }
+
+    /// Warn about, or with [`strict`](Self::strict) reject, low-confidence
+    /// columns
+    pub fn min_confidence(mut self, min: f64) -> Self {
+        self.loader.min_confidence = Some(min);
+        self
+    }
+
+    /// Abort instead of warning about low-confidence columns and integer
+    /// columns degraded by the inference cap
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.loader.strict = strict;
+        self
+    }
+
+    /// Fail if connecting takes longer than `timeout`
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.loader.connect_options.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Retry a connection attempt that fails transiently up to `retries`
+    /// times, backing off exponentially from one second
+    pub fn connect_retries(mut self, retries: usize) -> Self {
+        self.loader.connect_options.connect_retry.max_retries = retries;
+        self
+    }
+
+    /// Have the server cancel any statement, including a COPY, running
+    /// longer than `timeout`
+    pub fn statement_timeout(mut self, timeout: Duration) -> Self {
+        self.loader.connect_options.statement_timeout = Some(timeout);
+        self
+    }
+
+    /// Name the connections in `pg_stat_activity` (default:
+    /// `csv-sql-loader`, or whatever the connection string sets)
+    pub fn app_name(mut self, name: impl Into<String>) -> Self {
+        self.loader.connect_options.application_name = Some(name.into());
+        self
+    }
+
+    /// Extra connection parameters, e.g. `("options", "-c search_path=s")`
+    pub fn conn_options(mut self, parameters: Vec<(String, String)>) -> Self {
+        self.loader.connect_options.parameters = parameters;
+        self
+    }
+
+    /// Set the session's `search_path`, so an unqualified table name is
+    /// created, checked and loaded in the first of these schemas that
+    /// exists; a schema-qualified name is unaffected
+    pub fn search_path(mut self, schemas: Vec<String>) -> Self {
+        self.loader.connect_options.search_path = schemas;
+        self
+    }
+
+    /// Abort instead of warning when an existing table's column types
+    /// can't hold the values being loaded
+    pub fn strict_types(mut self, strict_types: bool) -> Self {
+        self.loader.strict_types = strict_types;
+        self
+    }
+
+    /// Write rows rejected in skip mode to this CSV file
+    pub fn error_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.loader.error_file = Some(path.into());
+        self
+    }
+
+    /// Record loaded rows in this file after each batch, and resume after
+    /// them when it exists
+    pub fn checkpoint_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.loader.checkpoint_file = Some(path.into());
+        self
+    }
+
+    /// Record loaded files in a ledger table, and refuse or skip files it
+    /// has already seen loaded into the table
+    pub fn ledger(mut self, options: LedgerOptions) -> Self {
+        self.loader.ledger = Some(options);
+        self
+    }
+
+    /// Stop after this many data rows; inference still reads its own sample
+    pub fn limit(mut self, limit: u64) -> Self {
+        self.loader.limit = Some(limit);
+        self
+    }
+
+    /// Write Prometheus textfile gauges describing the load to this file,
+    /// whether it succeeds or fails
+    pub fn metrics_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.loader.metrics_file = Some(path.into());
+        self
+    }
+
+    /// Labels added to every metric, as name/value pairs
+    pub fn metrics_labels(mut self, labels: Vec<(String, String)>) -> Self {
+        self.loader.metrics_labels = labels;
+        self
+    }
+
+    /// Count rows first so progress shows a percentage
+    pub fn count_rows(mut self, count_rows: bool) -> Self {
+        self.loader.count_rows = count_rows;
+        self
+    }
+
+    /// Stop after the batches in flight once `cancel` is triggered, failing
+    /// with [`LoaderError::Interrupted`]
+    pub fn cancel_token(mut self, cancel: CancelToken) -> Self {
+        self.loader.cancel = cancel;
+        self
+    }
+
+    /// Show the schema and DDL without loading; connects only with
+    /// `check_db`
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.loader.dry_run = dry_run;
+        self
+    }
+
+    /// In a dry run, connect read-only and compare the schema with the
+    /// existing table
+    pub fn check_db(mut self, check_db: bool) -> Self {
+        self.loader.check_db = check_db;
+        self
+    }
+
+    /// Send progress to `sink` instead of the terminal progress bar
+    pub fn progress(mut self, sink: Arc<dyn ProgressSink>) -> Self {
+        self.loader.progress = Some(sink);
+        self
+    }
+
+    /// Built-in progress display used when no sink is set
+    pub fn progress_format(mut self, format: ProgressFormat) -> Self {
+        self.loader.progress_format = format;
+        self
+    }
+
+    /// Hide the progress bar and the status lines printed to stdout
+    pub fn quiet(mut self, quiet: bool) -> Self {
+        self.loader.quiet = quiet;
+        self
+    }
+
+    /// Print extra detail, such as column renames and batch load times
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.loader.verbose = verbose;
+        self
+    }
+
+    /// Check the settings and build the loader
+    pub fn build(self) -> Result<Loader> {
+        let mut loader = self.loader;
+
+        if loader.parallelism == 0 {
+            return Err(LoaderError::ConfigError(
+                "--parallelism must be at least 1".to_string()
+            ));
+        }
+
+        if loader.connect_options.search_path.iter().any(|schema| schema.trim().is_empty()) {
+            return Err(LoaderError::ConfigError(
+                "--search-path has an empty schema name".to_string()
+            ));
+        }
+
+        if loader.strict_existence && loader.create_table {
+            return Err(LoaderError::ConfigError(
+                "--strict-existence cannot be combined with --create-table".to_string()
+            ));
+        }
+
+        if loader.swap && (loader.drop_table || loader.truncate || loader.checkpoint_file.is_some()) {
+            return Err(LoaderError::ConfigError(
+                "--swap cannot be combined with --drop-table, --truncate or --checkpoint-file".to_string()
+            ));
+        }
+
+        if let Some(name) = &loader.rename_to {
+            TableSchema::validate_table_name(name)?;
+            if loader.swap {
+                return Err(LoaderError::ConfigError(
+                    "--rename-to cannot be combined with --swap".to_string()
+                ));
+            }
+        }
+
+        if loader.swap && loader.maps_columns() {
+            return Err(LoaderError::ConfigError(
+                "--swap cannot be combined with --map, --use-table-order or --target-columns".to_string()
+            ));
+        }
+
+        if loader.check_db && !loader.dry_run {
+            return Err(LoaderError::ConfigError(
+                "--check-db only applies to --dry-run".to_string()
+            ));
+        }
+
+        if loader.limit == Some(0) {
+            return Err(LoaderError::ConfigError(
+                "--limit must be at least 1".to_string()
+            ));
+        }
+
+        if loader.channel_depth == 0 {
+            return Err(LoaderError::ConfigError(
+                "--channel-depth must be at least 1".to_string()
+            ));
+        }
+
+        if loader.is_atomic() && loader.parallelism > 1 {
+            return Err(LoaderError::ConfigError(
+                "--atomic requires a single connection (--parallelism 1)".to_string()
+            ));
+        }
+
+        if loader.atomic && !loader.batch_config.savepoints && loader.batch_config.isolate_errors {
+            return Err(LoaderError::ConfigError(
+                "--isolate-errors needs --atomic-with-savepoints instead of --atomic".to_string()
+            ));
+        }
+
+        if loader.atomic && !loader.batch_config.savepoints && loader.batch_config.on_error == OnError::Skip {
+            return Err(LoaderError::ConfigError(
+                "--atomic cannot be combined with --on-error skip; use --atomic-with-savepoints".to_string()
+            ));
+        }
+
+        // FREEZE needs the table created or truncated in the COPY's own
+        // transaction, and not in a savepoint's subtransaction
+        if loader.copy_options.freeze
+            && (!loader.atomic || loader.batch_config.savepoints || loader.copy_options.method != LoadMethod::Copy)
+        {
+            return Err(LoaderError::ConfigError(
+                "--freeze requires --atomic (not --atomic-with-savepoints) and --method copy".to_string()
+            ));
+        }
+
+        if loader.checkpoint_file.is_some() && loader.is_atomic() {
+            return Err(LoaderError::ConfigError(
+                "--checkpoint-file cannot be combined with --atomic or --atomic-with-savepoints".to_string()
+            ));
+        }
+
+        if let Some(ledger) = &loader.ledger {
+            TableSchema::validate_table_name(&ledger.table)?;
+            // Files recorded before an interruption would be left out on
+            // resume, while the checkpoint still counts their rows
+            if loader.checkpoint_file.is_some() {
+                return Err(LoaderError::ConfigError(
+                    "--ledger-table cannot be combined with --checkpoint-file".to_string()
+                ));
+            }
+        }
+
+        metrics::validate_labels(&loader.metrics_labels)?;
+
+        if loader.copy_options.null_marker.as_deref().is_some_and(|m| m.contains([',', '"', '\n', '\r'])) {
+            return Err(LoaderError::ConfigError(
+                "--null-marker cannot contain a comma, double quote or newline".to_string()
+            ));
+        }
+
+        if loader.copy_options.method == LoadMethod::Insert && loader.copy_options.format == CopyFormat::Binary {
+            return Err(LoaderError::ConfigError(
+                "--copy-format binary cannot be combined with --method insert".to_string()
+            ));
+        }
+        if loader.copy_options.method == LoadMethod::Insert && loader.copy_options.wire_format == WireFormat::Text {
+            return Err(LoaderError::ConfigError(
+                "--copy-wire-format text cannot be combined with --method insert".to_string()
+            ));
+        }
+        if !loader.bind_types.is_empty() && loader.copy_options.method != LoadMethod::Insert {
+            return Err(LoaderError::ConfigError(
+                "--bind-type requires --method insert".to_string()
+            ));
+        }
+        for (_, type_name) in &loader.bind_types {
+            insert::validate_cast_type(type_name)?;
+        }
+
+        if loader.union_schema && (loader.schema_in.is_some() || !loader.column_map.is_empty() || loader.use_table_order) {
+            return Err(LoaderError::ConfigError(
+                "--union-schema cannot be combined with --schema-in, --map or --use-table-order".to_string()
+            ));
+        }
+
+        if loader.force_text && loader.schema_in.is_some() {
+            return Err(LoaderError::ConfigError(
+                "--force-text cannot be combined with --schema-in".to_string()
+            ));
+        }
+
+        if loader.schema_in.is_some() && !loader.renames.is_empty() {
+            return Err(LoaderError::ConfigError(
+                "--rename cannot be combined with --schema-in, which names the columns".to_string()
+            ));
+        }
+
+        if loader.all_nullable && loader.no_nulls {
+            return Err(LoaderError::ConfigError(
+                "--all-nullable cannot be combined with --no-nulls".to_string()
+            ));
+        }
+
+        if !loader.target_columns.is_empty() && (loader.use_table_order || loader.union_schema) {
+            return Err(LoaderError::ConfigError(
+                "--target-columns cannot be combined with --use-table-order or --union-schema".to_string()
+            ));
+        }
+
+        if loader.target_columns.iter().any(|column| column.trim().is_empty()) {
+            return Err(LoaderError::ConfigError(
+                "--target-columns has an empty column name".to_string()
+            ));
+        }
+
+        if loader.indexes.iter().any(|column| column.trim().is_empty()) {
+            return Err(LoaderError::ConfigError(
+                "--index has an empty column name".to_string()
+            ));
+        }
+
+        if loader.use_table_order && !loader.column_map.is_empty() {
+            return Err(LoaderError::ConfigError(
+                "--use-table-order cannot be combined with --map, which already loads in table order".to_string()
+            ));
+        }
+
+        if loader.inference.buffer_sample && loader.inference.sample_strategy != SampleStrategy::Head {
+            return Err(LoaderError::ConfigError(
+                "--buffer-sample requires --sample-strategy head".to_string()
+            ));
+        }
+
+        if loader.inference.varchar_padding < 1.0 {
+            return Err(LoaderError::ConfigError(
+                "--varchar-padding must be at least 1.0".to_string()
+            ));
+        }
+
+        if loader.min_confidence.is_some_and(|min| !(0.0..=1.0).contains(&min)) {
+            return Err(LoaderError::ConfigError(
+                "--min-confidence must be between 0.0 and 1.0".to_string()
+            ));
+        }
+
+        if loader.inference.thousands_separator == Some(loader.inference.decimal_separator) {
+            return Err(LoaderError::ConfigError(
+                "--decimal-separator and --thousands-separator must differ".to_string()
+            ));
+        }
+
+        // Lines with bad quoting are dropped as rejects only when skipping them
+        loader.csv_options.recover_quotes = loader.batch_config.on_error == OnError::Skip;
+
+        Ok(loader)
+    }
+}
+
+impl Loader {
+    /// Whether the load runs in a single transaction
+    fn is_atomic(&self) -> bool {
+        self.atomic || self.batch_config.savepoints
+    }
+
+    /// Infer (or read) the schema, prepare the table, and load the file
+    pub async fn load<P: AsRef<Path>>(&self, csv_path: P) -> Result<LoadStats> {
+        self.load_many(&[csv_path]).await
+    }
+
+    /// Load several files into one table
+    ///
+    /// The schema comes from the first file and every file must have the
+    /// same columns, unless `union_schema` is set: then every file is
+    /// inferred and the schemas are combined. Progress and statistics
+    /// cover all files.
+    pub async fn load_many<P: AsRef<Path>>(&self, csv_paths: &[P]) -> Result<LoadStats> {
+        let csv_paths: Vec<&Path> = csv_paths.iter().map(|path| path.as_ref()).collect();
+        let mut partial = LoadStats::default();
+        let result = self.load_paths(&csv_paths, &mut partial).await;
+
+        let Some(path) = &self.metrics_file else {
+            return result;
+        };
+        let written = match &result {
+            Ok(stats) => metrics::write_textfile(path, &self.metrics_labels, stats, true),
+            Err(_) => metrics::write_textfile(path, &self.metrics_labels, &partial, false),
+        };
+        result.and_then(|stats| written.map(|()| stats))
+    }
+
+    /// Load the files, filling in `partial` with what was done if the load
+    /// fails part way
+    async fn load_paths(&self, csv_paths: &[&Path], partial: &mut LoadStats) -> Result<LoadStats> {
+        let Some(&first_path) = csv_paths.first() else {
+            return Err(LoaderError::ConfigError("No CSV files to load".to_string()));
+        };
+
+        if let Some(missing) = csv_paths.iter().find(|path| !path.exists()) {
+            return Err(LoaderError::FileNotFound(missing.display().to_string()));
+        }
+
+        // Determine table name
+        let table_name = self.table.clone().unwrap_or_else(|| {
+            first_path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("imported_data")
+                .to_string()
+        });
+
+        // Parse CSV and infer schema
+        let has_headers = self.csv_options.has_headers;
+        // Only files inferred and then reset to load are mapped, so no load
+        // reads a mapping that could change underneath it
+        let mapped_files = if self.schema_in.is_some() || self.force_text || self.inference.buffer_sample {
+            0
+        } else if self.union_schema {
+            csv_paths.len()
+        } else {
+            1
+        };
+        let mut parsers = csv_paths
+            .iter()
+            .enumerate()
+            .map(|(index, path)| {
+                // Sanitizing numbers repeated names itself
+                let options = CsvOptions {
+                    dedup_headers: self.csv_options.dedup_headers || self.sanitize_columns,
+                    mmap: self.csv_options.mmap && index < mapped_files,
+                    ..self.csv_options.clone()
+                };
+                let mut parser = CsvParser::from_path_with(path, options)?;
+                if let Some(selection) = &self.selection {
+                    parser.select_columns(selection)?;
+                }
+                if !self.renames.is_empty() {
+                    parser.rename_columns(&self.renames)?;
+                }
+                Ok(parser)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // Index detection reads the profile's distinct counts
+        let inference_config = InferenceConfig {
+            has_headers,
+            profile: self.inference.profile || self.index_detect,
+            ..self.inference.clone()
+        };
+
+        let mut schema = match &self.schema_in {
+            Some(path) => {
+                status!(self, "Loading schema from: {}", path.display());
+                let mut schema = TableSchema::from_json_file(path)?;
+                schema.validate_headers(&parsers[0].headers())?;
+                if self.table.is_some() {
+                    schema.table_name = table_name;
+                }
+                schema
+            }
+            None if self.force_text => {
+                let inferred = if self.union_schema { parsers.len() } else { 1 };
+                let mut names: Vec<String> = Vec::new();
+                for parser in parsers.iter().take(inferred) {
+                    for header in parser.headers() {
+                        if !names.contains(&header) {
+                            names.push(header);
+                        }
+                    }
+                }
+                TableSchema::all_text(table_name.clone(), names)
+            }
+            None => {
+                let inferred = if self.union_schema { parsers.len() } else { 1 };
+                let mut schema: Option<TableSchema> = None;
+                for (path, parser) in csv_paths.iter().zip(&mut parsers).take(inferred) {
+                    status!(self, "Analyzing CSV file: {}", path.display());
+                    let file_schema = parser.infer_schema(table_name.clone(), &inference_config)?;
+                    schema = Some(match schema {
+                        Some(schema) => schema.merge(&file_schema)?,
+                        None => file_schema,
+                    });
+                }
+                let mut schema = schema.expect("at least one file is inferred");
+
+                // Uniqueness is checked per file, and ids may restart in each
+                if inference_config.detect_pk && csv_paths.len() > 1 {
+                    tracing::warn!(
+                        "--detect-pk checks one file at a time; no primary key is detected across {} files",
+                        csv_paths.len()
+                    );
+                    schema.forget_uniqueness();
+                }
+                schema
+            }
+        };
+
+        // Schema column of each CSV column, per file
+        let first_headers = parsers[0].headers();
+        let layouts = csv_paths
+            .iter()
+            .zip(&parsers)
+            .map(|(path, parser)| {
+                let headers = parser.headers();
+                if self.union_schema {
+                    return Ok(headers
+                        .iter()
+                        .map(|header| {
+                            schema
+                                .columns
+                                .iter()
+                                .position(|c| c.name == *header)
+                                .expect("the union has every file's columns")
+                        })
+                        .collect::<Vec<usize>>());
+                }
+                if headers != first_headers {
+                    return Err(LoaderError::SchemaInferenceError(format!(
+                        "{} has columns ({}) but {} has ({}); use --union-schema to combine them",
+                        path.display(),
+                        headers.join(", "),
+                        first_path.display(),
+                        first_headers.join(", ")
+                    )));
+                }
+                Ok((0..headers.len()).collect())
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut table_name = schema.table_name.clone();
+        TableSchema::validate_table_name(&table_name)?;
+
+        // Nothing was sampled to be unsure about
+        if !self.force_text {
+            if let Some(min) = self.min_confidence {
+                check_confidence(&schema, min, self.strict, &self.column_types)?;
+            }
+            check_int_caps(&schema, self.inference.max_int_type, self.strict, &self.column_types)?;
+        }
+
+        schema.apply_type_overrides(&self.column_types)?;
+        let transforms = transform_columns(&schema, &self.transforms)?;
+
+        if self.all_nullable {
+            schema.set_nullability(true);
+        } else if self.no_nulls {
+            let had_nulls = schema.set_nullability(false);
+            if !had_nulls.is_empty() {
+                tracing::warn!(
+                    "--no-nulls makes nullable columns NOT NULL; loading their nulls will fail: {}",
+                    had_nulls.join(", ")
+                );
+            }
+        }
+
+        if self.sanitize_columns {
+            let renamed = schema.sanitize_column_names();
+            if self.verbose {
+                for (original, sanitized) in &renamed {
+                    status!(self, "Column renamed: {} -> {}", original, sanitized);
+                }
+            }
+        }
+
+        if let Some(name) = &self.primary_key {
+            schema.set_primary_key(name)?;
+        } else if let Some(pk) = &schema.primary_key {
+            if inference_config.detect_pk && inference_config.sample_size != 0 {
+                tracing::warn!(
+                    "Primary key '{}' detected from a sample of {} rows; use --sample-size 0 to check every row",
+                    pk,
+                    inference_config.sample_size
+                );
+            }
+        }
+
+        if let Some(path) = &self.schema_out {
+            schema.to_json_file(path)?;
+            status!(self, "Schema written to: {}", path.display());
+        }
+
+        if let Some(path) = &self.report {
+            InferenceReport::new(&schema, self.min_confidence).to_json_file(path)?;
+            status!(self, "Inference report written to: {}", path.display());
+        }
+
+        // A default may name a CSV column left out of the load, which a
+        // created table still gets, or a column only the existing table has
+        let csv_headers = parsers[0].all_headers();
+        let mut ddl_options = self.ddl_options.clone();
+        ddl_options.omitted_columns = ddl_options.defaults
+            .iter()
+            .map(|(name, _)| name)
+            .filter(|name| csv_headers.contains(name) && !schema.columns.iter().any(|c| &c.name == *name))
+            .cloned()
+            .collect();
+        if self.dry_run || self.ddl_out.is_some() || self.drop_table {
+            schema.validate_ddl_options(&ddl_options)?;
+        }
+
+        if let Some(path) = &self.ddl_out {
+            schema.to_ddl_file(path, &ddl_options)?;
+            status!(self, "CREATE TABLE SQL written to: {}", path.display());
+        }
+
+        // Display schema
+        let source = if self.schema_in.is_some() {
+            "Loaded"
+        } else if self.force_text {
+            "All-TEXT"
+        } else {
+            "Inferred"
+        };
+        status!(self, "\n{} Schema:", source);
+        status!(self, "Table: {}", schema.table_name);
+        status!(self, "Columns:");
+        for col in &schema.columns {
+            let nullable = if col.nullable { "NULL" } else { "NOT NULL" };
+            let confidence = (col.confidence() * 100.0) as u8;
+            status!(self, 
+                "  - {} {} {} ({}% confidence, {} samples, {} nulls)",
+                col.name,
+                col.sql_type.to_sql(),
+                nullable,
+                confidence,
+                col.sample_count,
+                col.null_count
+            );
+            if let Some(profile) = &col.profile {
+                status!(self, "      {}", profile.summary(&col.sql_type, col.max_length));
+            }
+        }
+        status!(self);
+
+        let detected = if self.index_detect { schema.index_candidates() } else { Vec::new() };
+        if !detected.is_empty() {
+            status!(self, "Suggested indexes:");
+            for (column, reason) in &detected {
+                status!(self, "  - {} ({})", schema.columns[*column].name, reason);
+            }
+            status!(self);
+        }
+
+        // Dry run - exit after showing schema
+        if self.dry_run {
+            status!(self, "CREATE TABLE SQL:");
+            status!(self, "{}", schema.to_create_table_sql_with(&ddl_options));
+            let index_columns = self.index_columns(&schema, &detected, None);
+            for (column, name) in index_columns.iter().zip(index_names(&table_name, &index_columns)) {
+                status!(self, "{};", create_index_sql(&table_name, &name, column, self.ddl_options.quote_identifiers));
+            }
+            if self.check_db {
+                self.check_table(&schema, &table_name).await?;
+            }
+            if let Some(name) = &self.rename_to {
+                status!(self, "The table would then be renamed to '{}'.", name);
+            }
+            status!(self, "\nDry run complete. No data loaded.");
+            return Ok(LoadStats {
+                table_name,
+                ..Default::default()
+            });
+        }
+
+        let mut checkpoint = match &self.checkpoint_file {
+            Some(path) => Some(Checkpoint::open(path)?),
+            None => None,
+        };
+        let resume_rows = checkpoint.as_ref().map_or(0, Checkpoint::rows);
+        if resume_rows > 0 {
+            if self.drop_table || self.truncate {
+                return Err(LoaderError::ConfigError(format!(
+                    "Checkpoint records {} loaded rows; --drop-table and --truncate would discard them",
+                    resume_rows
+                )));
+            }
+            status!(self, "Resuming from checkpoint: skipping {} rows", resume_rows);
+        }
+
+        // Connect to database
+        status!(self, "Connecting to database...");
+        let db = DbConnection::connect_with(&self.connection_string, &self.connect_options).await?;
+
+        // The ledger records files under the name the table ends up with
+        let ledger_name = self.rename_to.clone().unwrap_or_else(|| table_name.clone());
+        let ledger_files = match &self.ledger {
+            Some(ledger) => self.check_ledger(&db, ledger, csv_paths, &ledger_name).await?,
+            None => Vec::new(),
+        };
+        let skipped_files = ledger_files.iter().filter(|(_, load)| !load).count();
+        if skipped_files == csv_paths.len() {
+            status!(self, "Every file was already loaded into '{}'; nothing to do.", ledger_name);
+            return Ok(LoadStats {
+                table_name,
+                ..Default::default()
+            });
+        }
+
+        // An atomic load's transaction starts before the table is dropped,
+        // truncated or created, so a failed load also undoes those; FREEZE
+        // relies on it
+        let atomic = self.is_atomic();
+        if atomic {
+            db.begin_transaction().await?;
+        }
+
+        // --swap loads a copy of the existing table, made next to it in its
+        // schema, and replaces it once every row is in
+        let swap_target = if self.swap {
+            let Some(namespace) = db.table_schema(&table_name).await? else {
+                return Err(swap_missing(&table_name));
+            };
+            let (_, table) = split_table_name(&table_name);
+            let staging = format!("{}.{}_new", namespace, table);
+            status!(self, "Loading into '{}' to swap in for '{}'...", staging, table_name);
+            // Only a table left over from an interrupted swap is replaced
+            if db.table_exists(&staging).await? {
+                if db.table_comment(&staging).await?.as_deref() != Some(SWAP_STAGING_COMMENT) {
+                    return Err(LoaderError::ConfigError(format!(
+                        "Table '{}' already exists and was not made by --swap; drop or rename it to swap '{}'",
+                        staging, table_name
+                    )));
+                }
+                db.drop_table(&staging).await?;
+            }
+            db.create_table_like(&staging, &table_name).await?;
+            db.set_table_comment(&staging, Some(SWAP_STAGING_COMMENT)).await?;
+            schema.table_name = staging.clone();
+            Some(std::mem::replace(&mut table_name, staging))
+        } else {
+            None
+        };
+
+        // Handle table creation/dropping
+        if self.drop_table {
+            status!(self, "Dropping existing table...");
+            db.drop_table(&table_name).await?;
+        }
+
+        let table_exists = db.table_exists(&table_name).await?;
+
+        if !table_exists {
+            schema.validate_ddl_options(&ddl_options)?;
+        } else if !ddl_options.defaults.is_empty() {
+            let table_columns = db.table_columns(&table_name).await?;
+            let unknown = ddl_options.defaults
+                .iter()
+                .find(|(name, _)| !table_columns.iter().any(|(column, _)| column == name));
+            if let Some((name, _)) = unknown {
+                return Err(LoaderError::ConfigError(format!(
+                    "Unknown column '{}' in default (table '{}' has: {})",
+                    name,
+                    table_name,
+                    table_columns.iter().map(|(column, _)| column.as_str()).collect::<Vec<_>>().join(", ")
+                )));
+            }
+        }
+
+        if self.copy_options.freeze && table_exists && !self.truncate && !self.swap {
+            return Err(LoaderError::ConfigError(format!(
+                "--freeze needs a table created or truncated by this load, but '{}' exists; add --truncate or --drop-table",
+                table_name
+            )));
+        }
+
+        if self.truncate {
+            if !table_exists {
+                return Err(LoaderError::ConfigError(format!(
+                    "Table '{}' does not exist, so it cannot be truncated.",
+                    table_name
+                )));
+            }
+            status!(self, "Truncating table...");
+            db.truncate_table(&table_name, self.restart_identity).await?;
+        }
+
+        // Replacing the table's rows makes its ledger entries stale
+        if let Some(ledger) = &self.ledger {
+            if self.replaces_rows() {
+                db.forget_loads(&ledger.table, &ledger_name).await?;
+            }
+        }
+
+        if !table_exists {
+            if self.creates_missing_table() {
+                status!(self, "Creating table...");
+                let create_sql = schema.to_create_table_sql_with(&ddl_options);
+                db.create_table(&create_sql).await?;
+            } else {
+                return Err(missing_table(&table_name));
+            }
+        }
+
+        let target_columns = if !self.maps_columns() {
+            None
+        } else {
+            let table_columns = db.table_columns(&table_name).await?;
+            let targets = self.map_targets(&schema, &table_columns)?;
+            Some((targets, table_columns))
+        };
+        check_bind_types(&schema, target_columns.as_ref().map(|(targets, _)| targets.as_slice()), &self.bind_types)?;
+
+        // Check --index columns before loading rather than once the data
+        // is in
+        let index_columns = self.index_columns(
+            &schema,
+            &detected,
+            target_columns.as_ref().map(|(targets, _)| targets.as_slice()),
+        );
+        if !self.indexes.is_empty() {
+            let table_columns = match &target_columns {
+                Some((_, table_columns)) => table_columns.clone(),
+                None => db.table_columns(&table_name).await?,
+            };
+            let unknown: Vec<&str> = self.indexes
+                .iter()
+                .filter(|column| !table_columns.iter().any(|(name, _)| name == *column))
+                .map(String::as_str)
+                .collect();
+            if !unknown.is_empty() {
+                return Err(LoaderError::ConfigError(format!(
+                    "--index names columns table '{}' doesn't have: {}",
+                    table_name,
+                    unknown.join(", ")
+                )));
+            }
+        }
+
+        // Catch type conflicts with an existing table before COPY trips
+        // over them
+        if table_exists {
+            let loaded = loaded_types(&schema, target_columns.as_ref().map(|(targets, _)| targets.as_slice()));
+            let mismatches = db.type_mismatches(&table_name, &loaded).await?;
+            check_table_types(&table_name, &mismatches, self.strict_types)?;
+        }
+
+        // INSERT casts every value to its column's type, so it must be the
+        // table's type rather than the inferred one
+        let table_types = if target_columns.is_none() && self.copy_options.method == LoadMethod::Insert {
+            Some(db.table_columns(&table_name).await?)
+        } else {
+            None
+        };
+
+        // Each file loads the columns it has, in its own order
+        let file_schemas: Vec<TableSchema> = layouts
+            .iter()
+            .map(|layout| TableSchema {
+                columns: layout.iter().map(|&column| schema.columns[column].clone()).collect(),
+                ..schema.clone()
+            })
+            .collect();
+
+        // Each file's position of every transformed column
+        let file_transforms: Vec<Vec<(usize, ValueTransform)>> = layouts
+            .iter()
+            .map(|layout| {
+                transforms
+                    .iter()
+                    .filter_map(|(column, transform)| {
+                        layout.iter().position(|c| c == column).map(|position| (position, *transform))
+                    })
+                    .collect()
+            })
+            .collect();
+
+        // Each INSERT binds every cell of its batch, up to the server's limit
+        let mut batch_size = self.batch_config.batch_size;
+        if self.copy_options.method == LoadMethod::Insert {
+            let width = file_schemas.iter().map(|s| s.columns.len()).max().unwrap_or(1);
+            let max = insert::max_rows(width);
+            if batch_size > max {
+                tracing::warn!("Batch size lowered to {} so each INSERT fits {} columns", max, width);
+                batch_size = max;
+            }
+        }
+
+        // Reset inferred parsers to the beginning of their file, unless
+        // they're replaying the rows buffered during inference
+        if self.schema_in.is_none() && !self.force_text && !inference_config.buffer_sample {
+            let inferred = if self.union_schema { parsers.len() } else { 1 };
+            for (path, parser) in csv_paths.iter().zip(&mut parsers).take(inferred) {
+                parser.reset(path, has_headers)?;
+            }
+        }
+
+        // Verbose loads time every batch
+        let batch_config = BatchConfig {
+            timings: self.batch_config.timings || self.verbose,
+            ..self.batch_config.clone()
+        };
+        let batch_processor = BatchProcessor::new(batch_config).with_cancel(self.cancel.clone());
+
+        let mut reject_writer = match &self.error_file {
+            Some(path) => Some(RejectWriter::create(path)?),
+            None => None,
+        };
+
+        // Set up progress tracker
+        let total_rows_expected = if self.count_rows {
+            status!(self, "Counting rows...");
+            let mut total = 0;
+            for path in csv_paths {
+                total += parser::count_records(path, &self.csv_options)?;
+            }
+            Some(self.limit.map_or(total, |limit| total.min(resume_rows + limit)))
+        } else {
+            None
+        };
+        let progress: Arc<dyn ProgressSink> = match &self.progress {
+            Some(sink) => Arc::clone(sink),
+            None if self.quiet => Arc::new(NoProgress),
+            None => match self.progress_format {
+                ProgressFormat::Bar => Arc::new(ProgressTracker::new(total_rows_expected, false)),
+                ProgressFormat::Json => Arc::new(JsonProgress::new()),
+            },
+        };
+        let start = Instant::now();
+        let mut processed = 0u64;
+
+        // Load data
+        status!(self, "Loading data...");
+
+        // Extra connections for parallel workers; the primary connection is worker 0
+        let extra_connections = DbConnection::connect_many(
+            &self.connection_string,
+            self.parallelism - 1,
+            &self.connect_options,
+        ).await?;
+        let mut total_rows = 0u64;
+        let mut skipped_rows = 0u64;
+        let mut bytes_read = 0u64;
+        let mut ragged = RaggedRows::default();
+
+        let rows_before = if self.verify_count {
+            Some(db.row_count(&table_name).await?)
+        } else {
+            None
+        };
+
+        let mut result = Ok(());
+        let files = csv_paths.iter().zip(parsers).zip(file_schemas.iter().zip(&file_transforms));
+        for (index, ((path, parser), (file_schema, transforms))) in files.enumerate() {
+            let ledger_file = ledger_files.get(index);
+            if ledger_file.is_some_and(|(_, load)| !load) {
+                continue;
+            }
+            let rows_before_file = total_rows;
+
+            let loaders: Vec<CopyLoader> = std::iter::once(&db)
+                .chain(extra_connections.iter())
+                .map(|conn| {
+                    let loader = CopyLoader::with_options(conn.client(), file_schema, self.copy_options.clone())
+                        .with_transforms(transforms.clone());
+                    let loader = match (&target_columns, &table_types) {
+                        (Some((targets, table_columns)), _) => loader.with_target_columns(targets, table_columns),
+                        (None, Some(table_columns)) => loader.with_table_types(file_schema, table_columns),
+                        (None, None) => loader,
+                    };
+                    loader.with_bind_types(&self.bind_types)
+                })
+                .collect();
+
+            // Skip rows a checkpoint recorded as loaded, which may run on
+            // past the end of this file
+            let mut parser = parser;
+            match parser.skip_records(resume_rows - skipped_rows) {
+                Ok(skipped) => {
+                    skipped_rows += skipped;
+                    processed += skipped;
+                }
+                Err(e) => {
+                    result = Err(if csv_paths.len() > 1 { e.in_file(path) } else { e });
+                    break;
+                }
+            }
+            // Every earlier row has been skipped or has finished loading
+            let first_row = processed;
+            let remaining = match self.limit {
+                Some(limit) => (limit - (processed - skipped_rows)) as usize,
+                None => usize::MAX,
+            };
+            if remaining == 0 {
+                break;
+            }
+
+            // Parse on a dedicated thread so parsing overlaps with loading
+            let (tx, batches) = mpsc::channel(self.channel_depth);
+            let batch_bytes = self.batch_config.batch_bytes;
+            let cancel = self.cancel.clone();
+            let file_bytes = parser.byte_counter();
+            let malformed = parser.malformed_lines();
+            let producer = std::thread::spawn(move || {
+                BatchIterator::new(parser.numbered_records().take(remaining), batch_size)
+                    .with_max_bytes(batch_bytes)
+                    .with_cancel(cancel)
+                    .feed(tx);
+                parser
+            });
+
+            result = batch_processor
+                .process_parallel(&loaders, batches, |rows, outcome| {
+                    total_rows += outcome.loaded;
+                    processed += rows.end - rows.start;
+                    progress.on_bytes(bytes_read + file_bytes.get());
+                    progress.on_batch(processed, total_rows_expected);
+
+                    // Lines the parser dropped for bad quoting count as rejects too
+                    let dropped = malformed.take();
+                    batch_processor.count_rejected(dropped.len())?;
+                    for rejected in outcome.rejected.iter().chain(&dropped) {
+                        tracing::warn!("Rejected line {}: {}", rejected.line, rejected.error);
+                        if let Some(writer) = reject_writer.as_mut() {
+                            writer.write(rejected)?;
+                        }
+                    }
+
+                    if let Some(checkpoint) = checkpoint.as_mut() {
+                        checkpoint.commit(first_row + rows.start..first_row + rows.end)?;
+                    }
+                    Ok(())
+                })
+                .await;
+            let mut parser = producer.join().unwrap_or_else(|e| std::panic::resume_unwind(e));
+            bytes_read += parser.bytes_read();
+
+            let file_ragged = parser.ragged_rows();
+            ragged.padded += file_ragged.padded;
+            ragged.truncated += file_ragged.truncated;
+
+            // Lines dropped after the last batch, or in a file with none
+            let dropped = parser.take_malformed_lines();
+            for rejected in &dropped {
+                tracing::warn!("Rejected line {}: {}", rejected.line, rejected.error);
+            }
+            if result.is_ok() {
+                result = batch_processor.count_rejected(dropped.len());
+            }
+            if let (Some(writer), Ok(())) = (reject_writer.as_mut(), &result) {
+                result = dropped.iter().try_for_each(|rejected| writer.write(rejected));
+            }
+
+            if let Err(e) = result {
+                result = Err(if csv_paths.len() > 1 { e.in_file(path) } else { e });
+                break;
+            }
+
+            // Batches in flight have finished; report what stays loaded
+            if self.cancel.is_cancelled() {
+                let rows_loaded = if atomic { 0 } else { total_rows };
+                result = Err(LoaderError::Interrupted { rows_loaded });
+                break;
+            }
+
+            // In an atomic load the entry commits or rolls back with the rows
+            if let (Some(ledger), Some((fingerprint, _))) = (&self.ledger, ledger_file) {
+                let rows = total_rows - rows_before_file;
+                result = db.record_load(&ledger.table, &ledger_name, fingerprint, rows).await;
+                if let Err(e) = result {
+                    result = Err(if csv_paths.len() > 1 { e.in_file(path) } else { e });
+                    break;
+                }
+            }
+        }
+
+        let stats_now = |rows_loaded: u64| {
+            let elapsed = start.elapsed();
+            let per_sec = |count: u64| {
+                if elapsed.as_secs_f64() > 0.0 {
+                    count as f64 / elapsed.as_secs_f64()
+                } else {
+                    0.0
+                }
+            };
+            LoadStats {
+                table_name: table_name.clone(),
+                files: csv_paths.len() - skipped_files,
+                rows_loaded,
+                rows_rejected: batch_processor.rejected_total(),
+                rows_padded: ragged.padded,
+                rows_truncated: ragged.truncated,
+                rows_skipped: skipped_rows,
+                bytes_read,
+                retries: batch_processor.retries_total(),
+                elapsed,
+                throughput: per_sec(processed - skipped_rows),
+                bytes_per_sec: per_sec(bytes_read),
+                ..Default::default()
+            }
+        };
+
+        if let Err(e) = result {
+            progress.on_error(&e);
+            *partial = stats_now(if atomic { 0 } else { total_rows });
+            if let Some(writer) = reject_writer.as_mut() {
+                writer.flush()?;
+            }
+            if atomic {
+                status!(self, "Rolling back...");
+                db.rollback_transaction().await?;
+            }
+            return Err(e);
+        }
+
+        if atomic {
+            db.commit_transaction().await?;
+        }
+
+        if let Some(writer) = reject_writer.as_mut() {
+            writer.flush()?;
+        }
+
+        if let Some(checkpoint) = checkpoint {
+            checkpoint.remove()?;
+        }
+
+        let mut stats = stats_now(total_rows);
+        if self.batch_config.timings || self.verbose {
+            status!(self, "{}", batch_processor.timings().summary());
+        }
+
+        if let Some(before) = rows_before {
+            let actual = db.row_count(&table_name).await? as i64 - before as i64;
+            if actual != total_rows as i64 {
+                let e = LoaderError::CountMismatch { expected: total_rows, actual };
+                progress.on_error(&e);
+                *partial = stats;
+                return Err(e);
+            }
+            status!(self, "Verified row count: table gained {} rows", actual);
+        }
+
+        // Indexes go on after the data, which loads faster without them
+        if !index_columns.is_empty() {
+            status!(self, "Creating indexes...");
+            let index_start = Instant::now();
+            for (column, name) in index_columns.iter().zip(index_names(&table_name, &index_columns)) {
+                let sql = create_index_sql(&table_name, &name, column, self.ddl_options.quote_identifiers);
+                // Only indexes this load creates are reported
+                let created = match db.index_name_taken(&table_name, &name).await {
+                    Ok(true) => Ok(false),
+                    Ok(false) => db.execute(&sql).await.map(|_| true),
+                    Err(e) => Err(e),
+                };
+                match created {
+                    Ok(true) => stats.indexes_created.push(name),
+                    Ok(false) => tracing::warn!("Index '{}' on {} not created: the name is already taken", name, column),
+                    Err(e) => {
+                        progress.on_error(&e);
+                        *partial = stats;
+                        return Err(e);
+                    }
+                }
+            }
+            stats.index_time = index_start.elapsed();
+        }
+
+        if let Some(target) = swap_target {
+            status!(self, "Swapping '{}' in for '{}'...", table_name, target);
+            match db.swap_table(&table_name, &target).await {
+                Ok(renamed) => {
+                    for index in &mut stats.indexes_created {
+                        if let Some((_, new_name)) = renamed.iter().find(|(old, _)| old == index) {
+                            *index = new_name.clone();
+                        }
+                    }
+                    stats.table_name = target;
+                }
+                Err(e) => {
+                    progress.on_error(&e);
+                    *partial = stats;
+                    return Err(e);
+                }
+            }
+        }
+
+        if let Some(name) = &self.rename_to {
+            status!(self, "Renaming '{}' to '{}'...", table_name, name);
+            if let Err(e) = db.rename_table(&table_name, name).await {
+                progress.on_error(&e);
+                *partial = stats;
+                return Err(e);
+            }
+            stats.table_name = name.clone();
+        }
+
+        progress.on_finish(&stats);
+
+        Ok(stats)
+    }
+
+    /// Whether the load replaces the table's existing rows, leaving
+    /// nothing a reloaded file could duplicate
+    fn replaces_rows(&self) -> bool {
+        self.drop_table || self.truncate || self.swap
+    }
+
+    /// Fingerprint every file and decide from the ledger whether to load it
+    ///
+    /// Files already loaded into `table_name` fail the load or are left
+    /// out, as `on_duplicate` says, unless `force` is set.
+    async fn check_ledger(
+        &self,
+        db: &DbConnection,
+        ledger: &LedgerOptions,
+        csv_paths: &[&Path],
+        table_name: &str,
+    ) -> Result<Vec<(FileFingerprint, bool)>> {
+        db.create_ledger(&ledger.table).await?;
+
+        let mut files = Vec::with_capacity(csv_paths.len());
+        for path in csv_paths {
+            let fingerprint = FileFingerprint::of(path, ledger.cheap)?;
+            let loaded_at = if self.replaces_rows() {
+                None
+            } else {
+                db.ledger_entry(&ledger.table, table_name, &fingerprint).await?
+            };
+
+            let load = match loaded_at {
+                None => true,
+                Some(loaded_at) if ledger.force => {
+                    tracing::warn!("{} was already loaded into '{}' at {}; loading it again", path.display(), table_name, loaded_at);
+                    true
+                }
+                Some(loaded_at) => match ledger.on_duplicate {
+                    OnDuplicateFile::Error => {
+                        return Err(LoaderError::DuplicateFile {
+                            path: path.display().to_string(),
+                            table: table_name.to_string(),
+                            loaded_at,
+                        });
+                    }
+                    OnDuplicateFile::Skip => {
+                        tracing::warn!("Skipping {}: already loaded into '{}' at {}", path.display(), table_name, loaded_at);
+                        false
+                    }
+                },
+            };
+            files.push((fingerprint, load));
+        }
+        Ok(files)
+    }
+
+    /// Whether a missing table is created: always with `--create-table`,
+    /// otherwise unless `--strict-existence`, `--drop-table` or
+    /// `--truncate` says the table is expected to exist
+    ///
+    /// | table   | flags                        | result  |
+    /// |---------|------------------------------|---------|
+    /// | exists  | any                          | load    |
+    /// | missing | `--create-table`             | create  |
+    /// | missing | none                         | create  |
+    /// | missing | `--strict-existence`         | error   |
+    /// | missing | `--drop-table` or `--truncate` | error |
+    ///
+    /// With `--swap` the table loaded is always a new one.
+    fn creates_missing_table(&self) -> bool {
+        self.create_table || self.swap || !(self.strict_existence || self.drop_table || self.truncate)
+    }
+
+    /// Table columns to index: the `--index` columns, then the detected
+    /// ones under the names they load into, without repeats
+    fn index_columns(
+        &self,
+        schema: &TableSchema,
+        detected: &[(usize, &str)],
+        targets: Option<&[(usize, String)]>,
+    ) -> Vec<String> {
+        let detected = detected.iter().filter_map(|(column, _)| match targets {
+            Some(targets) => targets.iter().find(|(index, _)| index == column).map(|(_, name)| name.clone()),
+            None => Some(schema.columns[*column].name.clone()),
+        });
+
+        let mut columns: Vec<String> = Vec::new();
+        for column in self.indexes.iter().cloned().chain(detected) {
+            if !columns.contains(&column) {
+                columns.push(column);
+            }
+        }
+        columns
+    }
+
+    /// Whether CSV columns load into table columns chosen by `--map`,
+    /// `--use-table-order` or `--target-columns` rather than by name
+    fn maps_columns(&self) -> bool {
+        !self.column_map.is_empty() || self.use_table_order || !self.target_columns.is_empty()
+    }
+
+    /// CSV column indices and the table columns they load into, as chosen
+    /// by `--map`, `--use-table-order` or `--target-columns`
+    fn map_targets(&self, schema: &TableSchema, table_columns: &[(String, Option<SqlType>)]) -> Result<Vec<(usize, String)>> {
+        let names: Vec<String> = table_columns.iter().map(|(name, _)| name.clone()).collect();
+        if !self.target_columns.is_empty() {
+            schema.target_table_columns(&self.target_columns, &self.column_map, &names)
+        } else if self.use_table_order {
+            schema.order_by_table_columns(&names)
+        } else {
+            schema.map_to_table_columns(&self.column_map, &names)
+        }
+    }
+
+    /// Compare the schema with the target table for a dry run, over a
+    /// read-only connection
+    ///
+    /// Loaded columns the table lacks are an error, as the load would fail
+    /// on them; type conflicts warn, or fail with `strict_types`.
+    async fn check_table(&self, schema: &TableSchema, table_name: &str) -> Result<()> {
+        status!(self, "\nChecking table '{}' (read-only)...", table_name);
+        let db = DbConnection::connect_with(&self.connection_string, &self.connect_options).await?;
+        db.execute("SET SESSION CHARACTERISTICS AS TRANSACTION READ ONLY").await?;
+
+        // --swap loads a copy of the existing table
+        if self.swap && !db.table_exists(table_name).await? {
+            return Err(swap_missing(table_name));
+        }
+        if self.drop_table || !db.table_exists(table_name).await? {
+            if !self.creates_missing_table() {
+                return Err(missing_table(table_name));
+            }
+            let how = if self.drop_table { "dropped and created" } else { "created" };
+            status!(self, "The table would be {} as above.", how);
+            return Ok(());
+        }
+
+        let table_columns = db.table_columns(table_name).await?;
+        let targets = if !self.maps_columns() {
+            None
+        } else {
+            Some(self.map_targets(schema, &table_columns)?)
+        };
+        let loaded = loaded_types(schema, targets.as_deref());
+
+        let missing: Vec<&str> = loaded
+            .iter()
+            .filter(|(name, _)| !table_columns.iter().any(|(column, _)| column == name))
+            .map(|(name, _)| name.as_str())
+            .collect();
+        if !missing.is_empty() {
+            return Err(LoaderError::ConfigError(format!(
+                "Table '{}' has no column {}",
+                table_name,
+                missing.join(", ")
+            )));
+        }
+
+        let unloaded: Vec<&str> = table_columns
+            .iter()
+            .filter(|(column, _)| !loaded.iter().any(|(name, _)| name == column))
+            .map(|(column, _)| column.as_str())
+            .collect();
+        if !unloaded.is_empty() {
+            status!(self, "Table columns not loaded (left to their defaults): {}", unloaded.join(", "));
+        }
+
+        let mismatches = db.type_mismatches(table_name, &loaded).await?;
+        if mismatches.is_empty() {
+            status!(self, "Every loaded column fits its table column's type.");
+        }
+        check_table_types(table_name, &mismatches, self.strict_types)
+    }
+}
+
+fn missing_table(table_name: &str) -> LoaderError {
+    LoaderError::ConfigError(format!(
+        "Table '{}' does not exist. Use --create-table to create it.",
+        table_name
+    ))
+}
+
+fn swap_missing(table_name: &str) -> LoaderError {
+    LoaderError::ConfigError(format!(
+        "Table '{}' does not exist, so --swap has nothing to replace",
+        table_name
+    ))
+}
+
+/// Names and types of the columns loaded, in table terms when `targets`
+/// maps CSV columns to table columns
+fn loaded_types(schema: &TableSchema, targets: Option<&[(usize, String)]>) -> Vec<(String, SqlType)> {
+    match targets {
+        Some(targets) => targets
+            .iter()
+            .map(|(index, name)| (name.clone(), schema.columns[*index].sql_type.clone()))
+            .collect(),
+        None => schema.columns.iter().map(|c| (c.name.clone(), c.sql_type.clone())).collect(),
+    }
+}
+
+/// Warn about, or with `strict` reject, loaded types an existing table's
+/// columns can't hold
+fn check_table_types(table_name: &str, mismatches: &[(String, SqlType, SqlType)], strict: bool) -> Result<()> {
+    if mismatches.is_empty() {
+        return Ok(());
+    }
+
+    let details: Vec<String> = mismatches
+        .iter()
+        .map(|(column, loaded, table)| format!("{} ({} into {})", column, loaded, table))
+        .collect();
+    let message = format!(
+        "Column types don't match table '{}': {}; use --column-type to override",
+        table_name,
+        details.join(", ")
+    );
+    if strict {
+        return Err(LoaderError::ConfigError(message));
+    }
+    tracing::warn!("{}", message);
+    Ok(())
+}
+
+/// Schema column index of each transformed column, in the order given
+fn transform_columns(
+    schema: &TableSchema,
+    transforms: &[(String, ValueTransform)],
+) -> Result<Vec<(usize, ValueTransform)>> {
+    transforms
+        .iter()
+        .map(|(name, transform)| {
+            let index = schema.columns.iter().position(|c| c.name == *name).ok_or_else(|| {
+                LoaderError::ConfigError(format!(
+                    "Unknown column '{}' in a value transform (available: {})",
+                    name,
+                    schema.column_names()
+                ))
+            })?;
+            Ok((index, *transform))
+        })
+        .collect()
+}
+
+/// Check that every `--bind-type` names a loaded column: a mapped target
+/// column, or a schema column when loading by name
+fn check_bind_types(
+    schema: &TableSchema,
+    targets: Option<&[(usize, String)]>,
+    bind_types: &[(String, String)],
+) -> Result<()> {
+    let loaded: Vec<&str> = match targets {
+        Some(targets) => targets.iter().map(|(_, name)| name.as_str()).collect(),
+        None => schema.columns.iter().map(|c| c.name.as_str()).collect(),
+    };
+
+    match bind_types.iter().find(|(name, _)| !loaded.contains(&name.as_str())) {
+        Some((name, _)) => Err(LoaderError::ConfigError(format!(
+            "Unknown column '{}' in --bind-type (available: {})",
+            name,
+            loaded.join(", ")
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Warn about, or with `strict` reject, columns inferred with low confidence
+///
+/// Columns given an explicit type override are not checked.
+fn check_confidence(
+    schema: &TableSchema,
+    min: f64,
+    strict: bool,
+    overrides: &[(String, SqlType)],
+) -> Result<()> {
+    let overridden = |name: &str| overrides.iter().any(|(column, _)| column == name);
+
+    let mut low = Vec::new();
+    for warning in column_warnings(schema, min) {
+        if overridden(warning.column()) {
+            continue;
+        }
+        match &warning {
+            ColumnWarning::AllNull { .. } => tracing::warn!("{}", warning),
+            ColumnWarning::LowConfidence { column, confidence } => low.push(format!("{} ({:.2})", column, confidence)),
+            ColumnWarning::TypeDegraded { .. } => {}
+        }
+    }
+
+    if low.is_empty() {
+        return Ok(());
+    }
+
+    let message = format!("Columns below confidence {:.2}: {}", min, low.join(", "));
+    if strict {
+        return Err(LoaderError::SchemaInferenceError(message));
+    }
+    tracing::warn!("{}", message);
+    Ok(())
+}
+
+/// Warn about, or with `strict` reject, integer columns degraded to TEXT
+/// by `--max-int-type`
+fn check_int_caps(
+    schema: &TableSchema,
+    cap: IntegerCap,
+    strict: bool,
+    overrides: &[(String, SqlType)],
+) -> Result<()> {
+    let capped: Vec<&str> = schema.columns
+        .iter()
+        .filter(|column| column.int_capped && !overrides.iter().any(|(name, _)| *name == column.name))
+        .map(|column| column.name.as_str())
+        .collect();
+
+    if capped.is_empty() {
+        return Ok(());
+    }
+
+    let message = format!(
+        "Columns with integers wider than {}, loaded as TEXT: {}",
+        cap.sql_type().to_sql(),
+        capped.join(", ")
+    );
+    if strict {
+        return Err(LoaderError::SchemaInferenceError(message));
+    }
+    tracing::warn!("{}", message);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ExtraFields;
+
+    #[test]
+    fn test_builder_rejects_invalid_settings() {
+        let builder = LoaderBuilder::new("postgresql://localhost/test");
+
+        assert!(builder.clone().build().is_ok());
+        assert!(builder.clone().parallelism(0).build().is_err());
+        assert!(builder.clone().limit(0).build().is_err());
+        assert!(builder.clone().atomic(true).parallelism(2).build().is_err());
+        assert!(builder.clone().min_confidence(1.5).build().is_err());
+        assert!(builder.clone().atomic(true).checkpoint_file("state.json").build().is_err());
+
+        assert!(builder.clone().swap(true).truncate(true, false).build().is_err());
+        assert!(builder.clone().rename_to("a.b.c").build().is_err());
+        assert!(builder.clone().rename_to("events").swap(true).build().is_err());
+        assert!(builder.clone().swap(true).target_columns(vec!["id".to_string()]).build().is_err());
+        assert!(builder.clone().force_text(true).schema_in("schema.json").build().is_err());
+        assert!(builder.clone().ledger(LedgerOptions::new("loads")).checkpoint_file("state.json").build().is_err());
+        assert!(builder.clone().ledger(LedgerOptions::new("a.b.c")).build().is_err());
+
+        let freeze = CopyOptions { freeze: true, ..Default::default() };
+        assert!(builder.clone().copy_options(freeze.clone()).build().is_err());
+        assert!(builder.clone().atomic(true).copy_options(freeze.clone()).build().is_ok());
+        let insert = CopyOptions { method: LoadMethod::Insert, ..freeze };
+        assert!(builder.clone().atomic(true).copy_options(insert).build().is_err());
+        let text_insert = CopyOptions { method: LoadMethod::Insert, wire_format: WireFormat::Text, ..Default::default() };
+        assert!(builder.clone().copy_options(text_insert).build().is_err());
+
+        let skip = BatchConfig {
+            on_error: OnError::Skip,
+            ..Default::default()
+        };
+        assert!(builder.clone().atomic(true).batch_config(skip.clone()).build().is_err());
+        assert!(builder.atomic_with_savepoints(true).batch_config(BatchConfig { savepoints: true, ..skip }).build().is_ok());
+    }
+
+    #[test]
+    fn test_missing_table_decision() {
+        let builder = LoaderBuilder::new("postgresql://localhost/test");
+        let creates = |builder: LoaderBuilder| builder.build().unwrap().creates_missing_table();
+
+        assert!(creates(builder.clone()));
+        assert!(creates(builder.clone().create_table(true)));
+        assert!(creates(builder.clone().create_table(true).drop_table(true)));
+        assert!(creates(builder.clone().create_table(true).truncate(true, false)));
+        assert!(!creates(builder.clone().strict_existence(true)));
+        assert!(!creates(builder.clone().drop_table(true)));
+        assert!(!creates(builder.clone().truncate(true, false)));
+        assert!(creates(builder.clone().strict_existence(true).swap(true)));
+        assert!(builder.strict_existence(true).create_table(true).build().is_err());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_creates_missing_table() {
+        let db = DbConnection::connect("postgresql://localhost/test").await.unwrap();
+        db.drop_table("implied_create_test").await.unwrap();
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, b"id,name\n1,alice\n2,bob\n").unwrap();
+        let loader = LoaderBuilder::new("postgresql://localhost/test")
+            .table("implied_create_test")
+            .quiet(true);
+
+        for strict in [loader.clone().strict_existence(true), loader.clone().truncate(true, false)] {
+            let err = strict.build().unwrap().load(file.path()).await.unwrap_err();
+            assert!(err.to_string().contains("does not exist"), "{}", err);
+        }
+
+        let stats = loader.build().unwrap().load(file.path()).await.unwrap();
+        assert_eq!(stats.rows_loaded, 2);
+        assert_eq!(db.row_count("implied_create_test").await.unwrap(), 2);
+        db.drop_table("implied_create_test").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_infers_without_connecting() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, b"id,name\n1,a\n2,b,extra\n").unwrap();
+
+        let stats = LoaderBuilder::new("postgresql://invalid")
+            .table("people")
+            .csv_options(CsvOptions {
+                flexible: true,
+                extra_fields: ExtraFields::Drop,
+                ..Default::default()
+            })
+            .dry_run(true)
+            .build()
+            .unwrap()
+            .load(file.path())
+            .await
+            .unwrap();
+
+        assert_eq!(stats.table_name, "people");
+        assert_eq!(stats.rows_loaded, 0);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_custom_date_formats_load() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, b"id,day,at\n1,15.01.2024,15.01.2024 10:30\n2,29.02.2024,01.03.2024 23:05\n").unwrap();
+
+        let date_formats = vec!["%d.%m.%Y".to_string()];
+        let timestamp_formats = vec!["%d.%m.%Y %H:%M".to_string()];
+        let stats = LoaderBuilder::new("postgresql://localhost/test")
+            .table("date_format_test")
+            .drop_table(true)
+            .create_table(true)
+            .inference(InferenceConfig {
+                date_formats: date_formats.clone(),
+                timestamp_formats: timestamp_formats.clone(),
+                ..Default::default()
+            })
+            .copy_options(CopyOptions { date_formats, timestamp_formats, ..Default::default() })
+            .quiet(true)
+            .build()
+            .unwrap()
+            .load(file.path())
+            .await
+            .unwrap();
+        assert_eq!(stats.rows_loaded, 2);
+
+        let db = DbConnection::connect("postgresql://localhost/test").await.unwrap();
+        let row = db.client()
+            .query_one("SELECT pg_typeof(day)::text, day::text, at::text FROM date_format_test WHERE id = 2", &[])
+            .await
+            .unwrap();
+        assert_eq!(row.get::<_, String>(0), "date");
+        assert_eq!(row.get::<_, String>(1), "2024-02-29");
+        assert_eq!(row.get::<_, String>(2), "2024-03-01 23:05:00");
+        db.drop_table("date_format_test").await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_limit_loads_first_rows() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        let rows: String = (1..=10).map(|id| format!("{}\n", id)).collect();
+        std::io::Write::write_all(&mut file, format!("id\n{}", rows).as_bytes()).unwrap();
+
+        let stats = LoaderBuilder::new("postgresql://localhost/test")
+            .table("limit_test")
+            .drop_table(true)
+            .create_table(true)
+            .batch_size(2)
+            .limit(3)
+            .quiet(true)
+            .build()
+            .unwrap()
+            .load(file.path())
+            .await
+            .unwrap();
+        assert_eq!(stats.rows_loaded, 3);
+
+        let db = DbConnection::connect("postgresql://localhost/test").await.unwrap();
+        let row = db.client().query_one("SELECT count(*), max(id)::bigint FROM limit_test", &[]).await.unwrap();
+        assert_eq!((row.get::<_, i64>(0), row.get::<_, i64>(1)), (3, 3));
+        db.drop_table("limit_test").await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_use_table_order() {
+        let db = DbConnection::connect("postgresql://localhost/test").await.unwrap();
+        db.drop_table("table_order_test").await.unwrap();
+        db.create_table("CREATE TABLE table_order_test (id INTEGER, extra TEXT, name TEXT)").await.unwrap();
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, b"name,id\nalice,1\nbob,2\n").unwrap();
+
+        let loader = LoaderBuilder::new("postgresql://localhost/test")
+            .table("table_order_test")
+            .use_table_order(true)
+            .quiet(true);
+        let stats = loader.clone().build().unwrap().load(file.path()).await.unwrap();
+        assert_eq!(stats.rows_loaded, 2);
+
+        let row = db.client()
+            .query_one("SELECT name, extra FROM table_order_test WHERE id = 2", &[])
+            .await
+            .unwrap();
+        assert_eq!(row.get::<_, String>(0), "bob");
+        assert_eq!(row.get::<_, Option<String>>(1), None);
+
+        let mut unknown = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut unknown, b"id,nickname\n3,c\n").unwrap();
+        let err = loader.build().unwrap().load(unknown.path()).await.unwrap_err();
+        assert!(err.to_string().contains("no column 'nickname'"), "{}", err);
+
+        db.drop_table("table_order_test").await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_dry_run_check_db() {
+        let db = DbConnection::connect("postgresql://localhost/test").await.unwrap();
+        db.drop_table("check_db_test").await.unwrap();
+        db.create_table("CREATE TABLE check_db_test (id INTEGER, name INTEGER, note TEXT)").await.unwrap();
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, b"id,name\n1,alice\n2,bob\n").unwrap();
+        let loader = LoaderBuilder::new("postgresql://localhost/test")
+            .table("check_db_test")
+            .dry_run(true)
+            .check_db(true)
+            .quiet(true);
+
+        // TEXT into INTEGER only warns unless strict
+        loader.clone().build().unwrap().load(file.path()).await.unwrap();
+        let err = loader.clone().strict_types(true).build().unwrap().load(file.path()).await.unwrap_err();
+        assert!(err.to_string().contains("name (TEXT into INTEGER)"), "{}", err);
+
+        let mut extra = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut extra, b"id,nickname\n1,a\n").unwrap();
+        let err = loader.clone().build().unwrap().load(extra.path()).await.unwrap_err();
+        assert!(err.to_string().contains("has no column nickname"), "{}", err);
+
+        assert_eq!(db.row_count("check_db_test").await.unwrap(), 0);
+        db.drop_table("check_db_test").await.unwrap();
+
+        loader.clone().build().unwrap().load(file.path()).await.unwrap();
+        let err = loader.clone().strict_existence(true).build().unwrap().load(file.path()).await.unwrap_err();
+        assert!(err.to_string().contains("does not exist"), "{}", err);
+        assert!(LoaderBuilder::new("postgresql://localhost/test").check_db(true).build().is_err());
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_target_columns() {
+        let db = DbConnection::connect("postgresql://localhost/test").await.unwrap();
+        db.drop_table("target_columns_test").await.unwrap();
+        db.create_table(
+            "CREATE TABLE target_columns_test (id SERIAL, qty INTEGER, label TEXT, source TEXT DEFAULT 'csv')"
+        ).await.unwrap();
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, b"Name,Count,Ignored\nbolt,5,x\nnut,7,y\n").unwrap();
+        let loader = LoaderBuilder::new("postgresql://localhost/test")
+            .table("target_columns_test")
+            .select_columns(ColumnSelection::Include(vec!["Name".to_string(), "Count".to_string()]))
+            .target_columns(vec!["label".to_string(), "qty".to_string()])
+            .quiet(true);
+        let stats = loader.clone().build().unwrap().load(file.path()).await.unwrap();
+        assert_eq!(stats.rows_loaded, 2);
+
+        let row = db.client()
+            .query_one("SELECT id, qty, source FROM target_columns_test WHERE label = 'nut'", &[])
+            .await
+            .unwrap();
+        assert_eq!(row.get::<_, i32>(0), 2);
+        assert_eq!(row.get::<_, i32>(1), 7);
+        assert_eq!(row.get::<_, String>(2), "csv");
+
+        // Three CSV columns can't feed two targets by position
+        let err = loader
+            .select_columns(ColumnSelection::Exclude(Vec::new()))
+            .build()
+            .unwrap()
+            .load(file.path())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("lists 2 columns but the CSV has 3"), "{}", err);
+
+        db.drop_table("target_columns_test").await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_indexes() {
+        let db = DbConnection::connect("postgresql://localhost/test").await.unwrap();
+        db.drop_table("index_test").await.unwrap();
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        let rows: String = (1..=10).map(|id| format!("{},{},n{},N{}\n", id, id % 3, id, id)).collect();
+        std::io::Write::write_all(&mut file, format!("id,group_id,name,Name\n{}", rows).as_bytes()).unwrap();
+        let loader = LoaderBuilder::new("postgresql://localhost/test")
+            .table("index_test")
+            .create_table(true)
+            .quiet(true);
+
+        // Checked before any row is loaded
+        let err = loader.clone().indexes(vec!["nope".to_string()]).build().unwrap().load(file.path()).await.unwrap_err();
+        assert!(err.to_string().contains("doesn't have: nope"), "{}", err);
+        assert_eq!(db.row_count("index_test").await.unwrap(), 0);
+
+        let indexed = loader
+            .indexes(vec!["name".to_string(), "Name".to_string()])
+            .index_detect(true);
+        let stats = indexed.clone().build().unwrap().load(file.path()).await.unwrap();
+        assert_eq!(
+            stats.indexes_created,
+            vec!["index_test_name_idx", "index_test_name_2_idx", "index_test_group_id_idx"]
+        );
+
+        let index_count = || async {
+            db.client()
+                .query_one("SELECT count(*) FROM pg_indexes WHERE tablename = 'index_test'", &[])
+                .await
+                .unwrap()
+                .get::<_, i64>(0)
+        };
+        assert_eq!(index_count().await, 3);
+
+        // Appending finds the indexes already there and reports none
+        let stats = indexed.build().unwrap().load(file.path()).await.unwrap();
+        assert!(stats.indexes_created.is_empty(), "{:?}", stats.indexes_created);
+        assert_eq!(index_count().await, 3);
+        db.drop_table("index_test").await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_swap() {
+        let db = DbConnection::connect("postgresql://localhost/test").await.unwrap();
+        db.drop_table("swap_test").await.unwrap();
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, b"id,name\n1,alice\n2,bob\n").unwrap();
+        let loader = LoaderBuilder::new("postgresql://localhost/test")
+            .table("swap_test")
+            .swap(true)
+            .indexes(vec!["name".to_string()])
+            .quiet(true);
+
+        let err = loader.clone().build().unwrap().load(file.path()).await.unwrap_err();
+        assert!(err.to_string().contains("nothing to replace"), "{}", err);
+
+        db.create_table(
+            "CREATE TABLE swap_test (id BIGINT PRIMARY KEY, name VARCHAR(40) CHECK (name <> ''), \
+             seq SERIAL, note TEXT DEFAULT 'none')",
+        ).await.unwrap();
+        db.execute("INSERT INTO swap_test (id, name) VALUES (9, 'x')").await.unwrap();
+        db.set_table_comment("swap_test", Some("daily load")).await.unwrap();
+
+        // The first swap creates the index; the second finds it copied over
+        let stats = loader.clone().build().unwrap().load(file.path()).await.unwrap();
+        assert_eq!(stats.table_name, "swap_test");
+        assert_eq!(stats.indexes_created, vec!["swap_test_name_idx"]);
+        let stats = loader.clone().build().unwrap().load(file.path()).await.unwrap();
+        assert!(stats.indexes_created.is_empty(), "{:?}", stats.indexes_created);
+
+        // The old table's types, defaults and constraints carry over, and
+        // its serial sequence keeps counting
+        assert_eq!(db.row_count("swap_test").await.unwrap(), 2);
+        assert!(!db.table_exists("swap_test_new").await.unwrap());
+        let types: Vec<(String, String)> = db
+            .client()
+            .query(
+                "SELECT attname::text, format_type(atttypid, atttypmod) FROM pg_attribute \
+                 WHERE attrelid = 'swap_test'::regclass AND attnum > 0 ORDER BY attnum",
+                &[],
+            )
+            .await
+            .unwrap()
+            .iter()
+            .map(|row| (row.get(0), row.get(1)))
+            .collect();
+        assert_eq!(
+            types,
+            [("id", "bigint"), ("name", "character varying(40)"), ("seq", "integer"), ("note", "text")]
+                .map(|(name, sql_type)| (name.to_string(), sql_type.to_string()))
+        );
+        let row = db.client().query_one("SELECT max(seq), min(note) FROM swap_test", &[]).await.unwrap();
+        assert_eq!(row.get::<_, i32>(0), 5);
+        assert_eq!(row.get::<_, String>(1), "none");
+        assert!(db.execute("INSERT INTO swap_test (id, name) VALUES (3, '')").await.is_err());
+        let rows = db
+            .client()
+            .query("SELECT indexname::text FROM pg_indexes WHERE tablename = 'swap_test' ORDER BY 1", &[])
+            .await
+            .unwrap();
+        let indexes: Vec<String> = rows.iter().map(|row| row.get(0)).collect();
+        assert_eq!(indexes, vec!["swap_test_name_idx", "swap_test_pkey"]);
+        assert_eq!(db.table_comment("swap_test").await.unwrap().as_deref(), Some("daily load"));
+
+        // A dependent view keeps the old table in place
+        db.execute("CREATE VIEW swap_test_view AS SELECT id FROM swap_test").await.unwrap();
+        assert!(loader.clone().build().unwrap().load(file.path()).await.is_err());
+        assert_eq!(db.row_count("swap_test").await.unwrap(), 2);
+        db.execute("DROP VIEW swap_test_view").await.unwrap();
+
+        // The staging table that failed swap left is replaced; one the user
+        // made with that name is not
+        assert!(db.table_exists("swap_test_new").await.unwrap());
+        loader.clone().build().unwrap().load(file.path()).await.unwrap();
+        db.create_table("CREATE TABLE swap_test_new (keep INTEGER)").await.unwrap();
+        let err = loader.build().unwrap().load(file.path()).await.unwrap_err();
+        assert!(err.to_string().contains("was not made by --swap"), "{}", err);
+        assert!(db.table_columns("swap_test_new").await.unwrap().iter().any(|(name, _)| name == "keep"));
+        db.drop_table("swap_test_new").await.unwrap();
+        db.drop_table("swap_test").await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_rename_to() {
+        let db = DbConnection::connect("postgresql://localhost/test").await.unwrap();
+        for table in ["rename_test", "rename_test_done"] {
+            db.drop_table(table).await.unwrap();
+        }
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, b"id\n1\n2\n").unwrap();
+        let loader = LoaderBuilder::new("postgresql://localhost/test")
+            .table("rename_test")
+            .create_table(true)
+            .quiet(true);
+
+        let stats = loader.clone().rename_to("public.rename_test_done").build().unwrap().load(file.path()).await.unwrap();
+        assert_eq!(stats.table_name, "public.rename_test_done");
+        assert!(!db.table_exists("rename_test").await.unwrap());
+        assert_eq!(db.row_count("rename_test_done").await.unwrap(), 2);
+
+        // RENAME TO can't move the table to another schema
+        let err = loader.rename_to("other.rename_test_done").build().unwrap().load(file.path()).await.unwrap_err();
+        assert!(err.to_string().contains("can't move it"), "{}", err);
+        assert_eq!(db.row_count("rename_test").await.unwrap(), 2);
+
+        for table in ["rename_test", "rename_test_done"] {
+            db.drop_table(table).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_transforms() {
+        let db = DbConnection::connect("postgresql://localhost/test").await.unwrap();
+        db.drop_table("transform_test").await.unwrap();
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, b"id,email,code\n1,Alice@Example.COM, ab \n2,bob@example.com,\n").unwrap();
+        let loader = LoaderBuilder::new("postgresql://localhost/test")
+            .table("transform_test")
+            .create_table(true)
+            .copy_options(CopyOptions { null_marker: Some("\\N".to_string()), ..Default::default() })
+            .quiet(true);
+
+        let err = loader.clone().transform("missing", ValueTransform::Lower).build().unwrap().load(file.path()).await.unwrap_err();
+        assert!(err.to_string().contains("Unknown column 'missing'"), "{}", err);
+
+        loader
+            .transform("email", ValueTransform::Lower)
+            .transform("code", ValueTransform::Trim)
+            .transform("code", ValueTransform::Upper)
+            .transform("code", ValueTransform::NullifyEmpty)
+            .build()
+            .unwrap()
+            .load(file.path())
+            .await
+            .unwrap();
+
+        let rows = db.client().query("SELECT email, code FROM transform_test ORDER BY id", &[]).await.unwrap();
+        let values: Vec<(String, Option<String>)> = rows.iter().map(|row| (row.get(0), row.get(1))).collect();
+        assert_eq!(
+            values,
+            vec![
+                ("alice@example.com".to_string(), Some("AB".to_string())),
+                ("bob@example.com".to_string(), None),
+            ]
+        );
+
+        db.drop_table("transform_test").await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_force_text() {
+        let db = DbConnection::connect("postgresql://localhost/test").await.unwrap();
+        db.drop_table("force_text_test").await.unwrap();
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, b"id,amount,seen\n1,2.50,2024-01-02\n2,,2024-01-03\n").unwrap();
+        let stats = LoaderBuilder::new("postgresql://localhost/test")
+            .table("force_text_test")
+            .create_table(true)
+            .force_text(true)
+            .quiet(true)
+            .build()
+            .unwrap()
+            .load(file.path())
+            .await
+            .unwrap();
+        assert_eq!(stats.rows_loaded, 2);
+
+        let columns = db.table_columns("force_text_test").await.unwrap();
+        assert_eq!(columns.len(), 3);
+        assert!(columns.iter().all(|(_, sql_type)| *sql_type == Some(SqlType::Text)), "{:?}", columns);
+        let rows = db.client().query("SELECT amount FROM force_text_test ORDER BY id", &[]).await.unwrap();
+        let amounts: Vec<Option<String>> = rows.iter().map(|row| row.get(0)).collect();
+        assert_eq!(amounts, vec![Some("2.50".to_string()), None]);
+
+        db.drop_table("force_text_test").await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_ledger_refuses_reloads() {
+        let db = DbConnection::connect("postgresql://localhost/test").await.unwrap();
+        db.drop_table("ledger_test").await.unwrap();
+        db.drop_table("ledger_test_loads").await.unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("first.csv");
+        let second = dir.path().join("second.csv");
+        std::fs::write(&first, "id\n1\n2\n").unwrap();
+        std::fs::write(&second, "id\n3\n").unwrap();
+        let loader = |options: LedgerOptions| {
+            LoaderBuilder::new("postgresql://localhost/test")
+                .table("ledger_test")
+                .ledger(options)
+                .quiet(true)
+                .build()
+                .unwrap()
+        };
+        let ledger = LedgerOptions::new("ledger_test_loads");
+
+        let stats = loader(ledger.clone()).load(&first).await.unwrap();
+        assert_eq!(stats.rows_loaded, 2);
+
+        let err = loader(ledger.clone()).load_many(&[&first, &second]).await.unwrap_err();
+        assert!(matches!(err, LoaderError::DuplicateFile { .. }), "{}", err);
+        assert_eq!(db.row_count("ledger_test").await.unwrap(), 2);
+
+        let skip = LedgerOptions { on_duplicate: OnDuplicateFile::Skip, ..ledger.clone() };
+        let stats = loader(skip.clone()).load_many(&[&first, &second]).await.unwrap();
+        assert_eq!((stats.files, stats.rows_loaded), (1, 1));
+        let stats = loader(skip).load_many(&[&first, &second]).await.unwrap();
+        assert_eq!((stats.files, stats.rows_loaded), (0, 0));
+
+        let stats = loader(LedgerOptions { force: true, ..ledger.clone() }).load(&second).await.unwrap();
+        assert_eq!(stats.rows_loaded, 1);
+        assert_eq!(db.row_count("ledger_test").await.unwrap(), 4);
+        assert_eq!(db.row_count("ledger_test_loads").await.unwrap(), 2);
+
+        // Cheap fingerprints are separate entries from hashed ones
+        let cheap = LedgerOptions { cheap: true, ..ledger };
+        loader(cheap.clone()).load(&first).await.unwrap();
+        assert!(loader(cheap).load(&first).await.is_err());
+
+        db.drop_table("ledger_test").await.unwrap();
+        db.drop_table("ledger_test_loads").await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_freeze() {
+        let db = DbConnection::connect("postgresql://localhost/test").await.unwrap();
+        db.drop_table("freeze_test").await.unwrap();
+        db.create_table("CREATE TABLE freeze_test (id INTEGER)").await.unwrap();
+        db.execute("INSERT INTO freeze_test VALUES (0)").await.unwrap();
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, b"id\n1\n2\n").unwrap();
+        let loader = LoaderBuilder::new("postgresql://localhost/test")
+            .table("freeze_test")
+            .atomic(true)
+            .copy_options(CopyOptions { freeze: true, ..Default::default() })
+            .quiet(true);
+
+        // The existing table wasn't created or truncated by this load
+        let err = loader.clone().build().unwrap().load(file.path()).await.unwrap_err();
+        assert!(err.to_string().contains("add --truncate"), "{}", err);
+        assert_eq!(db.row_count("freeze_test").await.unwrap(), 1);
+
+        // A failed load rolls back the truncation too
+        let mut bad = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut bad, b"id\n1\nx\n").unwrap();
+        let typed = loader.clone().truncate(true, false).column_type("id", SqlType::Integer);
+        assert!(typed.build().unwrap().load(bad.path()).await.is_err());
+        assert_eq!(db.row_count("freeze_test").await.unwrap(), 1);
+
+        let stats = loader.truncate(true, false).build().unwrap().load(file.path()).await.unwrap();
+        assert_eq!(stats.rows_loaded, 2);
+        assert_eq!(db.row_count("freeze_test").await.unwrap(), 2);
+        db.drop_table("freeze_test").await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_failed_atomic_load_keeps_replaced_rows() {
+        let db = DbConnection::connect("postgresql://localhost/test").await.unwrap();
+        db.drop_table("atomic_replace_test").await.unwrap();
+        db.create_table("CREATE TABLE atomic_replace_test (id INTEGER)").await.unwrap();
+        db.execute("INSERT INTO atomic_replace_test VALUES (0)").await.unwrap();
+
+        let mut bad = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut bad, b"id\n1\nx\n").unwrap();
+        let loader = LoaderBuilder::new("postgresql://localhost/test")
+            .table("atomic_replace_test")
+            .column_type("id", SqlType::Integer)
+            .quiet(true);
+
+        for replacing in [
+            loader.clone().atomic(true).truncate(true, false),
+            loader.clone().atomic(true).drop_table(true).create_table(true),
+            loader.clone().atomic_with_savepoints(true).truncate(true, false),
+        ] {
+            assert!(replacing.build().unwrap().load(bad.path()).await.is_err());
+            assert_eq!(db.row_count("atomic_replace_test").await.unwrap(), 1);
+        }
+        db.drop_table("atomic_replace_test").await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_verify_count() {
+        let db = DbConnection::connect("postgresql://localhost/test").await.unwrap();
+        db.drop_table("verify_count_test").await.unwrap();
+        db.create_table("CREATE TABLE verify_count_test (id INTEGER)").await.unwrap();
+        db.execute("INSERT INTO verify_count_test VALUES (0)").await.unwrap();
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, b"id\n1\n2\n3\n").unwrap();
+        let loader = LoaderBuilder::new("postgresql://localhost/test")
+            .table("verify_count_test")
+            .verify_count(true)
+            .quiet(true);
+
+        // Appending compares the rows added, not the table's total
+        let stats = loader.clone().build().unwrap().load(file.path()).await.unwrap();
+        assert_eq!(stats.rows_loaded, 3);
+
+        // A trigger quietly removing a row after COPY counted it
+        db.execute(
+            "CREATE FUNCTION verify_count_drop() RETURNS trigger LANGUAGE plpgsql AS \
+             $$ BEGIN DELETE FROM verify_count_test WHERE id = 2; RETURN NULL; END $$",
+        ).await.unwrap();
+        db.execute(
+            "CREATE TRIGGER drop_one AFTER INSERT ON verify_count_test \
+             FOR EACH STATEMENT EXECUTE FUNCTION verify_count_drop()",
+        ).await.unwrap();
+
+        let err = loader.build().unwrap().load(file.path()).await.unwrap_err();
+        assert!(
+            matches!(err, LoaderError::CountMismatch { expected: 3, actual: 1 }),
+            "{}",
+            err
+        );
+
+        db.drop_table("verify_count_test").await.unwrap();
+        db.execute("DROP FUNCTION verify_count_drop()").await.unwrap();
+    }
+
+    /// Cancels the load once the first batch is in
+    struct CancelAfterFirstBatch(CancelToken);
+
+    impl ProgressSink for CancelAfterFirstBatch {
+        fn on_batch(&self, _rows_loaded: u64, _total: Option<u64>) {
+            self.0.cancel();
+        }
+
+        fn on_finish(&self, _stats: &LoadStats) {}
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_cancel_reports_rows_loaded() {
+        let db = DbConnection::connect("postgresql://localhost/test").await.unwrap();
+        db.drop_table("cancel_test").await.unwrap();
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        let csv: String = std::iter::once("id\n".to_string())
+            .chain((1..=10_000).map(|id| format!("{}\n", id)))
+            .collect();
+        std::io::Write::write_all(&mut file, csv.as_bytes()).unwrap();
+        let loader = |cancel: CancelToken| {
+            LoaderBuilder::new("postgresql://localhost/test")
+                .table("cancel_test")
+                .create_table(true)
+                .batch_size(100)
+                .progress(Arc::new(CancelAfterFirstBatch(cancel.clone())))
+                .cancel_token(cancel)
+                .quiet(true)
+        };
+
+        // Batches in flight finish and stay loaded
+        let err = loader(CancelToken::new()).build().unwrap().load(file.path()).await.unwrap_err();
+        let LoaderError::Interrupted { rows_loaded } = err else {
+            panic!("expected an interrupt, got {}", err);
+        };
+        assert!(rows_loaded > 0 && rows_loaded < 10_000, "{}", rows_loaded);
+        assert_eq!(db.row_count("cancel_test").await.unwrap(), rows_loaded);
+
+        // An atomic load rolls them back
+        let err = loader(CancelToken::new()).atomic(true).build().unwrap().load(file.path()).await.unwrap_err();
+        assert!(matches!(err, LoaderError::Interrupted { rows_loaded: 0 }), "{}", err);
+        assert_eq!(db.row_count("cancel_test").await.unwrap(), rows_loaded);
+
+        db.drop_table("cancel_test").await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_parallel_load_reports_first_failure() {
+        let db = DbConnection::connect("postgresql://localhost/test").await.unwrap();
+        db.drop_table("parallel_fail_test").await.unwrap();
+        db.create_table("CREATE TABLE parallel_fail_test (id INTEGER CONSTRAINT no_5000 CHECK (id <> 5000))")
+            .await
+            .unwrap();
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        let csv: String = std::iter::once("id\n".to_string())
+            .chain((1..=10_000).map(|id| format!("{}\n", id)))
+            .collect();
+        std::io::Write::write_all(&mut file, csv.as_bytes()).unwrap();
+        let loader = LoaderBuilder::new("postgresql://localhost/test")
+            .table("parallel_fail_test")
+            .batch_size(100)
+            .parallelism(4)
+            .quiet(true);
+
+        // One worker's failure stops the others and is what the load reports
+        let err = loader.build().unwrap().load(file.path()).await.unwrap_err();
+        assert!(err.to_string().contains("no_5000"), "{}", err);
+        assert!(db.row_count("parallel_fail_test").await.unwrap() < 10_000);
+
+        db.drop_table("parallel_fail_test").await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_malformed_lines_count_against_max_errors() {
+        let db = DbConnection::connect("postgresql://localhost/test").await.unwrap();
+        db.drop_table("malformed_quote_test").await.unwrap();
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, b"id,note\n1,ok\n2,\"a\n3,ok\n4,\"b\n5,ok\n6,\"c\n7,ok\n").unwrap();
+        let skip = |max_errors| BatchConfig { on_error: OnError::Skip, max_errors, ..Default::default() };
+        let loader = LoaderBuilder::new("postgresql://localhost/test")
+            .table("malformed_quote_test")
+            .batch_size(2)
+            .quiet(true);
+
+        // Skipping turns on quote recovery without setting it in the CSV options
+        let err = loader.clone().batch_config(skip(1)).build().unwrap().load(file.path()).await.unwrap_err();
+        assert!(matches!(err, LoaderError::TooManyErrors { max: 1, .. }), "{}", err);
+
+        db.drop_table("malformed_quote_test").await.unwrap();
+        let stats = loader.batch_config(skip(3)).build().unwrap().load(file.path()).await.unwrap();
+        assert_eq!((stats.rows_loaded, stats.rows_rejected), (4, 3));
+
+        db.drop_table("malformed_quote_test").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_load_many_requires_matching_headers() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("a.csv");
+        let second = dir.path().join("b.csv");
+        std::fs::write(&first, "id,name\n1,a\n").unwrap();
+        std::fs::write(&second, "id,email\n2,b@example.com\n").unwrap();
+
+        let loader = LoaderBuilder::new("postgresql://invalid").dry_run(true);
+        let err = loader.clone().build().unwrap().load_many(&[&first, &second]).await.unwrap_err();
+        assert!(err.to_string().contains("--union-schema"));
+
+        let stats = loader.union_schema(true).build().unwrap().load_many(&[&first, &second]).await.unwrap();
+        assert_eq!(stats.table_name, "a");
+    }
+}
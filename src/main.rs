@@ -1,35 +1,75 @@
 //! CSV-SQL Streaming Loader
 //! High-performance CLI tool for loading CSV files into PostgreSQL
 
-mod errors;
-mod types;
-mod schema;
-mod parser;
-mod db;
-mod progress;
-
-use clap::Parser;
-use errors::{LoaderError, Result};
-use parser::CsvParser;
-use schema::{InferenceConfig, TableSchema};
-use db::{DbConnection, CopyLoader, BatchProcessor, batch::BatchConfig, batch::BatchIterator};
-use progress::ProgressTracker;
-use std::path::PathBuf;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, ValueEnum};
+use csv_sql_loader::config::{self, Config};
+use csv_sql_loader::db::batch::{BatchConfig, OnError, RetryJitter};
+use csv_sql_loader::db::{self, CopyFormat, CopyOptions, LoadMethod, ValueTransform, WireFormat};
+use csv_sql_loader::fixed_width;
+use csv_sql_loader::ledger::{LedgerOptions, OnDuplicateFile};
+use csv_sql_loader::compression::Compression;
+use csv_sql_loader::parser::{self, ColumnSelection, CsvOptions, ExtraFields};
+use csv_sql_loader::progress::ProgressFormat;
+use csv_sql_loader::schema::{DdlOptions, InferenceConfig, SampleStrategy};
+use csv_sql_loader::types::{IntegerCap, SqlType, BOOL_FALSE_TOKENS, BOOL_TRUE_TOKENS};
+use csv_sql_loader::{CancelToken, LoaderBuilder, LoaderError, Result};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 #[derive(Parser, Debug)]
 #[command(name = "csv-sql-loader")]
 #[command(version = "0.1.0")]
 #[command(about = "High-performance CSV to PostgreSQL loader", long_about = None)]
 struct Args {
-    /// CSV file to load
-    #[arg(value_name = "CSV_FILE")]
-    csv_file: PathBuf,
+    /// CSV files or glob patterns to load into one table, optionally
+    /// followed by the PostgreSQL connection string (default:
+    /// $DATABASE_URL, or the libpq PG* variables). Prefer the environment
+    /// to keep passwords out of shell history
+    #[arg(value_name = "CSV_FILE", required = true, num_args = 1..)]
+    inputs: Vec<String>,
 
-    /// PostgreSQL connection string
-    #[arg(value_name = "CONNECTION_STRING")]
-    connection_string: String,
+    /// Read settings from a TOML file; flags given on the command line win
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
 
-    /// Target table name (default: inferred from filename)
+    /// Environment variable holding the connection string
+    #[arg(long, value_name = "VAR", default_value = "DATABASE_URL")]
+    dsn_env: String,
+
+    /// Give up connecting after this many seconds
+    #[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..))]
+    connect_timeout: Option<u64>,
+
+    /// Retry connecting this many times, with exponential backoff, while
+    /// the server refuses connections or can't be resolved (e.g. still
+    /// starting up); rejected credentials fail at once
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    connect_retries: usize,
+
+    /// Have the server cancel any statement, including a COPY, running
+    /// longer than this many seconds
+    #[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..))]
+    statement_timeout: Option<u64>,
+
+    /// Name shown in pg_stat_activity (default: csv-sql-loader)
+    #[arg(long, value_name = "NAME")]
+    app_name: Option<String>,
+
+    /// Extra connection parameter, e.g. options=-c search_path=myschema
+    /// (repeatable)
+    #[arg(long = "conn-option", value_name = "KEY=VALUE", value_parser = parse_key_value)]
+    conn_options: Vec<(String, String)>,
+
+    /// Schemas to resolve unqualified table names in, e.g. staging,public.
+    /// A schema-qualified --table ignores this
+    #[arg(long, value_name = "SCHEMAS", value_delimiter = ',')]
+    search_path: Vec<String>,
+
+    /// Target table name, optionally schema-qualified as schema.table
+    /// (default: inferred from filename)
     #[arg(short, long)]
     table: Option<String>,
 
@@ -37,186 +77,990 @@ struct Args {
     #[arg(short, long, default_value_t = 10000)]
     batch_size: usize,
 
-    /// Rows to sample for type inference
+    /// Also end a batch once it holds this much data, e.g. 64M; whichever of
+    /// this and --batch-size is reached first wins
+    #[arg(long, value_name = "SIZE", value_parser = parse_byte_size)]
+    batch_bytes: Option<usize>,
+
+    /// Rows to sample for type inference; 0 scans every row so types and
+    /// nullability reflect the whole file
     #[arg(short, long, default_value_t = 1000)]
     sample_size: usize,
 
-    /// Create table if it doesn't exist
+    /// head samples the first rows; reservoir samples the whole file
+    /// uniformly (costs an extra full read); spread samples the head,
+    /// middle and tail by seeking
+    #[arg(long, value_enum, default_value_t = SampleStrategy::Head)]
+    sample_strategy: SampleStrategy,
+
+    /// Keep the sampled rows in memory and load them from there instead of
+    /// re-reading them (holds the whole file with --sample-size 0)
+    #[arg(long)]
+    buffer_sample: bool,
+
+    /// Create the table if it doesn't exist, even with --drop-table or
+    /// --truncate
+    ///
+    /// A missing table is also created without this flag, unless
+    /// --strict-existence, --drop-table or --truncate is given, in which
+    /// case it is an error.
     #[arg(long)]
     create_table: bool,
 
+    /// Fail if the table doesn't exist instead of creating it
+    #[arg(long, conflicts_with = "create_table")]
+    strict_existence: bool,
+
+    /// Load into a new <table>_new created like the existing table, then
+    /// drop the existing table and rename the new one over it in one
+    /// transaction. Foreign keys, grants and triggers are not carried over
+    #[arg(long, conflicts_with_all = ["drop_table", "truncate"])]
+    swap: bool,
+
+    /// Rename the table to this name once the load is done; a schema
+    /// prefix must be the table's own schema
+    #[arg(long, value_name = "NAME", conflicts_with = "swap")]
+    rename_to: Option<String>,
+
     /// Drop table before loading
     #[arg(long)]
     drop_table: bool,
 
-    /// CSV delimiter
+    /// Empty the existing table before loading, keeping its structure
+    #[arg(long, conflicts_with = "drop_table")]
+    truncate: bool,
+
+    /// Reset identity sequences when truncating
+    #[arg(long, requires = "truncate")]
+    restart_identity: bool,
+
+    /// CSV delimiter; several characters, e.g. "||", are also accepted, and
+    /// "auto" detects one of , tab ; |
     #[arg(short, long, default_value = ",")]
     delimiter: String,
 
+    /// Read fixed-width lines instead of CSV, slicing each into columns by
+    /// 0-based byte ranges with an exclusive end, e.g. id:0-10,name:10-30.
+    /// A header line, if any, is skipped; fields are trimmed
+    #[arg(long, value_name = "SPEC", conflicts_with_all = ["delimiter", "flexible"])]
+    fixed_width: Option<String>,
+
     /// CSV has no header row
     #[arg(long)]
     no_header: bool,
 
+    /// Number repeated header names (id, id_2) instead of failing
+    #[arg(long)]
+    dedup_headers: bool,
+
+    /// Discard this many raw lines (e.g. a title banner) before the header
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    skip_rows: usize,
+
+    /// Load only the first N data rows (counted after --skip-rows and the header)
+    #[arg(long, value_name = "N")]
+    limit: Option<u64>,
+
+    /// Ignore lines starting with this character, e.g. "#"
+    #[arg(long, value_name = "CHAR")]
+    comment_char: Option<char>,
+
+    /// Character used to quote fields in the input file
+    #[arg(long, value_name = "CHAR", default_value_t = '"')]
+    quote_char: char,
+
+    /// Character that escapes a quote inside a quoted field, e.g. a backslash
+    #[arg(long, value_name = "CHAR")]
+    escape_char: Option<char>,
+
+    /// Input text encoding, e.g. latin1 or windows-1252
+    #[arg(long, value_name = "LABEL", default_value = "utf-8")]
+    encoding: String,
+
+    /// Input compression; auto goes by the .gz, .zst or .bz2 extension,
+    /// then by the file's magic bytes
+    #[arg(long, value_enum, default_value_t = Compression::Auto)]
+    compression: Compression,
+
+    /// Memory-map the file while inferring its schema, for faster scans of
+    /// large uncompressed files. Loading always reads the file normally
+    #[arg(long)]
+    mmap: bool,
+
+    /// Strip surrounding whitespace from headers and fields. Off by default
+    /// since trailing spaces can be meaningful in text columns
+    #[arg(long)]
+    trim: bool,
+
+    /// Accept rows with fewer or more fields than the header; short rows
+    /// are padded with NULLs
+    #[arg(long)]
+    flexible: bool,
+
+    /// Skip rows whose fields are all empty or whitespace, like `,,,` or a
+    /// trailing line of spaces. A quoted `""` is a value, so `"",,` is not blank
+    #[arg(long, default_value_t = true, action = clap::ArgAction::Set, value_name = "BOOL")]
+    skip_blank_rows: bool,
+
+    /// What to do with fields beyond the header in --flexible mode
+    #[arg(long, value_enum, default_value_t = ExtraFields::Drop, requires = "flexible")]
+    on_extra_fields: ExtraFields,
+
+    /// Only load these CSV columns (comma-separated)
+    #[arg(long, value_name = "COLS", value_delimiter = ',', conflicts_with = "exclude_columns")]
+    include_columns: Vec<String>,
+
+    /// Load every CSV column except these (comma-separated)
+    #[arg(long, value_name = "COLS", value_delimiter = ',')]
+    exclude_columns: Vec<String>,
+
+    /// Treat this value as NULL (repeatable, e.g. --null-token NA --null-token -)
+    #[arg(long = "null-token", value_name = "TOKEN")]
+    null_tokens: Vec<String>,
+
+    /// Send NULLs to COPY as this marker, e.g. \N, so empty text cells load
+    /// as empty strings; cells equal to the marker are NULL
+    #[arg(long, value_name = "STR")]
+    null_marker: Option<String>,
+
+    /// Value recognized as boolean true (repeatable; replaces the defaults).
+    /// 1/0 are opt-in so integer columns aren't mistaken for booleans
+    #[arg(long = "bool-true", value_name = "TOKEN", default_values = BOOL_TRUE_TOKENS)]
+    bool_true: Vec<String>,
+
+    /// Value recognized as boolean false (repeatable; replaces the defaults)
+    #[arg(long = "bool-false", value_name = "TOKEN", default_values = BOOL_FALSE_TOKENS)]
+    bool_false: Vec<String>,
+
+    /// Number of concurrent connections loading batches
+    #[arg(short, long, default_value_t = 1)]
+    parallelism: usize,
+
+    /// Parsed batches buffered ahead of the loaders; bounds memory while
+    /// parsing overlaps with loading
+    #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u64).range(1..))]
+    channel_depth: u64,
+
+    /// COPY wire format; binary is faster for numeric-heavy files and falls
+    /// back to text when no column benefits
+    #[arg(long, value_enum, default_value_t = CopyFormat::Text)]
+    copy_format: CopyFormat,
+
+    /// Serialization of text COPY: csv, or Postgres's tab-separated text
+    /// format with backslash escapes, more compact for values full of
+    /// quotes and commas
+    #[arg(long, value_enum, default_value_t = WireFormat::Csv)]
+    copy_wire_format: WireFormat,
+
+    /// How rows are sent: COPY, or multi-row INSERT statements for roles
+    /// without COPY privileges (slower; batches are capped at 65535 cells)
+    #[arg(long, value_enum, default_value_t = LoadMethod::Copy)]
+    method: LoadMethod,
+
+    /// With --method insert, bind a column as text cast to a type, e.g. a
+    /// domain or enum: --bind-type "email=app.email" (repeatable)
+    #[arg(long = "bind-type", value_name = "COL=TYPE", value_parser = parse_key_value)]
+    bind_types: Vec<(String, String)>,
+
+    /// Load everything in one transaction, rolling back on any failure
+    #[arg(long)]
+    atomic: bool,
+
+    /// Like --atomic, but each batch runs in a savepoint: a failed batch is
+    /// rolled back on its own (and skipped with --on-error skip) while the
+    /// rest commit at the end. Adds a round trip per batch
+    #[arg(long, conflicts_with = "atomic")]
+    atomic_with_savepoints: bool,
+
+    /// COPY WITH (FREEZE), so rows need no later vacuum to be visible to
+    /// all; needs --atomic and a table this load creates or truncates
+    /// (--truncate, --drop-table, or a missing table)
+    #[arg(long)]
+    freeze: bool,
+
     /// Maximum retry attempts
     #[arg(long, default_value_t = 3)]
     max_retries: usize,
 
+    /// Randomize retry backoff so parallel workers don't retry in lockstep
+    #[arg(long, value_enum, default_value_t = RetryJitter::None)]
+    retry_jitter: RetryJitter,
+
+    /// What to do when a batch fails: abort the load, or skip the bad rows
+    #[arg(long, value_enum, default_value_t = OnError::Abort)]
+    on_error: OnError,
+
+    /// When a batch fails, find the offending row and show its line and
+    /// contents. May re-run the batch several times, always rolled back
+    #[arg(long)]
+    isolate_errors: bool,
+
+    /// Write rows rejected in skip mode to this CSV file
+    #[arg(long, value_name = "FILE")]
+    error_file: Option<PathBuf>,
+
+    /// Record loaded rows here after each batch; rerunning with the file
+    /// present skips them. Removed once the load completes
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["atomic", "atomic_with_savepoints"])]
+    checkpoint_file: Option<PathBuf>,
+
+    /// Record each loaded file in this table, created if missing, and
+    /// refuse to load a file it has already seen loaded into the table
+    #[arg(long, value_name = "TABLE", conflicts_with = "checkpoint_file")]
+    ledger_table: Option<String>,
+
+    /// error fails the load when a file was already loaded; skip warns
+    /// and leaves the file out
+    #[arg(long, value_enum, value_name = "MODE", default_value_t = OnDuplicateFile::Error, requires = "ledger_table")]
+    on_duplicate_file: OnDuplicateFile,
+
+    /// Identify files in the ledger by size and modification time instead
+    /// of hashing their contents
+    #[arg(long, requires = "ledger_table")]
+    ledger_cheap: bool,
+
+    /// Load files the ledger has already seen loaded
+    #[arg(long, requires = "ledger_table")]
+    force: bool,
+
+    /// Abort once more than this many rows have been rejected
+    #[arg(long, default_value_t = 1000)]
+    max_errors: usize,
+
+    /// Infer zero-padded numbers like 00123 as TEXT so the zeros survive
+    #[arg(long, default_value_t = true, action = clap::ArgAction::Set, value_name = "BOOL")]
+    keep_leading_zeros: bool,
+
+    /// Extra date format in chrono strftime syntax, e.g. "%d.%m.%Y" (repeatable)
+    #[arg(long = "date-format", value_name = "FORMAT")]
+    date_formats: Vec<String>,
+
+    /// Extra timestamp format in chrono strftime syntax (repeatable)
+    #[arg(long = "timestamp-format", value_name = "FORMAT")]
+    timestamp_formats: Vec<String>,
+
+    /// Decimal separator in numeric values, e.g. "," for 1.234,56
+    #[arg(long, default_value_t = '.')]
+    decimal_separator: char,
+
+    /// Digit grouping separator in numeric values, e.g. "," for 1,234.56
+    #[arg(long)]
+    thousands_separator: Option<char>,
+
+    /// Widest integer type to infer; columns with larger values become TEXT
+    /// (or abort with --strict)
+    #[arg(long, value_enum, value_name = "TYPE", default_value_t = IntegerCap::BigInt)]
+    max_int_type: IntegerCap,
+
+    /// Keep IP addresses as TEXT instead of inferring INET/CIDR
+    #[arg(long)]
+    no_inet: bool,
+
+    /// Keep hexadecimal values like 0xFF as TEXT instead of integers
+    #[arg(long)]
+    no_hex: bool,
+
+    /// Treat inf, -inf and NaN as TEXT instead of floating point
+    #[arg(long)]
+    reject_nonfinite: bool,
+
+    /// Strip $, €, £ and % from numbers, loading $1,234.50 as NUMERIC
+    #[arg(long)]
+    strip_symbols: bool,
+
+    /// Divide percentages by 100 when stripping symbols, so 45% loads as 0.45
+    #[arg(long, requires = "strip_symbols")]
+    percent_as_fraction: bool,
+
+    /// Infer VARCHAR(n) for text columns, sized from the longest sampled value
+    #[arg(long)]
+    varchar: bool,
+
+    /// Headroom factor for --varchar lengths before rounding up to a power of two
+    #[arg(long, value_name = "FACTOR", default_value_t = 1.0, requires = "varchar")]
+    varchar_padding: f64,
+
+    /// Show min/max, distinct counts and longest values for each column,
+    /// also written to --report
+    #[arg(long)]
+    profile: bool,
+
+    /// Make the first unique, non-null integer column the primary key.
+    /// Uniqueness is only exact with --sample-size 0, and is checked per
+    /// file, so loads of several files detect no key
+    #[arg(long)]
+    detect_pk: bool,
+
+    /// Use this column as the primary key of a created table
+    #[arg(long, value_name = "NAME")]
+    pk_column: Option<String>,
+
+    /// Make every column nullable, e.g. for staging tables
+    #[arg(long, conflicts_with = "no_nulls")]
+    all_nullable: bool,
+
+    /// Make every column NOT NULL, warning about columns the sample found
+    /// nulls in
+    #[arg(long)]
+    no_nulls: bool,
+
+    /// Column DEFAULT for a created table, e.g. --default status=active or
+    /// --default created_at="now()" (repeatable). Numbers, NULL/true/false,
+    /// quoted literals and function calls are used verbatim; other values
+    /// are quoted as strings. A column left out by --include-columns is
+    /// still created, as TEXT, with its default
+    #[arg(long = "default", value_name = "NAME=VALUE", value_parser = parse_key_value)]
+    defaults: Vec<(String, String)>,
+
+    /// Override a column's inferred type, e.g. --column-type zip=TEXT (repeatable)
+    #[arg(long = "column-type", value_name = "NAME=TYPE", value_parser = parse_key_value)]
+    column_types: Vec<(String, String)>,
+
+    /// Lowercase these columns' values as they're loaded (comma-separated),
+    /// e.g. for case-insensitive joins. Inference sees the original values
+    #[arg(long, value_name = "COLS", value_delimiter = ',')]
+    lowercase: Vec<String>,
+
+    /// Uppercase these columns' values as they're loaded (comma-separated)
+    #[arg(long, value_name = "COLS", value_delimiter = ',')]
+    uppercase: Vec<String>,
+
+    /// Rewrite a column's values as they're loaded, e.g.
+    /// --transform "name=trim,lower" (repeatable). Transforms: trim, lower,
+    /// upper, nullify-empty; they run in the order given, after inference
+    #[arg(long = "transform", value_name = "NAME=TRANSFORMS", value_parser = parse_key_value)]
+    transforms: Vec<(String, String)>,
+
+    /// Load a CSV column into a differently named table column,
+    /// e.g. --map "Full Name=name" (repeatable). Table columns with no
+    /// source keep their DEFAULT
+    #[arg(long = "map", value_name = "CSV_COL=TABLE_COL", value_parser = parse_key_value)]
+    column_map: Vec<(String, String)>,
+
+    /// Rename a CSV column for the table, e.g. --rename "Customer ID=customer_id"
+    /// (repeatable)
+    #[arg(long = "rename", value_name = "OLD=NEW", value_parser = parse_key_value, conflicts_with = "schema_in")]
+    renames: Vec<(String, String)>,
+
+    /// Load columns in an existing table's order, failing if the table
+    /// lacks any CSV column
+    #[arg(long, conflicts_with_all = ["column_map", "map_file"])]
+    use_table_order: bool,
+
+    /// COPY into only these table columns (comma-separated), which the CSV
+    /// columns feed in order, or by name with --map; the rest of the table
+    /// keeps its DEFAULT
+    #[arg(long, value_name = "COLS", value_delimiter = ',', conflicts_with = "use_table_order")]
+    target_columns: Vec<String>,
+
+    /// JSON object of CSV column to table column mappings; --map entries
+    /// take precedence
+    #[arg(long, value_name = "FILE")]
+    map_file: Option<PathBuf>,
+
+    /// Rewrite column names into safe lowercase identifiers
+    #[arg(long)]
+    sanitize_columns: bool,
+
+    /// Emit column names unquoted so Postgres folds them to lowercase
+    #[arg(long)]
+    no_quote_identifiers: bool,
+
+    /// Write the inferred schema to a JSON file
+    #[arg(long, value_name = "FILE", conflicts_with = "schema_in")]
+    schema_out: Option<PathBuf>,
+
+    /// Skip inference and use a schema JSON file written by --schema-out
+    #[arg(long, value_name = "FILE")]
+    schema_in: Option<PathBuf>,
+
+    /// Skip inference and create every column as nullable TEXT
+    #[arg(long, conflicts_with = "schema_in")]
+    force_text: bool,
+
+    /// With several files, infer every one and widen types across them;
+    /// columns missing from some files become nullable
+    #[arg(long, conflicts_with = "schema_in")]
+    union_schema: bool,
+
+    /// Write the CREATE TABLE statement to a SQL file
+    #[arg(long, value_name = "FILE")]
+    ddl_out: Option<PathBuf>,
+
+    /// Write a JSON report of inferred types, confidence, and warnings
+    #[arg(long, value_name = "FILE")]
+    report: Option<PathBuf>,
+
+    /// Warn about columns whose inference confidence (0.0-1.0) is below
+    /// this. All-null columns are only warned about, never counted
+    #[arg(long, value_name = "SCORE")]
+    min_confidence: Option<f64>,
+
+    /// Abort instead of warning when a column is below --min-confidence or
+    /// exceeds --max-int-type
+    #[arg(long)]
+    strict: bool,
+
+    /// Abort instead of warning when an existing table has column types the
+    /// loaded values don't fit, e.g. TEXT values into an INTEGER column
+    #[arg(long)]
+    strict_types: bool,
+
+    /// After loading, check that the table gained exactly the rows loaded
+    #[arg(long)]
+    verify_count: bool,
+
+    /// Create an index on each of these table columns (comma-separated)
+    /// once the data is loaded
+    #[arg(long, value_name = "COLS", value_delimiter = ',')]
+    index: Vec<String>,
+
+    /// Also index columns that look like foreign keys (`*_id`) or, from
+    /// profiling the sample, have mostly distinct values
+    #[arg(long)]
+    index_detect: bool,
+
     /// Show inferred schema without loading (dry run)
     #[arg(long)]
     dry_run: bool,
 
+    /// With --dry-run, connect read-only and compare the schema with the
+    /// existing table's columns and types
+    #[arg(long, requires = "dry_run")]
+    check_db: bool,
+
+    /// Count rows before loading so progress shows a percentage and ETA
+    /// (costs one extra read of the file; exact unless the file uses a
+    /// non-standard quote character)
+    #[arg(long)]
+    count_rows: bool,
+
     /// Verbose output
     #[arg(short, long)]
     verbose: bool,
 
-    /// Suppress progress display
+    /// Suppress progress display and status output
     #[arg(short, long)]
     quiet: bool,
+
+    /// bar, or json for one JSON object per batch and a JSON summary on stderr
+    #[arg(long, value_enum, default_value_t = ProgressFormat::Bar)]
+    progress_format: ProgressFormat,
+
+    /// Write Prometheus textfile gauges (rows, bytes, time, retries,
+    /// rejects, success) here when the load ends, even if it fails
+    #[arg(long, value_name = "FILE")]
+    metrics_file: Option<PathBuf>,
+
+    /// Labels for every metric, e.g. table=events,env=prod
+    #[arg(long, value_name = "NAME=VALUE,...", value_delimiter = ',', value_parser = parse_key_value, requires = "metrics_file")]
+    metrics_labels: Vec<(String, String)>,
 }
 
 #[tokio::main]
 async fn main() {
-    if let Err(e) = run().await {
-        eprintln!("Error: {}", e);
-        std::process::exit(1);
+    match run().await {
+        Ok(()) => {}
+        Err(LoaderError::Interrupted { rows_loaded }) => {
+            eprintln!("\nInterrupted: {} rows remain loaded", rows_loaded);
+            std::process::exit(EXIT_INTERRUPTED);
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
     }
 }
 
+/// Exit status after Ctrl-C, following the 128 + SIGINT convention
+const EXIT_INTERRUPTED: i32 = 130;
+
+/// Cancel the load on the first Ctrl-C and exit immediately on the second
+fn handle_ctrl_c(cancel: CancelToken) {
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_err() {
+            return;
+        }
+        eprintln!("\nInterrupted; finishing batches in flight (Ctrl-C again to quit now)");
+        cancel.cancel();
+
+        if tokio::signal::ctrl_c().await.is_ok() {
+            std::process::exit(EXIT_INTERRUPTED);
+        }
+    });
+}
+
 async fn run() -> Result<()> {
-    let args = Args::parse();
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    if let Some(path) = args.config.clone() {
+        apply_config(&mut args, &matches, Config::from_file(path)?)?;
+    }
 
     // Initialize logging
     init_logging(args.verbose);
 
-    // Validate inputs
-    if !args.csv_file.exists() {
-        return Err(LoaderError::FileNotFound(
-            args.csv_file.display().to_string()
-        ));
+    for (flag, value) in [
+        ("--comment-char", args.comment_char),
+        ("--quote-char", Some(args.quote_char)),
+        ("--escape-char", args.escape_char),
+    ] {
+        if value.is_some_and(|c| !c.is_ascii()) {
+            return Err(LoaderError::ConfigError(format!(
+                "{} must be a single ASCII character",
+                flag
+            )));
+        }
     }
 
-    // Determine table name
-    let table_name = args.table.unwrap_or_else(|| {
-        args.csv_file
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("imported_data")
-            .to_string()
-    });
-
-    TableSchema::validate_table_name(&table_name)?;
-
     // Parse delimiter
-    let delimiter = parser::parse_delimiter(&args.delimiter)?;
+    let detect_delimiter = args.delimiter == "auto";
+    let (delimiter, delimiter_sequence) = if detect_delimiter {
+        (b',', None)
+    } else {
+        parser::parse_delimiter_sequence(&args.delimiter)?
+    };
+    let fixed_width = args.fixed_width.as_deref().map(fixed_width::parse_spec).transpose()?;
 
-    // Parse CSV and infer schema
     let has_headers = !args.no_header;
-    let mut parser = CsvParser::from_path(&args.csv_file, delimiter, has_headers)?;
+    let csv_options = CsvOptions {
+        delimiter,
+        detect_delimiter,
+        delimiter_sequence,
+        fixed_width,
+        has_headers,
+        dedup_headers: args.dedup_headers,
+        skip_rows: args.skip_rows,
+        comment: args.comment_char.map(|c| c as u8),
+        quote: args.quote_char as u8,
+        escape: args.escape_char.map(|c| c as u8),
+        compression: args.compression,
+        mmap: args.mmap,
+        encoding: parser::parse_encoding(&args.encoding)?,
+        trim: args.trim,
+        flexible: args.flexible,
+        extra_fields: args.on_extra_fields,
+        skip_blank_rows: args.skip_blank_rows,
+        // Derived by the loader from --on-error
+        recover_quotes: false,
+    };
 
-    println!("Analyzing CSV file: {}", args.csv_file.display());
+    // Cells holding the NULL marker shouldn't make a column look like text
+    let inference_config = InferenceConfig {
+        null_tokens: args.null_tokens.iter().chain(&args.null_marker).cloned().collect(),
+        bool_true: args.bool_true.clone(),
+        bool_false: args.bool_false.clone(),
+        keep_leading_zeros: args.keep_leading_zeros,
+        date_formats: args.date_formats.clone(),
+        timestamp_formats: args.timestamp_formats.clone(),
+        decimal_separator: args.decimal_separator,
+        thousands_separator: args.thousands_separator,
+        sample_strategy: args.sample_strategy,
+        varchar: args.varchar,
+        detect_pk: args.detect_pk,
+        varchar_padding: args.varchar_padding,
+        buffer_sample: args.buffer_sample,
+        inet: !args.no_inet,
+        hex: !args.no_hex,
+        reject_nonfinite: args.reject_nonfinite,
+        strip_symbols: args.strip_symbols,
+        percent_as_fraction: args.percent_as_fraction,
+        max_int_type: args.max_int_type,
+        profile: args.profile,
+        ..InferenceConfig::new(args.sample_size, has_headers)
+    };
 
-    let inference_config = InferenceConfig::new(args.sample_size, has_headers);
-    let schema = parser.infer_schema(table_name.clone(), &inference_config)?;
+    let copy_options = CopyOptions {
+        method: args.method,
+        format: args.copy_format,
+        wire_format: args.copy_wire_format,
+        null_tokens: args.null_tokens.clone(),
+        null_marker: args.null_marker.clone(),
+        bool_true: args.bool_true.clone(),
+        bool_false: args.bool_false.clone(),
+        decimal_separator: args.decimal_separator,
+        thousands_separator: args.thousands_separator,
+        quote_identifiers: !args.no_quote_identifiers,
+        date_formats: args.date_formats.clone(),
+        timestamp_formats: args.timestamp_formats.clone(),
+        strip_symbols: args.strip_symbols,
+        percent_as_fraction: args.percent_as_fraction,
+        freeze: args.freeze,
+    };
 
-    // Display schema
-    println!("\nInferred Schema:");
-    println!("Table: {}", schema.table_name);
-    println!("Columns:");
-    for col in &schema.columns {
-        let nullable = if col.nullable { "NULL" } else { "NOT NULL" };
-        let confidence = (col.confidence() * 100.0) as u8;
-        println!(
-            "  - {} {} {} ({}% confidence, {} samples, {} nulls)",
-            col.name,
-            col.sql_type.to_sql(),
-            nullable,
-            confidence,
-            col.sample_count,
-            col.null_count
-        );
+    let batch_config = BatchConfig {
+        batch_size: args.batch_size,
+        batch_bytes: args.batch_bytes,
+        max_retries: args.max_retries,
+        retry_jitter: args.retry_jitter,
+        on_error: args.on_error,
+        max_errors: args.max_errors,
+        savepoints: args.atomic_with_savepoints,
+        isolate_errors: args.isolate_errors,
+        ..Default::default()
+    };
+
+    let ddl_options = DdlOptions {
+        quote_identifiers: !args.no_quote_identifiers,
+        defaults: args.defaults.clone(),
+        ..Default::default()
+    };
+
+    let (csv_files, connection_string) = split_inputs(&args.inputs)?;
+
+    // A dry run never connects, so it doesn't need connection info
+    let connection_string = match db::resolve_connection_string(connection_string, &args.dsn_env) {
+        Ok(dsn) => dsn,
+        Err(_) if args.dry_run && !args.check_db => String::new(),
+        Err(e) => return Err(e),
+    };
+
+    let mut builder = LoaderBuilder::new(connection_string)
+        .csv_options(csv_options)
+        .inference(inference_config)
+        .copy_options(copy_options)
+        .batch_config(batch_config)
+        .ddl_options(ddl_options)
+        .parallelism(args.parallelism)
+        .channel_depth(args.channel_depth as usize)
+        .create_table(args.create_table)
+        .strict_existence(args.strict_existence)
+        .swap(args.swap)
+        .drop_table(args.drop_table)
+        .truncate(args.truncate, args.restart_identity)
+        .atomic(args.atomic)
+        .union_schema(args.union_schema)
+        .force_text(args.force_text)
+        .column_map(load_column_map(args.map_file.as_deref(), &args.column_map)?)
+        .renames(args.renames.clone())
+        .use_table_order(args.use_table_order)
+        .target_columns(args.target_columns.clone())
+        .sanitize_columns(args.sanitize_columns)
+        .count_rows(args.count_rows)
+        .dry_run(args.dry_run)
+        .check_db(args.check_db)
+        .quiet(args.quiet)
+        .strict(args.strict)
+        .strict_types(args.strict_types)
+        .all_nullable(args.all_nullable)
+        .no_nulls(args.no_nulls)
+        .verify_count(args.verify_count)
+        .indexes(args.index.clone())
+        .index_detect(args.index_detect)
+        .progress_format(args.progress_format)
+        .verbose(args.verbose);
+
+    if let Some(table) = &args.table {
+        builder = builder.table(table);
+    }
+    if let Some(limit) = args.limit {
+        builder = builder.limit(limit);
+    }
+    if let Some(secs) = args.connect_timeout {
+        builder = builder.connect_timeout(Duration::from_secs(secs));
+    }
+    if args.connect_retries > 0 {
+        builder = builder.connect_retries(args.connect_retries);
+    }
+    if let Some(secs) = args.statement_timeout {
+        builder = builder.statement_timeout(Duration::from_secs(secs));
+    }
+    if let Some(name) = &args.app_name {
+        builder = builder.app_name(name);
+    }
+    if let Some(name) = &args.rename_to {
+        builder = builder.rename_to(name);
+    }
+    if !args.conn_options.is_empty() {
+        builder = builder.conn_options(args.conn_options.clone());
+    }
+    if !args.search_path.is_empty() {
+        builder = builder.search_path(args.search_path.clone());
+    }
+    if !args.include_columns.is_empty() {
+        builder = builder.select_columns(ColumnSelection::Include(args.include_columns.clone()));
+    } else if !args.exclude_columns.is_empty() {
+        builder = builder.select_columns(ColumnSelection::Exclude(args.exclude_columns.clone()));
+    }
+    for (name, type_name) in &args.column_types {
+        let sql_type = SqlType::from_sql(type_name).ok_or_else(|| LoaderError::ConfigError(format!(
+            "Unknown type '{}' for column '{}'",
+            type_name, name
+        )))?;
+        builder = builder.column_type(name, sql_type);
+    }
+    for (name, names) in &args.transforms {
+        for transform in names.split(',') {
+            let transform = ValueTransform::from_str(transform.trim(), true).map_err(|_| LoaderError::ConfigError(format!(
+                "Unknown transform '{}' for column '{}' (expected trim, lower, upper or nullify-empty)",
+                transform, name
+            )))?;
+            builder = builder.transform(name, transform);
+        }
+    }
+    for (name, type_name) in &args.bind_types {
+        builder = builder.bind_type(name, type_name);
+    }
+    for name in &args.lowercase {
+        builder = builder.transform(name, ValueTransform::Lower);
+    }
+    for name in &args.uppercase {
+        builder = builder.transform(name, ValueTransform::Upper);
+    }
+    if let Some(name) = &args.pk_column {
+        builder = builder.primary_key(name);
+    }
+    if let Some(path) = &args.schema_in {
+        builder = builder.schema_in(path);
+    }
+    if let Some(path) = &args.schema_out {
+        builder = builder.schema_out(path);
+    }
+    if let Some(path) = &args.ddl_out {
+        builder = builder.ddl_out(path);
+    }
+    if let Some(path) = &args.report {
+        builder = builder.report(path);
+    }
+    if let Some(min) = args.min_confidence {
+        builder = builder.min_confidence(min);
+    }
+    if let Some(path) = &args.error_file {
+        builder = builder.error_file(path);
+    }
+    if let Some(path) = &args.checkpoint_file {
+        builder = builder.checkpoint_file(path);
+    }
+    if let Some(table) = &args.ledger_table {
+        builder = builder.ledger(LedgerOptions {
+            on_duplicate: args.on_duplicate_file,
+            cheap: args.ledger_cheap,
+            force: args.force,
+            ..LedgerOptions::new(table)
+        });
+    }
+    if let Some(path) = &args.metrics_file {
+        builder = builder.metrics_file(path).metrics_labels(args.metrics_labels.clone());
     }
-    println!();
 
-    // Dry run - exit after showing schema
-    if args.dry_run {
-        println!("CREATE TABLE SQL:");
-        println!("{}", schema.to_create_table_sql());
-        println!("\nDry run complete. No data loaded.");
+    let cancel = CancelToken::new();
+    handle_ctrl_c(cancel.clone());
+
+    let stats = builder.cancel_token(cancel).build()?.load_many(&csv_files).await?;
+    // The JSON progress sink reports its own summary
+    if args.dry_run || args.progress_format == ProgressFormat::Json {
         return Ok(());
     }
 
-    // Connect to database
-    println!("Connecting to database...");
-    let db = DbConnection::connect(&args.connection_string).await?;
-
-    // Handle table creation/dropping
-    if args.drop_table {
-        println!("Dropping existing table...");
-        db.drop_table(&table_name).await?;
+    println!("\n✓ Successfully loaded {} rows into '{}'", stats.rows_loaded, stats.table_name);
+    if stats.files > 1 {
+        println!("  Files: {}", stats.files);
+    }
+    if stats.rows_skipped > 0 {
+        println!("  Resumed after: {} rows", stats.rows_skipped);
+    }
+    if stats.rows_rejected > 0 {
+        match &args.error_file {
+            Some(path) => println!("  Rejected: {} rows (written to {})", stats.rows_rejected, path.display()),
+            None => println!("  Rejected: {} rows", stats.rows_rejected),
+        }
+    }
+    if stats.rows_padded > 0 {
+        println!("  Padded short rows: {}", stats.rows_padded);
+    }
+    if stats.rows_truncated > 0 {
+        println!("  Truncated long rows: {}", stats.rows_truncated);
+    }
+    println!(
+        "  Throughput: {:.0} rows/sec, {}/s",
+        stats.throughput,
+        indicatif::HumanBytes(stats.bytes_per_sec as u64)
+    );
+    println!("  Time: {:.2}s", stats.elapsed.as_secs_f64());
+    if !stats.indexes_created.is_empty() {
+        println!(
+            "  Indexes: {} ({:.2}s)",
+            stats.indexes_created.join(", "),
+            stats.index_time.as_secs_f64()
+        );
     }
 
-    let table_exists = db.table_exists(&table_name).await?;
+    Ok(())
+}
 
-    if !table_exists {
-        if args.create_table {
-            println!("Creating table...");
-            let create_sql = schema.to_create_table_sql();
-            db.create_table(&create_sql).await?;
-        } else {
-            return Err(LoaderError::ConfigError(format!(
-                "Table '{}' does not exist. Use --create-table to create it.",
-                table_name
-            )));
+/// Split the positional arguments into CSV files and a trailing connection
+/// string, expanding glob patterns the shell left alone
+fn split_inputs(inputs: &[String]) -> Result<(Vec<PathBuf>, Option<&str>)> {
+    let (files, connection_string) = match inputs.split_last() {
+        Some((last, files)) if !files.is_empty() && is_connection_string(last) => (files, Some(last.as_str())),
+        _ => (inputs, None),
+    };
+
+    let mut paths = Vec::new();
+    for input in files {
+        if Path::new(input).exists() || !input.contains(['*', '?', '[']) {
+            paths.push(PathBuf::from(input));
+            continue;
         }
+        let pattern = glob::glob(input)
+            .map_err(|e| LoaderError::ConfigError(format!("Invalid glob pattern '{}': {}", input, e)))?;
+        let mut matched = pattern.collect::<std::result::Result<Vec<_>, _>>().map_err(std::io::Error::from)?;
+        if matched.is_empty() {
+            return Err(LoaderError::FileNotFound(input.clone()));
+        }
+        matched.sort();
+        paths.append(&mut matched);
     }
 
-    // Reset parser to beginning of file
-    parser.reset(&args.csv_file, has_headers)?;
+    Ok((paths, connection_string))
+}
 
-    // Set up batch processor
-    let batch_config = BatchConfig {
-        batch_size: args.batch_size,
-        max_retries: args.max_retries,
-        ..Default::default()
-    };
-    let batch_processor = BatchProcessor::new(batch_config);
+/// Whether a positional argument is a connection string rather than a file
+fn is_connection_string(arg: &str) -> bool {
+    if Path::new(arg).exists() {
+        return false;
+    }
+    arg.starts_with("postgres://") || arg.starts_with("postgresql://") || arg.contains('=')
+}
 
-    // Set up progress tracker
-    let progress = ProgressTracker::new(None, args.quiet);
+/// Fill in settings from a config file that weren't given as flags
+///
+/// Key/value tables are merged, with flag entries replacing config entries
+/// for the same column.
+fn apply_config(args: &mut Args, matches: &ArgMatches, config: Config) -> Result<()> {
+    let explicit = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
 
-    // Load data
-    println!("Loading data...");
+    macro_rules! from_config {
+        ($($field:ident),* $(,)?) => {$(
+            if let Some(value) = config.$field {
+                if !explicit(stringify!($field)) {
+                    args.$field = value.into();
+                }
+            }
+        )*};
+    }
 
-    let loader = CopyLoader::new(db.client(), &schema);
-    let mut total_rows = 0u64;
+    from_config!(
+        table, dsn_env, connect_timeout, connect_retries, statement_timeout, app_name, search_path, batch_size, batch_bytes, sample_size, delimiter, fixed_width, no_header, dedup_headers, skip_rows,
+        limit, comment_char, quote_char, escape_char, encoding, mmap, trim, flexible, skip_blank_rows,
+        include_columns, exclude_columns, lowercase, uppercase, null_tokens, null_marker, bool_true, bool_false,
+        date_formats, timestamp_formats, decimal_separator, thousands_separator,
+        no_inet, no_hex, reject_nonfinite, profile, strip_symbols, percent_as_fraction,
+        create_table, strict_existence, swap, rename_to, drop_table, truncate, parallelism, atomic, freeze, union_schema, force_text, max_retries,
+        max_errors, isolate_errors, error_file, checkpoint_file, ledger_table, ledger_cheap, force, sanitize_columns, no_quote_identifiers, varchar,
+        detect_pk, pk_column, all_nullable, no_nulls, verify_count, index, index_detect, check_db, metrics_file, strict_types, use_table_order, target_columns,
+        buffer_sample, keep_leading_zeros, restart_identity, channel_depth, atomic_with_savepoints, varchar_padding, map_file, schema_in, schema_out,
+        ddl_out, report, min_confidence, strict, count_rows,
+    );
 
-    // Process batches
-    let records = parser.records();
-    let batches = BatchIterator::new(records, args.batch_size);
+    if let Some(value) = config.max_int_type.filter(|_| !explicit("max_int_type")) {
+        args.max_int_type = config::parse_enum("max_int_type", &value)?;
+    }
+    if let Some(value) = config.compression.filter(|_| !explicit("compression")) {
+        args.compression = config::parse_enum("compression", &value)?;
+    }
+    if let Some(value) = config.sample_strategy.filter(|_| !explicit("sample_strategy")) {
+        args.sample_strategy = config::parse_enum("sample_strategy", &value)?;
+    }
+    if let Some(value) = config.copy_format.filter(|_| !explicit("copy_format")) {
+        args.copy_format = config::parse_enum("copy_format", &value)?;
+    }
+    if let Some(value) = config.on_duplicate_file.filter(|_| !explicit("on_duplicate_file")) {
+        args.on_duplicate_file = config::parse_enum("on_duplicate_file", &value)?;
+    }
+    if let Some(value) = config.copy_wire_format.filter(|_| !explicit("copy_wire_format")) {
+        args.copy_wire_format = config::parse_enum("copy_wire_format", &value)?;
+    }
+    if let Some(value) = config.method.filter(|_| !explicit("method")) {
+        args.method = config::parse_enum("method", &value)?;
+    }
+    if let Some(value) = config.on_error.filter(|_| !explicit("on_error")) {
+        args.on_error = config::parse_enum("on_error", &value)?;
+    }
+    if let Some(value) = config.on_extra_fields.filter(|_| !explicit("on_extra_fields")) {
+        args.on_extra_fields = config::parse_enum("on_extra_fields", &value)?;
+    }
+    if let Some(value) = config.retry_jitter.filter(|_| !explicit("retry_jitter")) {
+        args.retry_jitter = config::parse_enum("retry_jitter", &value)?;
+    }
+    if let Some(value) = config.progress_format.filter(|_| !explicit("progress_format")) {
+        args.progress_format = config::parse_enum("progress_format", &value)?;
+    }
 
-    for batch_result in batches {
-        let batch = batch_result?;
-        let batch_size = batch.len() as u64;
+    for (from_file, from_flags) in [
+        (config.column_types, &mut args.column_types),
+        (config.transform, &mut args.transforms),
+        (config.bind_type, &mut args.bind_types),
+        (config.map, &mut args.column_map),
+        (config.rename, &mut args.renames),
+        (config.defaults, &mut args.defaults),
+        (config.metrics_labels, &mut args.metrics_labels),
+        (config.conn_options, &mut args.conn_options),
+    ] {
+        let mut merged: Vec<(String, String)> = from_file
+            .into_iter()
+            .filter(|(key, _)| !from_flags.iter().any(|(k, _)| k == key))
+            .collect();
+        merged.append(from_flags);
+        *from_flags = merged;
+    }
 
-        match batch_processor.process_batch(&loader, batch).await {
-            Ok(count) => {
-                total_rows += count;
-                progress.inc(batch_size);
-            }
-            Err(e) => {
-                progress.finish_with_error(&e.to_string());
-                return Err(e);
-            }
+    Ok(())
+}
+
+/// Merge column mappings from a JSON file with those given on the command line
+fn load_column_map(path: Option<&Path>, cli: &[(String, String)]) -> Result<Vec<(String, String)>> {
+    let mut map: Vec<(String, String)> = match path {
+        Some(path) => {
+            let file = File::open(path)?;
+            let entries: BTreeMap<String, String> = serde_json::from_reader(file).map_err(|e| {
+                LoaderError::ConfigError(format!("Invalid map file {}: {}", path.display(), e))
+            })?;
+            entries.into_iter().collect()
         }
+        None => Vec::new(),
+    };
+
+    for (csv_col, table_col) in cli {
+        map.retain(|(c, _)| c != csv_col);
+        map.push((csv_col.clone(), table_col.clone()));
     }
 
-    progress.finish();
+    Ok(map)
+}
 
-    println!("\n✓ Successfully loaded {} rows into '{}'", total_rows, table_name);
-    println!("  Throughput: {:.0} rows/sec", progress.throughput());
-    println!("  Time: {:.2}s", progress.elapsed().as_secs_f64());
+/// Parse a `key=value` command-line argument
+fn parse_key_value(s: &str) -> std::result::Result<(String, String), String> {
+    let (key, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected KEY=VALUE, got '{}'", s))?;
 
-    Ok(())
+    if key.is_empty() {
+        return Err(format!("missing key in '{}'", s));
+    }
+
+    Ok((key.to_string(), value.to_string()))
+}
+
+/// Parse a byte count with an optional K, M or G suffix (powers of 1024)
+fn parse_byte_size(s: &str) -> std::result::Result<usize, String> {
+    let upper = s.trim().to_ascii_uppercase();
+    let number = upper.strip_suffix('B').unwrap_or(&upper);
+    let (digits, multiplier) = match number.char_indices().last() {
+        Some((i, 'K')) => (&number[..i], 1 << 10),
+        Some((i, 'M')) => (&number[..i], 1 << 20),
+        Some((i, 'G')) => (&number[..i], 1 << 30),
+        _ => (number, 1),
+    };
+
+    let size: usize = digits
+        .trim()
+        .parse()
+        .map_err(|_| format!("expected a size like 512K or 64M, got '{}'", s))?;
+    match size.checked_mul(multiplier) {
+        Some(0) | None => Err(format!("size must be between 1 byte and {} bytes", usize::MAX)),
+        Some(bytes) => Ok(bytes),
+    }
 }
 
 fn init_logging(verbose: bool) {
@@ -233,3 +1077,56 @@ fn init_logging(verbose: bool) {
         .with_target(false)
         .init();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Flags that only make sense for a single run
+    const NOT_IN_CONFIG: [&str; 4] = ["config", "dry_run", "verbose", "quiet"];
+
+    #[test]
+    fn test_every_flag_has_a_config_key() {
+        for arg in Args::command().get_arguments() {
+            let Some(long) = arg.get_long() else {
+                continue;
+            };
+            let key = long.replace('-', "_");
+            if NOT_IN_CONFIG.contains(&key.as_str()) || ["help", "version"].contains(&long) {
+                continue;
+            }
+
+            // A value of the wrong type still shows the key is known
+            let parsed = toml::from_str::<Config>(&format!("{} = 0", key));
+            if let Err(e) = parsed {
+                assert!(!e.to_string().contains("unknown field"), "--{} has no config key: {}", long, e);
+            }
+        }
+    }
+
+    #[test]
+    fn test_apply_config_uses_flag_names() {
+        let config: Config = toml::from_str(r#"
+            null_token = ["NA"]
+            date_format = ["%d/%m/%Y"]
+            retry_jitter = "full"
+            varchar_padding = 1.5
+            schema_out = "schema.json"
+
+            [column_type]
+            zip = "TEXT"
+        "#).unwrap();
+
+        let matches = Args::command().get_matches_from(["csv-sql-loader", "data.csv", "--varchar", "--varchar-padding", "2"]);
+        let mut args = Args::from_arg_matches(&matches).unwrap();
+        apply_config(&mut args, &matches, config).unwrap();
+
+        assert_eq!(args.null_tokens, ["NA"]);
+        assert_eq!(args.date_formats, ["%d/%m/%Y"]);
+        assert_eq!(args.retry_jitter, RetryJitter::Full);
+        assert_eq!(args.schema_out.as_deref(), Some(Path::new("schema.json")));
+        assert_eq!(args.column_types, [("zip".to_string(), "TEXT".to_string())]);
+        // The command line wins
+        assert_eq!(args.varchar_padding, 2.0);
+    }
+}
@@ -0,0 +1,127 @@
+//! Load metrics in the Prometheus textfile format
+//!
+//! The file is meant for node_exporter's textfile collector, which reads
+//! every `*.prom` file in a directory.
+
+use crate::errors::{LoaderError, Result};
+use crate::loader::LoadStats;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+/// Check that label names are valid Prometheus identifiers
+pub fn validate_labels(labels: &[(String, String)]) -> Result<()> {
+    for (name, _) in labels {
+        let mut chars = name.chars();
+        let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid || name.starts_with("__") {
+            return Err(LoaderError::ConfigError(format!(
+                "Invalid metrics label name '{}'",
+                name
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Render the load's gauges, with `success` as 1 or 0
+pub fn render(labels: &[(String, String)], stats: &LoadStats, success: bool) -> String {
+    let labels = if labels.is_empty() {
+        String::new()
+    } else {
+        let pairs: Vec<String> = labels
+            .iter()
+            .map(|(name, value)| format!("{}=\"{}\"", name, escape_label_value(value)))
+            .collect();
+        format!("{{{}}}", pairs.join(","))
+    };
+
+    let gauges: [(&str, &str, f64); 8] = [
+        ("rows_loaded", "Rows loaded into the table", stats.rows_loaded as f64),
+        ("bytes_read", "CSV bytes read while loading", stats.bytes_read as f64),
+        ("elapsed_seconds", "Time spent loading", stats.elapsed.as_secs_f64()),
+        ("rows_per_second", "Load throughput", stats.throughput),
+        ("bytes_per_second", "CSV bytes read per second", stats.bytes_per_sec),
+        ("retries", "Batch attempts retried after transient failures", stats.retries as f64),
+        ("rows_rejected", "Rows rejected in skip mode", stats.rows_rejected as f64),
+        ("success", "1 if the load completed, 0 if it failed", if success { 1.0 } else { 0.0 }),
+    ];
+
+    let mut out = String::new();
+    for (name, help, value) in gauges {
+        let name = format!("csv_loader_{}", name);
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} gauge", name);
+        let _ = writeln!(out, "{}{} {}", name, labels, value);
+    }
+    out
+}
+
+/// Write the metrics file, replacing any old one in a single rename so the
+/// collector never reads a partial file
+pub fn write_textfile<P: AsRef<Path>>(
+    path: P,
+    labels: &[(String, String)],
+    stats: &LoadStats,
+    success: bool,
+) -> Result<()> {
+    let path = path.as_ref();
+    let tmp = path.with_extension("prom.tmp");
+    fs::write(&tmp, render(labels, stats, success))?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_render_metrics() {
+        let stats = LoadStats {
+            rows_loaded: 1200,
+            bytes_read: 4096,
+            retries: 2,
+            elapsed: Duration::from_millis(1500),
+            throughput: 800.0,
+            bytes_per_sec: 2730.0,
+            ..Default::default()
+        };
+        let labels = vec![
+            ("table".to_string(), "events".to_string()),
+            ("env".to_string(), "say \"prod\"".to_string()),
+        ];
+
+        let text = render(&labels, &stats, true);
+        assert!(text.contains("# TYPE csv_loader_rows_loaded gauge\n"));
+        assert!(text.contains("csv_loader_rows_loaded{table=\"events\",env=\"say \\\"prod\\\"\"} 1200\n"));
+        assert!(text.contains("csv_loader_elapsed_seconds{table=\"events\",env=\"say \\\"prod\\\"\"} 1.5\n"));
+        assert!(text.contains("csv_loader_bytes_per_second{table=\"events\",env=\"say \\\"prod\\\"\"} 2730\n"));
+        assert!(text.ends_with("csv_loader_success{table=\"events\",env=\"say \\\"prod\\\"\"} 1\n"));
+
+        let text = render(&[], &stats, false);
+        assert!(text.contains("csv_loader_retries 2\n"));
+        assert!(text.contains("csv_loader_success 0\n"));
+    }
+
+    #[test]
+    fn test_validate_labels() {
+        let label = |name: &str| vec![(name.to_string(), "x".to_string())];
+
+        assert!(validate_labels(&label("env")).is_ok());
+        assert!(validate_labels(&label("_shard2")).is_ok());
+        assert!(validate_labels(&label("2env")).is_err());
+        assert!(validate_labels(&label("env-name")).is_err());
+        assert!(validate_labels(&label("__name__")).is_err());
+    }
+}
@@ -1,43 +1,249 @@
 //! CSV streaming parser
 
+use crate::compression::{self, Compression};
 use crate::errors::{LoaderError, Result};
-use crate::schema::{InferenceConfig, TableSchema};
-use csv::{Reader, ReaderBuilder, StringRecord};
+use crate::fixed_width::{FixedWidthColumn, FixedWidthReader};
+use crate::quoting::{Dialect, MalformedLines, QuoteGuard};
+use crate::reject::RejectedRow;
+use crate::report::InferenceResult;
+use crate::schema::{InferenceConfig, SampleStrategy, TableSchema};
+use csv::{Reader, ReaderBuilder, StringRecord, Trim};
+use encoding_rs::Encoding;
+use encoding_rs_io::DecodeReaderBytesBuilder;
+use futures_util::Stream;
+use memmap2::Mmap;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::{HashSet, VecDeque};
 use std::fs::File;
-use std::path::Path;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::SystemTime;
+use tokio::sync::mpsc;
+
+/// Settings describing the CSV dialect and layout of the input file
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    /// Field delimiter
+    pub delimiter: u8,
+    /// Sniff the delimiter from the start of each file instead of using
+    /// `delimiter`
+    pub detect_delimiter: bool,
+    /// Multi-character delimiter, translated to `delimiter` before parsing
+    pub delimiter_sequence: Option<Vec<u8>>,
+    /// Slice lines into these columns instead of parsing CSV
+    pub fixed_width: Option<Vec<FixedWidthColumn>>,
+    /// Whether the first record is a header
+    pub has_headers: bool,
+    /// Number repeated header names (`id`, `id_2`) instead of failing
+    pub dedup_headers: bool,
+    /// Raw lines discarded before the header (or first record)
+    pub skip_rows: usize,
+    /// Lines starting with this byte are ignored
+    pub comment: Option<u8>,
+    /// Quote character
+    pub quote: u8,
+    /// Escape character for quotes inside quoted fields, in addition to
+    /// doubling them
+    pub escape: Option<u8>,
+    /// Compression of the input; `Auto` goes by extension, then magic bytes
+    pub compression: Compression,
+    /// Memory-map an uncompressed file for the first pass over it, which
+    /// infers the schema; `reset` checks the file is unchanged and reads it
+    /// normally again. Rows read before a `reset` aren't checked, so only
+    /// set it for a parser that is reset before loading
+    pub mmap: bool,
+    /// Source encoding to transcode to UTF-8; `None` reads UTF-8 as-is
+    pub encoding: Option<&'static Encoding>,
+    /// Strip leading and trailing whitespace from headers and fields
+    pub trim: bool,
+    /// Accept rows whose field count differs from the header; short rows
+    /// are padded with NULLs
+    pub flexible: bool,
+    /// What to do with fields beyond the header width in flexible mode
+    pub extra_fields: ExtraFields,
+    /// Drop rows whose fields are all empty or whitespace, such as `,,,`
+    /// or a trailing line of spaces; see [`is_blank`]
+    pub skip_blank_rows: bool,
+    /// Drop lines with malformed quoting, such as an unterminated quote,
+    /// instead of letting them swallow the lines after them; see
+    /// [`crate::quoting`]; the loader turns this on with `OnError::Skip`
+    pub recover_quotes: bool,
+}
+
+/// Handling of trailing fields beyond the header width
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ExtraFields {
+    /// Drop the extra fields and load the rest of the row
+    #[default]
+    Drop,
+    /// Fail on the row
+    Error,
+}
+
+/// Rows that were reshaped to fit the header width in flexible mode
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RaggedRows {
+    /// Short rows padded with NULLs
+    pub padded: u64,
+    /// Long rows whose extra fields were dropped
+    pub truncated: u64,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            detect_delimiter: false,
+            delimiter_sequence: None,
+            fixed_width: None,
+            has_headers: true,
+            dedup_headers: false,
+            skip_rows: 0,
+            comment: None,
+            quote: b'"',
+            escape: None,
+            compression: Compression::Auto,
+            mmap: false,
+            encoding: None,
+            trim: false,
+            flexible: false,
+            extra_fields: ExtraFields::Drop,
+            skip_blank_rows: true,
+            recover_quotes: false,
+        }
+    }
+}
+
+/// Where records come from
+enum Source {
+    Csv(Reader<Box<dyn BufRead + Send>>),
+    FixedWidth(FixedWidthReader),
+}
 
 /// CSV parser with streaming capability
 pub struct CsvParser {
-    reader: Reader<File>,
+    source: Source,
+    /// File being read, for seeking with the spread sample strategy
+    path: PathBuf,
     headers: StringRecord,
-    delimiter: u8,
+    options: CsvOptions,
+    /// Indices of the CSV columns to keep, when only some are loaded
+    selection: Option<Vec<usize>>,
+    ragged: RaggedRows,
+    /// Rows kept from inference, returned before reading further
+    replay: VecDeque<(u64, Vec<String>)>,
+    /// Size and modification time of a memory-mapped file when it was mapped
+    mapped: Option<FileStamp>,
+    /// Lines dropped for malformed quoting, with `recover_quotes`
+    malformed: MalformedLines,
+    /// Lines of quoted empty fields, which aren't blank rows
+    quoted_blanks: QuotedBlankLines,
+    /// Bytes read, updated as numbered records are read
+    byte_counter: ByteCounter,
+}
+
+/// Bytes of CSV text a parser has read, readable from another thread
+/// while it runs
+///
+/// Compressed input is counted after decompression.
+#[derive(Debug, Clone, Default)]
+pub struct ByteCounter(Arc<AtomicU64>);
+
+impl ByteCounter {
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// What is checked to tell that a memory-mapped file was changed
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FileStamp {
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+impl FileStamp {
+    fn of(path: &Path) -> Result<Self> {
+        let metadata = std::fs::metadata(path)?;
+        Ok(Self {
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+}
+
+/// Which CSV columns take part in inference and loading
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ColumnSelection {
+    /// Keep only these columns
+    Include(Vec<String>),
+    /// Keep every column except these
+    Exclude(Vec<String>),
 }
 
 impl CsvParser {
     /// Create a new CSV parser from a file path
     pub fn from_path<P: AsRef<Path>>(path: P, delimiter: u8, has_headers: bool) -> Result<Self> {
-        let file = File::open(&path).map_err(|_| {
-            LoaderError::FileNotFound(path.as_ref().display().to_string())
-        })?;
-
-        let mut reader = ReaderBuilder::new()
-            .delimiter(delimiter)
-            .has_headers(has_headers)
-            .flexible(false) // Enforce consistent column count
-            .from_reader(file);
-
-        let headers = if has_headers {
-            let h = reader.headers()?.clone();
-            if h.is_empty() {
-                return Err(LoaderError::EmptyFile);
-            }
-            h
+        Self::from_path_with(path, CsvOptions {
+            delimiter,
+            has_headers,
+            ..Default::default()
+        })
+    }
+
+    /// Create a new CSV parser from a file path using dialect settings
+    pub fn from_path_with<P: AsRef<Path>>(path: P, mut options: CsvOptions) -> Result<Self> {
+        if options.detect_delimiter && options.fixed_width.is_none() && options.delimiter_sequence.is_none() {
+            options.delimiter = detect_delimiter(&path, &options)?;
+            options.detect_delimiter = false;
+            tracing::info!("Detected delimiter {:?}", options.delimiter as char);
+        }
+        let mapped = if options.mmap { Some(FileStamp::of(path.as_ref())?) } else { None };
+
+        if options.fixed_width.is_some() {
+            let reader = open_fixed_width(&path, &options)?;
+            return Ok(Self {
+                headers: StringRecord::from(reader.headers()),
+                source: Source::FixedWidth(reader),
+                path: path.as_ref().to_path_buf(),
+                options,
+                selection: None,
+                ragged: RaggedRows::default(),
+                replay: VecDeque::new(),
+                mapped,
+                malformed: MalformedLines::default(),
+                quoted_blanks: QuotedBlankLines::default(),
+                byte_counter: ByteCounter::default(),
+            });
+        }
+
+        let malformed = MalformedLines::default();
+        let quoted_blanks = QuotedBlankLines::default();
+        let mut reader = open_reader(&path, &options, &malformed, &quoted_blanks)?;
+
+        // Without a header row the reader still reports the first record
+        // here, and yields it again as data
+        let first = reader.headers()?.clone();
+        if first.is_empty() {
+            return Err(LoaderError::EmptyFile);
+        }
+        let header_line = options.skip_rows as u64 + 1;
+        if options.has_headers && malformed.contains(header_line) {
+            return Err(LoaderError::SchemaInferenceError(
+                "header has malformed quoting".to_string(),
+            ).at_line(header_line));
+        }
+
+        let headers = if options.has_headers {
+            dedup_headers(&first, options.dedup_headers)?
         } else {
             // Generate default column names: col_0, col_1, etc.
-            let first_record = reader.records().next()
-                .ok_or(LoaderError::EmptyFile)??;
-
-            let default_headers: Vec<String> = (0..first_record.len())
+            let default_headers: Vec<String> = (0..first.len())
                 .map(|i| format!("col_{}", i))
                 .collect();
 
@@ -45,88 +251,1186 @@ impl CsvParser {
         };
 
         Ok(Self {
-            reader,
+            source: Source::Csv(reader),
+            path: path.as_ref().to_path_buf(),
             headers,
-            delimiter,
+            options,
+            selection: None,
+            ragged: RaggedRows::default(),
+            replay: VecDeque::new(),
+            mapped,
+            malformed,
+            quoted_blanks,
+            byte_counter: ByteCounter::default(),
         })
     }
 
+    /// Restrict the parser to a subset of columns
+    ///
+    /// Kept columns stay in CSV order. Naming a column that isn't in the
+    /// file is an error.
+    pub fn select_columns(&mut self, selection: &ColumnSelection) -> Result<()> {
+        let names = match selection {
+            ColumnSelection::Include(names) | ColumnSelection::Exclude(names) => names,
+        };
+
+        if let Some(unknown) = names.iter().find(|name| !self.headers.iter().any(|h| h == name.as_str())) {
+            return Err(LoaderError::ConfigError(format!(
+                "Unknown column '{}' (available: {})",
+                unknown,
+                self.headers.iter().collect::<Vec<_>>().join(", ")
+            )));
+        }
+
+        let keep: Vec<usize> = self.headers
+            .iter()
+            .enumerate()
+            .filter(|(_, header)| {
+                let listed = names.iter().any(|name| name == header);
+                matches!(selection, ColumnSelection::Include(_)) == listed
+            })
+            .map(|(index, _)| index)
+            .collect();
+
+        if keep.is_empty() {
+            return Err(LoaderError::ConfigError("No columns left to load".to_string()));
+        }
+
+        self.selection = Some(keep);
+        Ok(())
+    }
+
+    /// Rename header columns, given as `(old, new)` pairs of CSV header and
+    /// new name
+    ///
+    /// New names must be plain identifiers and stay unique among the loaded
+    /// columns.
+    pub fn rename_columns(&mut self, renames: &[(String, String)]) -> Result<()> {
+        let mut headers: Vec<String> = self.headers.iter().map(str::to_string).collect();
+        for (old, new) in renames {
+            let index = self.headers.iter().position(|h| h == old).ok_or_else(|| {
+                LoaderError::ConfigError(format!(
+                    "Unknown column '{}' in --rename (available: {})",
+                    old,
+                    self.headers.iter().collect::<Vec<_>>().join(", ")
+                ))
+            })?;
+            TableSchema::validate_column_name(new)?;
+            headers[index] = new.clone();
+        }
+
+        let renamed = StringRecord::from(headers);
+        let loaded = project(&renamed, renamed.len(), self.selection.as_deref());
+        let duplicate = loaded
+            .iter()
+            .enumerate()
+            .find_map(|(i, name)| loaded[..i].contains(name).then_some(name));
+        if let Some(name) = duplicate {
+            return Err(LoaderError::ConfigError(format!(
+                "Column name '{}' appears twice after --rename",
+                name
+            )));
+        }
+
+        self.headers = renamed;
+        Ok(())
+    }
+
     /// Get column headers
     pub fn headers(&self) -> Vec<String> {
-        self.headers.iter().map(String::from).collect()
+        project(&self.headers, self.headers.len(), self.selection.as_deref())
+    }
+
+    /// Get every column header, including columns left out by
+    /// [`select_columns`](Self::select_columns)
+    pub fn all_headers(&self) -> Vec<String> {
+        self.headers.iter().map(str::to_string).collect()
+    }
+
+    /// Bytes of CSV text read since the last reset
+    pub fn bytes_read(&self) -> u64 {
+        match &self.source {
+            Source::Csv(reader) => reader.position().byte(),
+            Source::FixedWidth(reader) => reader.bytes_read(),
+        }
+    }
+
+    /// A live view of [`CsvParser::bytes_read`], for reporting progress
+    /// from another thread; it is updated by [`CsvParser::numbered_records`]
+    pub fn byte_counter(&self) -> ByteCounter {
+        self.byte_counter.clone()
+    }
+
+    /// Rows padded or truncated since the last reset
+    pub fn ragged_rows(&self) -> RaggedRows {
+        self.ragged
+    }
+
+    /// Lines dropped for malformed quoting since the last call or reset,
+    /// with `recover_quotes`
+    ///
+    /// Each comes back as a single field holding the raw line.
+    pub fn take_malformed_lines(&mut self) -> Vec<RejectedRow> {
+        self.malformed.take()
+    }
+
+    /// A handle to the lines dropped for malformed quoting, for taking them
+    /// while another thread reads; a reset starts a new one
+    pub fn malformed_lines(&self) -> MalformedLines {
+        self.malformed.clone()
+    }
+
+    /// Read the next row with its 1-based source line, fitted to the header
+    /// width and column selection
+    fn next_row(&mut self) -> Option<Result<(u64, Vec<String>)>> {
+        if let Some(row) = self.replay.pop_front() {
+            return Some(Ok(row));
+        }
+
+        let skip_blank = self.options.skip_blank_rows;
+        let reader = match &mut self.source {
+            Source::Csv(reader) => reader,
+            Source::FixedWidth(reader) => loop {
+                return match reader.read_record() {
+                    // Fields are trimmed, so a line of spaces has only empty ones
+                    Ok(Some((_, fields))) if skip_blank && fields.iter().all(String::is_empty) => continue,
+                    Ok(Some((line, fields))) => {
+                        let record = StringRecord::from(fields);
+                        let line = line + self.options.skip_rows as u64;
+                        Some(Ok((line, project(&record, record.len(), self.selection.as_deref()))))
+                    }
+                    Ok(None) => None,
+                    Err(e) => Some(Err(e)),
+                };
+            },
+        };
+        let (record, line) = loop {
+            match reader.records().next()? {
+                Ok(record) => {
+                    // The reader counts lines from the end of the skipped banner
+                    let line = record.position().map_or(0, |p| p.line()) + self.options.skip_rows as u64;
+                    let blank = is_blank(&record);
+                    if blank && skip_blank && !self.quoted_blanks.contains(line) {
+                        continue;
+                    }
+                    if blank && self.malformed.contains(line) {
+                        continue;
+                    }
+                    break (record, line);
+                }
+                Err(e) => return Some(Err(e.into())),
+            }
+        };
+
+        // The reader accepts any width so blank rows can be dropped first
+        let width = self.headers.len();
+        if record.len() != width {
+            let long = record.len() > width;
+            if !self.options.flexible || (long && self.options.extra_fields == ExtraFields::Error) {
+                return Some(Err(LoaderError::TypeConversionError(format!(
+                    "row has {} fields but the header has {}",
+                    record.len(),
+                    width
+                )).at_line(line)));
+            }
+            if long {
+                self.ragged.truncated += 1;
+            } else {
+                self.ragged.padded += 1;
+            }
+        }
+
+        Some(Ok((line, project(&record, width, self.selection.as_deref()))))
     }
 
     /// Infer schema by sampling rows
+    ///
+    /// With `buffer_sample` and the head strategy, the sampled rows are kept
+    /// and handed out again by `numbered_records`, which then continues
+    /// where inference stopped instead of needing a `reset`.
     pub fn infer_schema(&mut self, table_name: String, config: &InferenceConfig) -> Result<TableSchema> {
         let mut schema = TableSchema::new(table_name, self.headers());
+        let full_scan = config.sample_size == 0;
+
+        let count = if config.sample_strategy != SampleStrategy::Head && !full_scan {
+            let sample = match config.sample_strategy {
+                SampleStrategy::Reservoir => self.reservoir_sample(config.sample_size)?,
+                _ => self.spread_sample(config.sample_size)?,
+            };
+            schema.update_rows_with(&sample, config)?;
+            sample.len()
+        } else {
+            // Stream rows into the schema a chunk at a time; a sample size of
+            // 0 scans the whole file
+            let mut count = 0;
+            let mut kept = VecDeque::new();
+            let mut chunk = Vec::new();
+            loop {
+                let more = full_scan || count < config.sample_size;
+                let row = if more { self.next_row() } else { None };
+                let Some(row) = row else {
+                    schema.update_rows_with(&chunk, config)?;
+                    if config.buffer_sample {
+                        kept.extend(chunk);
+                    }
+                    break;
+                };
+
+                chunk.push(row?);
+                count += 1;
+                if chunk.len() == INFERENCE_CHUNK_ROWS {
+                    schema.update_rows_with(&chunk, config)?;
+                    if config.buffer_sample {
+                        kept.extend(chunk.drain(..));
+                    } else {
+                        chunk.clear();
+                    }
+                }
+            }
+            self.replay = kept;
+            count
+        };
+
+        if count == 0 {
+            return Err(LoaderError::EmptyFile);
+        }
+
+        schema.finalize_with(config);
+        Ok(schema)
+    }
+
+    /// Infer schema by sampling rows, with warnings about degraded types,
+    /// all-null columns and columns below `min_confidence`
+    pub fn infer_schema_detailed(
+        &mut self,
+        table_name: String,
+        config: &InferenceConfig,
+        min_confidence: f64,
+    ) -> Result<InferenceResult> {
+        let schema = self.infer_schema(table_name, config)?;
+        Ok(InferenceResult::new(schema, min_confidence))
+    }
+
+    /// Read to the end of the file, keeping a uniform random sample of
+    /// `size` rows (Algorithm R)
+    ///
+    /// The generator is seeded with a constant so the same file always
+    /// infers the same schema.
+    fn reservoir_sample(&mut self, size: usize) -> Result<Vec<(u64, Vec<String>)>> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut sample = Vec::with_capacity(size);
+        let mut seen = 0usize;
+
+        while let Some(row) = self.next_row() {
+            let row = row?;
+            if sample.len() < size {
+                sample.push(row);
+            } else {
+                let slot = rng.gen_range(0..=seen);
+                if slot < size {
+                    sample[slot] = row;
+                }
+            }
+            seen += 1;
+        }
+
+        // Keep file order so errors and promotions read naturally
+        sample.sort_by_key(|(line, _)| *line);
+        Ok(sample)
+    }
+
+    /// Sample a third of `size` rows each from the head, middle and tail of
+    /// the file
+    ///
+    /// The middle and tail are found by seeking to byte offsets estimated
+    /// from the head's row length, then reading from the next line, so
+    /// sampled rows never repeat. A file with fewer than about `size * 2`
+    /// rows is simply read to the end. Compressed, transcoded, fixed-width
+    /// and non-regular inputs can't be seeked in and are sampled from the
+    /// head instead.
+    ///
+    /// Rows past the head are numbered by counting the newlines before
+    /// each seek point, a plain byte scan. A seek landing inside a quoted
+    /// field that spans lines can misread the rows after it; rows that then
+    /// don't fit the header are left out of the sample.
+    fn spread_sample(&mut self, size: usize) -> Result<Vec<(u64, Vec<String>)>> {
+        let seekable = self.options.fixed_width.is_none()
+            && self.options.encoding.is_none()
+            && !compression::is_compressed(&self.path, self.options.compression)?
+            && std::fs::metadata(&self.path)?.is_file();
+        if !seekable {
+            tracing::warn!(
+                "--sample-strategy spread can't seek in {}; sampling its first {} rows instead",
+                self.path.display(),
+                size
+            );
+            return (0..size).map_while(|_| self.next_row()).collect();
+        }
+
+        let part = size / 3;
+        let mut sample = (0..size - 2 * part).map_while(|_| self.next_row()).collect::<Result<Vec<_>>>()?;
+        if sample.len() < size - 2 * part {
+            return Ok(sample);
+        }
+
+        // Rows read so far, counting the header, give the typical row length
+        let head_end = data_start(&self.path, &self.options)? + self.bytes_read();
+        let rows_read = sample.len() as u64 + self.options.has_headers as u64;
+        let row_bytes = self.bytes_read().div_ceil(rows_read).max(1);
+        let remaining = std::fs::metadata(&self.path)?.len().saturating_sub(head_end);
+        if remaining / row_bytes < 5 * part as u64 {
+            while let Some(row) = self.next_row() {
+                sample.push(row?);
+            }
+            return Ok(sample);
+        }
+
+        let middle = head_end + remaining / 2 - part as u64 * row_bytes / 2;
+        let mut segment = self.segment_at(middle, (0, 1))?;
+        sample.extend(segment.rows().take(part).collect::<Result<Vec<_>>>()?);
+        let middle_end = segment.end();
+        let anchor = segment.anchor();
+
+        // Rows near the end may be longer than the head's, so the window is
+        // widened until it holds enough of them
+        let end = head_end + remaining;
+        let mut window = 2 * part as u64 * row_bytes;
+        let tail_rows = loop {
+            let tail = end.saturating_sub(window).max(middle_end);
+            let mut tail_rows = VecDeque::with_capacity(part + 1);
+            for row in self.segment_at(tail, anchor)?.rows() {
+                tail_rows.push_back(row?);
+                if tail_rows.len() > part {
+                    tail_rows.pop_front();
+                }
+            }
+            if tail_rows.len() == part || tail == middle_end {
+                break tail_rows;
+            }
+            window *= 4;
+        };
+        sample.extend(tail_rows);
+
+        Ok(sample)
+    }
+
+    /// A parser over this file's rows from the first line starting at or
+    /// after byte `offset`, numbered from `anchor`, the `(offset, line)` of
+    /// an earlier line start
+    fn segment_at(&self, offset: u64, anchor: (u64, u64)) -> Result<Segment> {
+        let mut file = BufReader::with_capacity(64 * 1024, File::open(&self.path)?);
+        file.seek(SeekFrom::Start(offset.saturating_sub(1)))?;
+        // Reading to the next newline lands at the start of a line
+        let start = offset.saturating_sub(1) + file.read_until(b'\n', &mut Vec::new())? as u64;
+        let (anchor_offset, anchor_line) = anchor;
+        let first_line = anchor_line + count_newlines(&self.path, anchor_offset, start)?;
+
+        let options = CsvOptions { has_headers: false, skip_rows: 0, ..self.options.clone() };
+        let mut source: Box<dyn BufRead + Send> = Box::new(file);
+        if let Some(sequence) = &options.delimiter_sequence {
+            source = Box::new(DelimiterTranslator::new(source, sequence.clone(), options.delimiter, options.quote, options.escape));
+        }
+        let malformed = MalformedLines::default();
+        let quoted_blanks = QuotedBlankLines::default();
+        let reader = csv_reader(source, &options, &malformed, &quoted_blanks, 1);
+
+        Ok(Segment {
+            start,
+            first_line,
+            parser: CsvParser {
+                source: Source::Csv(reader),
+                path: self.path.clone(),
+                headers: self.headers.clone(),
+                options,
+                selection: self.selection.clone(),
+                ragged: RaggedRows::default(),
+                replay: VecDeque::new(),
+                mapped: None,
+                malformed,
+                quoted_blanks,
+                byte_counter: ByteCounter::default(),
+            },
+        })
+    }
+
+    /// Skip up to `count` data records, returning how many were skipped
+    ///
+    /// Records are read by the CSV reader, so a quoted field spanning
+    /// several lines is skipped as one record. Skipped rows are not checked
+    /// against the header width or counted as ragged. Blank rows don't
+    /// count towards `count` when `skip_blank_rows` drops them.
+    pub fn skip_records(&mut self, count: u64) -> Result<u64> {
+        let mut skipped = 0;
+        let mut record = StringRecord::new();
+        let skip_blank = self.options.skip_blank_rows;
+
+        while skipped < count {
+            if self.replay.pop_front().is_none() {
+                let (more, blank, dropped) = match &mut self.source {
+                    Source::Csv(reader) => {
+                        let more = reader.read_record(&mut record)?;
+                        let line = record.position().map_or(0, |p| p.line()) + self.options.skip_rows as u64;
+                        let blank = more && is_blank(&record);
+                        (more, blank && !self.quoted_blanks.contains(line), blank && self.malformed.contains(line))
+                    }
+                    Source::FixedWidth(reader) => match reader.read_record()? {
+                        Some((_, fields)) => (true, fields.iter().all(String::is_empty), false),
+                        None => (false, false, false),
+                    },
+                };
+                if !more {
+                    break;
+                }
+                if skip_blank && blank || dropped {
+                    continue;
+                }
+            }
+            skipped += 1;
+        }
+
+        Ok(skipped)
+    }
+
+    /// Get an iterator over records
+    pub fn records(&mut self) -> CsvRecordIterator<'_> {
+        CsvRecordIterator { parser: self }
+    }
+
+    /// Get an iterator over records paired with their source line numbers
+    pub fn numbered_records(&mut self) -> NumberedRecordIterator<'_> {
+        NumberedRecordIterator { parser: self }
+    }
+
+    /// Turn the parser into an async stream of records
+    ///
+    /// Reading happens on a blocking task, a few records ahead of the
+    /// consumer, so file I/O never blocks the runtime. Dropping the stream
+    /// stops the reader. Must be called within a Tokio runtime.
+    pub fn into_stream(mut self) -> RecordStream {
+        let (sender, receiver) = mpsc::channel(RECORD_STREAM_BUFFER);
+        tokio::task::spawn_blocking(move || {
+            while let Some(row) = self.next_row() {
+                let failed = row.is_err();
+                if sender.blocking_send(row.map(|(_, row)| row)).is_err() || failed {
+                    break;
+                }
+            }
+        });
+        RecordStream { receiver }
+    }
+
+    /// Reset reader to beginning (requires re-opening file)
+    pub fn reset<P: AsRef<Path>>(&mut self, path: P, has_headers: bool) -> Result<()> {
+        self.path = path.as_ref().to_path_buf();
+        self.options.has_headers = has_headers;
+        self.ragged = RaggedRows::default();
+        self.replay.clear();
+        self.malformed = MalformedLines::default();
+        self.quoted_blanks = QuotedBlankLines::default();
+
+        // Rows inferred from a mapped file must be the rows loaded
+        if let Some(stamp) = self.mapped.take() {
+            if FileStamp::of(path.as_ref())? != stamp {
+                return Err(LoaderError::IoError(io::Error::other(format!(
+                    "{} changed while its schema was being inferred",
+                    path.as_ref().display()
+                ))));
+            }
+        }
+        self.options.mmap = false;
+
+        if self.options.fixed_width.is_some() {
+            self.source = Source::FixedWidth(open_fixed_width(path, &self.options)?);
+            return Ok(());
+        }
+
+        let mut reader = open_reader(path, &self.options, &self.malformed, &self.quoted_blanks)?;
+        // Skip headers if present
+        if has_headers {
+            reader.headers()?;
+        }
+        self.source = Source::Csv(reader);
+
+        Ok(())
+    }
+}
+
+/// Check header names are unique, or with `dedup` number the repeats
+fn dedup_headers(headers: &StringRecord, dedup: bool) -> Result<StringRecord> {
+    let mut duplicates: Vec<&str> = Vec::new();
+    for (i, name) in headers.iter().enumerate() {
+        if headers.iter().take(i).any(|earlier| earlier == name) && !duplicates.contains(&name) {
+            duplicates.push(name);
+        }
+    }
+    if duplicates.is_empty() {
+        return Ok(headers.clone());
+    }
+
+    if !dedup {
+        return Err(LoaderError::ConfigError(format!(
+            "Duplicate column names in header: {}; use --dedup-headers to number them",
+            duplicates.join(", ")
+        )));
+    }
+
+    let mut names: Vec<String> = Vec::with_capacity(headers.len());
+    for name in headers {
+        let mut candidate = name.to_string();
+        let mut suffix = 2;
+        while names.contains(&candidate) || (candidate != name && headers.iter().any(|h| h == candidate)) {
+            candidate = format!("{}_{}", name, suffix);
+            suffix += 1;
+        }
+        names.push(candidate);
+    }
+    Ok(StringRecord::from(names))
+}
+
+/// Open a file as decompressed UTF-8 text with the leading raw lines
+/// already skipped
+fn open_source<P: AsRef<Path>>(path: P, options: &CsvOptions) -> Result<Box<dyn BufRead + Send>> {
+    let file = match map_file(path.as_ref(), options)? {
+        Some(map) => Box::new(io::Cursor::new(map)),
+        None => compression::open(path, options.compression)?,
+    };
+
+    let mut source: Box<dyn BufRead + Send> = match options.encoding {
+        Some(encoding) => {
+            let decoder = DecodeReaderBytesBuilder::new()
+                .encoding(Some(encoding))
+                .build(file);
+            Box::new(BufReader::with_capacity(64 * 1024, decoder))
+        }
+        None => Box::new(BufReader::with_capacity(64 * 1024, file)),
+    };
+    strip_bom(&mut source)?;
+    skip_lines(&mut source, options.skip_rows)?;
+
+    if let Some(sequence) = &options.delimiter_sequence {
+        source = Box::new(DelimiterTranslator::new(source, sequence.clone(), options.delimiter, options.quote, options.escape));
+    }
+
+    Ok(source)
+}
+
+/// Memory-map the file with `mmap`, unless it is compressed or not a
+/// regular file
+fn map_file(path: &Path, options: &CsvOptions) -> Result<Option<Mmap>> {
+    if !options.mmap || compression::is_compressed(path, options.compression)? {
+        return Ok(None);
+    }
+    let file = File::open(path).map_err(|_| LoaderError::FileNotFound(path.display().to_string()))?;
+    if !file.metadata()?.is_file() {
+        return Ok(None);
+    }
+
+    // SAFETY: the map is only read. Another process truncating the file
+    // while it is mapped would fault on access, which no check here can
+    // rule out; other changes are caught by comparing the file's size and
+    // modification time on `reset`.
+    let map = unsafe { Mmap::map(&file)? };
+    Ok(Some(map))
+}
+
+/// Delimiters tried when sniffing
+const DELIMITER_CANDIDATES: [u8; 4] = [b',', b'\t', b';', b'|'];
 
-        let mut count = 0;
-        for result in self.reader.records() {
-            if count >= config.sample_size {
-                break;
+/// Records read from the start of a file to sniff its delimiter
+const SNIFF_RECORDS: usize = 20;
+
+/// Pick the candidate delimiter that splits the first records into the
+/// same number of fields each
+///
+/// If several are consistent, the one giving the most fields wins, and a
+/// tie is an error. A file none of them splits is a single column.
+fn detect_delimiter<P: AsRef<Path>>(path: P, options: &CsvOptions) -> Result<u8> {
+    let mut source = open_source(path, options)?;
+    let records = sniff_records(&mut source, options)?;
+    let counts = |delimiter: u8| -> Vec<usize> {
+        records.iter().map(|record| count_outside_quotes(record, delimiter, options.quote)).collect()
+    };
+
+    let mut consistent: Vec<(u8, usize)> = DELIMITER_CANDIDATES
+        .iter()
+        .filter_map(|&delimiter| {
+            let counts = counts(delimiter);
+            let first = *counts.first()?;
+            (first > 0 && counts.iter().all(|&n| n == first)).then_some((delimiter, first))
+        })
+        .collect();
+    consistent.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+
+    match consistent.as_slice() {
+        [] if DELIMITER_CANDIDATES.iter().all(|&d| counts(d).iter().all(|&n| n == 0)) => Ok(b','),
+        [] => Err(LoaderError::ConfigError(
+            "Could not detect the delimiter: no candidate gives a consistent column count; pass --delimiter".to_string()
+        )),
+        [(first, count), (second, other), ..] if count == other => Err(LoaderError::ConfigError(format!(
+            "Could not detect the delimiter: {:?} and {:?} both fit; pass --delimiter",
+            *first as char, *second as char
+        ))),
+        [(delimiter, _), ..] => Ok(*delimiter),
+    }
+}
+
+/// Read up to `SNIFF_RECORDS` non-blank, non-comment records as raw bytes,
+/// keeping quoted newlines inside their record
+fn sniff_records<R: BufRead>(source: &mut R, options: &CsvOptions) -> Result<Vec<Vec<u8>>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut line = Vec::new();
+
+    while records.len() < SNIFF_RECORDS {
+        line.clear();
+        if source.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        let is_start = record.is_empty();
+        if is_start && options.comment.is_some() && line.first() == options.comment.as_ref() {
+            continue;
+        }
+
+        record.extend_from_slice(&line);
+        let quotes = record.iter().filter(|&&b| b == options.quote).count();
+        if quotes % 2 == 0 {
+            if record.iter().any(|b| !b.is_ascii_whitespace()) {
+                records.push(std::mem::take(&mut record));
+            } else {
+                record.clear();
             }
+        }
+    }
+    if !record.is_empty() {
+        records.push(record);
+    }
 
-            let record = result?;
-            let row: Vec<String> = record.iter().map(String::from).collect();
+    Ok(records)
+}
 
-            schema.update_row(&row)?;
+/// Occurrences of `delimiter` in `record` outside quoted fields
+fn count_outside_quotes(record: &[u8], delimiter: u8, quote: u8) -> usize {
+    let mut in_quotes = false;
+    let mut count = 0;
+    for &byte in record {
+        if byte == quote {
+            in_quotes = !in_quotes;
+        } else if byte == delimiter && !in_quotes {
             count += 1;
         }
+    }
+    count
+}
 
-        if count == 0 {
-            return Err(LoaderError::EmptyFile);
+/// Open a fixed-width file, past its header line if it has one
+fn open_fixed_width<P: AsRef<Path>>(path: P, options: &CsvOptions) -> Result<FixedWidthReader> {
+    let columns = options.fixed_width.clone().unwrap_or_default();
+    let mut reader = FixedWidthReader::new(open_source(path, options)?, columns, options.comment);
+    if options.has_headers {
+        reader.skip_line()?;
+    }
+    Ok(reader)
+}
+
+/// Open a file and wrap it in a CSV reader
+fn open_reader<P: AsRef<Path>>(
+    path: P,
+    options: &CsvOptions,
+    malformed: &MalformedLines,
+    quoted_blanks: &QuotedBlankLines,
+) -> Result<Reader<Box<dyn BufRead + Send>>> {
+    let file = open_source(path, options)?;
+    Ok(csv_reader(file, options, malformed, quoted_blanks, options.skip_rows as u64 + 1))
+}
+
+/// Wrap `file` in a CSV reader; with `recover_quotes`, its quoting is
+/// checked from source line `first_line` on, and with `skip_blank_rows`
+/// lines of quoted empty fields are noted in `quoted_blanks`
+fn csv_reader(
+    mut file: Box<dyn BufRead + Send>,
+    options: &CsvOptions,
+    malformed: &MalformedLines,
+    quoted_blanks: &QuotedBlankLines,
+    first_line: u64,
+) -> Reader<Box<dyn BufRead + Send>> {
+    if options.recover_quotes {
+        let dialect = Dialect {
+            delimiter: options.delimiter,
+            quote: options.quote,
+            escape: options.escape,
+            comment: options.comment,
+        };
+        file = Box::new(QuoteGuard::new(file, dialect, first_line, malformed.clone()));
+    }
+    if options.skip_blank_rows {
+        let scan = BlankLineScan::new(options.delimiter, options.quote, first_line, quoted_blanks.clone());
+        file = Box::new(QuotedBlankTap { inner: file, scan });
+    }
+
+    ReaderBuilder::new()
+        .delimiter(options.delimiter)
+        .has_headers(options.has_headers)
+        .comment(options.comment)
+        .quote(options.quote)
+        .escape(options.escape)
+        .trim(if options.trim { Trim::All } else { Trim::None })
+        // Row widths are checked by `next_row`, after blank rows are dropped
+        .flexible(true)
+        .from_reader(file)
+}
+
+/// Drop a leading UTF-8 byte order mark so it can't end up in the first
+/// header, or hide a comment marker on the first line
+///
+/// Returns the number of bytes dropped.
+fn strip_bom<R: BufRead>(reader: &mut R) -> Result<u64> {
+    const BOM: &[u8] = b"\xef\xbb\xbf";
+
+    if reader.fill_buf()?.starts_with(BOM) {
+        reader.consume(BOM.len());
+        return Ok(BOM.len() as u64);
+    }
+    Ok(0)
+}
+
+/// Discard `count` newline-terminated lines, ignoring CSV quoting, and
+/// return the number of bytes discarded
+fn skip_lines<R: BufRead>(reader: &mut R, count: usize) -> Result<u64> {
+    let mut line = Vec::new();
+    let mut skipped = 0;
+    for _ in 0..count {
+        line.clear();
+        match reader.read_until(b'\n', &mut line)? {
+            0 => break,
+            n => skipped += n as u64,
         }
+    }
+    Ok(skipped)
+}
+
+/// Byte offset in an uncompressed file where the CSV text starts, after
+/// any byte order mark and skipped lines
+fn data_start(path: &Path, options: &CsvOptions) -> Result<u64> {
+    let mut file = BufReader::new(File::open(path)?);
+    Ok(strip_bom(&mut file)? + skip_lines(&mut file, options.skip_rows)?)
+}
+
+/// Rows read from partway through a file by [`CsvParser::segment_at`]
+struct Segment {
+    /// Byte offset of the segment's first line
+    start: u64,
+    /// Source line number of the segment's first line
+    first_line: u64,
+    parser: CsvParser,
+}
+
+impl Segment {
+    /// The segment's rows with their source lines; rows of the wrong
+    /// width, from a seek into a multi-line field, are left out
+    fn rows(&mut self) -> impl Iterator<Item = Result<(u64, Vec<String>)>> + '_ {
+        // The segment's reader counts lines from 1
+        let offset = self.first_line - 1;
+        std::iter::from_fn(|| self.parser.next_row()).filter_map(move |row| match row {
+            Ok((line, row)) => Some(Ok((line + offset, row))),
+            Err(LoaderError::AtLine { source, .. }) if matches!(*source, LoaderError::TypeConversionError(_)) => None,
+            Err(e) => Some(Err(e)),
+        })
+    }
+
+    /// Byte offset and line number of the segment's first line, for
+    /// numbering a later segment
+    fn anchor(&self) -> (u64, u64) {
+        (self.start, self.first_line)
+    }
+
+    /// Byte offset just past the last row read
+    fn end(&self) -> u64 {
+        self.start + self.parser.bytes_read()
+    }
+}
+
+/// Count the newlines between byte offsets `from` and `to` of a file
+fn count_newlines(path: &Path, from: u64, to: u64) -> Result<u64> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(from))?;
+    let mut reader = file.take(to.saturating_sub(from));
+    let mut buf = vec![0; 64 * 1024];
+    let mut count = 0;
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            return Ok(count);
+        }
+        count += buf[..n].iter().filter(|&&b| b == b'\n').count() as u64;
+    }
+}
+
+/// Rewrites a multi-character delimiter to the single byte the CSV reader
+/// splits on
+///
+/// Occurrences inside quoted fields are left alone, including after an
+/// escaped quote. A match split across two reads is held back until the
+/// next read completes it. Input that already contains the replacement
+/// byte is an error, since it would split a field.
+struct DelimiterTranslator<R> {
+    inner: R,
+    sequence: Vec<u8>,
+    replacement: u8,
+    quote: u8,
+    escape: Option<u8>,
+    in_quotes: bool,
+    /// The previous byte was the escape character inside quotes
+    escaped: bool,
+    /// Bytes read but not yet translated
+    pending: Vec<u8>,
+    out: Vec<u8>,
+    pos: usize,
+}
+
+impl<R: BufRead> DelimiterTranslator<R> {
+    fn new(inner: R, sequence: Vec<u8>, replacement: u8, quote: u8, escape: Option<u8>) -> Self {
+        Self {
+            inner,
+            sequence,
+            replacement,
+            quote,
+            // A doubled quote already toggles twice
+            escape: escape.filter(|&escape| escape != quote),
+            in_quotes: false,
+            escaped: false,
+            pending: Vec::new(),
+            out: Vec::new(),
+            pos: 0,
+        }
+    }
+
+    fn refill(&mut self) -> io::Result<()> {
+        self.out.clear();
+        self.pos = 0;
+
+        loop {
+            let chunk = self.inner.fill_buf()?;
+            let eof = chunk.is_empty();
+            self.pending.extend_from_slice(chunk);
+            let len = chunk.len();
+            self.inner.consume(len);
+
+            // Keep back a tail that could be the start of a split match
+            let limit = if eof {
+                self.pending.len()
+            } else {
+                self.pending.len().saturating_sub(self.sequence.len() - 1)
+            };
+
+            let mut i = 0;
+            while i < limit {
+                let byte = self.pending[i];
+                if byte == self.replacement {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "input contains byte 0x{:02x}, which stands in for the multi-character delimiter",
+                            self.replacement
+                        ),
+                    ));
+                }
+                if !self.in_quotes && self.pending[i..].starts_with(&self.sequence) {
+                    self.out.push(self.replacement);
+                    i += self.sequence.len();
+                } else {
+                    if self.escaped {
+                        self.escaped = false;
+                    } else if self.in_quotes && Some(byte) == self.escape {
+                        self.escaped = true;
+                    } else if byte == self.quote {
+                        self.in_quotes = !self.in_quotes;
+                    }
+                    self.out.push(byte);
+                    i += 1;
+                }
+            }
+            self.pending.drain(..i);
+
+            if !self.out.is_empty() || eof {
+                return Ok(());
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Read for DelimiterTranslator<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for DelimiterTranslator<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.pos >= self.out.len() {
+            self.refill()?;
+        }
+        Ok(&self.out[self.pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.out.len());
+    }
+}
+
+/// Lines that look blank but hold a quote, like `"",,`, shared between a
+/// [`QuotedBlankTap`] and the parser
+///
+/// A record starting on such a line has a quoted empty or whitespace
+/// field, so it is a row of values rather than a blank row.
+#[derive(Debug, Clone, Default)]
+struct QuotedBlankLines(Arc<Mutex<HashSet<u64>>>);
+
+impl QuotedBlankLines {
+    fn contains(&self, line: u64) -> bool {
+        self.0.lock().unwrap().contains(&line)
+    }
+}
+
+/// Line-by-line state of a [`QuotedBlankTap`]
+struct BlankLineScan {
+    delimiter: u8,
+    quote: u8,
+    /// Source line of the next byte
+    line: u64,
+    /// The line so far holds only delimiters, whitespace and quotes
+    blank: bool,
+    quoted: bool,
+    lines: QuotedBlankLines,
+}
+
+impl BlankLineScan {
+    fn new(delimiter: u8, quote: u8, first_line: u64, lines: QuotedBlankLines) -> Self {
+        Self { delimiter, quote, line: first_line, blank: true, quoted: false, lines }
+    }
+
+    fn scan(&mut self, mut bytes: &[u8]) {
+        while let Some((&byte, rest)) = bytes.split_first() {
+            if !self.blank && byte != b'\n' {
+                // Nothing else on this line matters
+                match bytes.iter().position(|&b| b == b'\n') {
+                    Some(i) => bytes = &bytes[i..],
+                    None => return,
+                }
+                continue;
+            }
+            bytes = rest;
+
+            if byte == b'\n' {
+                self.end_line();
+                self.line += 1;
+                self.blank = true;
+            } else if byte == self.quote {
+                self.quoted = true;
+            } else if byte != self.delimiter && !byte.is_ascii_whitespace() {
+                self.blank = false;
+            }
+        }
+    }
+
+    fn end_line(&mut self) {
+        if self.blank && self.quoted {
+            self.lines.0.lock().unwrap().insert(self.line);
+        }
+        self.blank = false;
+        self.quoted = false;
+    }
+}
+
+/// Passes input through unchanged, noting the lines of quoted empty
+/// fields, which the CSV reader returns just like blank ones
+///
+/// Only lines made of delimiters, whitespace and quotes are noted, so the
+/// set stays small.
+struct QuotedBlankTap<R> {
+    inner: R,
+    scan: BlankLineScan,
+}
+
+impl<R: BufRead> Read for QuotedBlankTap<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if n == 0 && !buf.is_empty() {
+            // The last line may have no newline
+            self.scan.end_line();
+        }
+        self.scan.scan(&buf[..n]);
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for QuotedBlankTap<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        let buf = self.inner.fill_buf()?;
+        if buf.is_empty() {
+            self.scan.end_line();
+        }
+        Ok(buf)
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if let Ok(buf) = self.inner.fill_buf() {
+            self.scan.scan(&buf[..amt.min(buf.len())]);
+        }
+        self.inner.consume(amt);
+    }
+}
+
+/// Iterator over CSV records
+pub struct CsvRecordIterator<'a> {
+    parser: &'a mut CsvParser,
+}
+
+impl<'a> Iterator for CsvRecordIterator<'a> {
+    type Item = Result<Vec<String>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.parser.next_row().map(|row| row.map(|(_, row)| row))
+    }
+}
+
+/// Iterator over CSV records and the line each one starts on
+pub struct NumberedRecordIterator<'a> {
+    parser: &'a mut CsvParser,
+}
+
+impl<'a> Iterator for NumberedRecordIterator<'a> {
+    type Item = Result<(u64, Vec<String>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let row = self.parser.next_row();
+        self.parser.byte_counter.0.store(self.parser.bytes_read(), Ordering::Relaxed);
+        row
+    }
+}
+
+/// Records read before inferring them together, spread across threads
+const INFERENCE_CHUNK_ROWS: usize = 65_536;
+
+/// Records read ahead of a [`RecordStream`]'s consumer
+const RECORD_STREAM_BUFFER: usize = 1024;
+
+/// Async stream of CSV records, from [`CsvParser::into_stream`]
+///
+/// Ends after the first error.
+pub struct RecordStream {
+    receiver: mpsc::Receiver<Result<Vec<String>>>,
+}
+
+impl Stream for RecordStream {
+    type Item = Result<Vec<String>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+/// Whether a CSV row is blank: every field is empty or whitespace, as in
+/// `,,,`, `  ,  ` or a line of spaces
+///
+/// A lone empty field is not blank. The reader already drops empty lines,
+/// so it can only come from a quoted `""`, which is an empty value in a
+/// one-column file. With `trim` a line of spaces is trimmed to a lone empty
+/// field first and is kept the same way. Wider rows with a quoted field,
+/// like `"",,`, look blank here; the parser keeps them by their line.
+pub fn is_blank(record: &StringRecord) -> bool {
+    record.iter().all(|field| field.trim().is_empty()) && !(record.len() == 1 && record[0].is_empty())
+}
 
-        schema.finalize();
-        Ok(schema)
+/// Copy a record's fields, keeping only the selected columns if any
+///
+/// Without a selection the row is cut or padded with empty fields to `width`.
+fn project(record: &StringRecord, width: usize, selection: Option<&[usize]>) -> Vec<String> {
+    let field = |i: usize| record.get(i).unwrap_or_default().to_string();
+
+    match selection {
+        Some(indices) => indices.iter().map(|&i| field(i)).collect(),
+        None => (0..width).map(field).collect(),
     }
+}
 
-    /// Get an iterator over records
-    pub fn records(&mut self) -> CsvRecordIterator<'_> {
-        CsvRecordIterator {
-            reader: &mut self.reader,
+/// Count data records with a fast byte scan instead of a full CSV parse
+///
+/// Newlines inside double-quoted fields are not counted, so the result is
+/// exact for standard CSV quoting. Blank lines are skipped, matching the
+/// CSV reader, but rows of empty fields such as `,,,` are counted even when
+/// loading skips them. Skipped leading lines and comment lines are not
+/// counted.
+pub fn count_records<P: AsRef<Path>>(path: P, options: &CsvOptions) -> Result<u64> {
+    if options.fixed_width.is_some() {
+        let mut reader = open_fixed_width(path, options)?;
+        let mut records = 0;
+        while reader.read_record()?.is_some() {
+            records += 1;
         }
+        return Ok(records);
     }
 
-    /// Reset reader to beginning (requires re-opening file)
-    pub fn reset<P: AsRef<Path>>(&mut self, path: P, has_headers: bool) -> Result<()> {
-        let file = File::open(&path).map_err(|_| {
-            LoaderError::FileNotFound(path.as_ref().display().to_string())
-        })?;
+    let mut reader = open_source(path, options)?;
 
-        self.reader = ReaderBuilder::new()
-            .delimiter(self.delimiter)
-            .has_headers(has_headers)
-            .flexible(false)
-            .from_reader(file);
+    let mut records = 0u64;
+    let mut in_quotes = false;
+    let mut line_has_data = false;
+    let mut in_comment = false;
+    let mut escaped = false;
 
-        // Skip headers if present
-        if has_headers {
-            self.reader.headers()?;
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            break;
         }
 
-        Ok(())
-    }
-}
-
-/// Iterator over CSV records
-pub struct CsvRecordIterator<'a> {
-    reader: &'a mut Reader<File>,
-}
+        for &byte in buf {
+            if in_comment {
+                in_comment = byte != b'\n';
+                continue;
+            }
 
-impl<'a> Iterator for CsvRecordIterator<'a> {
-    type Item = Result<Vec<String>>;
+            if escaped {
+                escaped = false;
+                continue;
+            }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        match self.reader.records().next() {
-            Some(Ok(record)) => {
-                let row: Vec<String> = record.iter().map(String::from).collect();
-                Some(Ok(row))
+            match byte {
+                b if !line_has_data && Some(b) == options.comment => in_comment = true,
+                b if in_quotes && Some(b) == options.escape => escaped = true,
+                b if b == options.quote => {
+                    in_quotes = !in_quotes;
+                    line_has_data = true;
+                }
+                b'\n' if !in_quotes => {
+                    if line_has_data {
+                        records += 1;
+                    }
+                    line_has_data = false;
+                }
+                b'\r' if !in_quotes => {}
+                _ => line_has_data = true,
             }
-            Some(Err(e)) => Some(Err(e.into())),
-            None => None,
         }
+
+        let len = buf.len();
+        reader.consume(len);
+    }
+
+    // Final record without a trailing newline
+    if line_has_data {
+        records += 1;
     }
+
+    if options.has_headers {
+        records = records.saturating_sub(1);
+    }
+
+    Ok(records)
 }
 
 /// Parse delimiter from string
@@ -141,9 +1445,40 @@ pub fn parse_delimiter(s: &str) -> Result<u8> {
     }
 }
 
+/// Parse a delimiter that may be several characters long, e.g. `||`
+///
+/// Returns the byte the CSV reader splits on and, for a multi-character
+/// delimiter, the sequence that is translated to it. The stand-in byte is
+/// the ASCII unit separator; data containing it is rejected.
+pub fn parse_delimiter_sequence(s: &str) -> Result<(u8, Option<Vec<u8>>)> {
+    if let Ok(delimiter) = parse_delimiter(s) {
+        return Ok((delimiter, None));
+    }
+
+    if s.len() < 2 || s.contains(['\n', '\r', '"']) {
+        return Err(LoaderError::ConfigError(format!("Invalid delimiter: {}", s)));
+    }
+    Ok((MULTI_DELIMITER_BYTE, Some(s.as_bytes().to_vec())))
+}
+
+/// Byte a multi-character delimiter is translated to (ASCII unit separator)
+const MULTI_DELIMITER_BYTE: u8 = 0x1f;
+
+/// Look up an input encoding by its WHATWG label, e.g. "latin1"
+///
+/// UTF-8 maps to `None` so the input is read without transcoding.
+pub fn parse_encoding(label: &str) -> Result<Option<&'static Encoding>> {
+    match Encoding::for_label(label.trim().as_bytes()) {
+        Some(encoding) if encoding == encoding_rs::UTF_8 => Ok(None),
+        Some(encoding) => Ok(Some(encoding)),
+        None => Err(LoaderError::ConfigError(format!("Unknown encoding: {}", label))),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::SqlType;
     use std::io::Write;
     use tempfile::NamedTempFile;
 
@@ -188,6 +1523,278 @@ mod tests {
         assert_eq!(schema.columns[2].name, "salary");
     }
 
+    #[test]
+    fn test_infer_schema_detailed() {
+        let file = create_test_csv("id,note,unused\n1,x,\n2,,\n");
+
+        let mut parser = CsvParser::from_path(file.path(), b',', true).unwrap();
+        let config = InferenceConfig::new(100, true);
+        let result = parser.infer_schema_detailed("t".to_string(), &config, 0.5).unwrap();
+
+        let columns: Vec<&str> = result.warnings.iter().map(|w| w.column()).collect();
+        assert_eq!(columns, vec!["note", "unused"]);
+        assert_eq!(result.schema.columns.len(), 3);
+    }
+
+    #[test]
+    fn test_select_columns() {
+        let file = create_test_csv("name,age,city\nAlice,25,NYC\nBob,30,LA\n");
+
+        let mut parser = CsvParser::from_path(file.path(), b',', true).unwrap();
+        parser.select_columns(&ColumnSelection::Exclude(vec!["age".to_string()])).unwrap();
+        assert_eq!(parser.headers(), vec!["name", "city"]);
+
+        let schema = parser.infer_schema("users".to_string(), &InferenceConfig::new(100, true)).unwrap();
+        assert_eq!(schema.columns.len(), 2);
+
+        parser.reset(file.path(), true).unwrap();
+        let rows: Vec<Vec<String>> = parser.records().map(|r| r.unwrap()).collect();
+        assert_eq!(rows[1], vec!["Bob", "LA"]);
+
+        parser.select_columns(&ColumnSelection::Include(vec!["city".to_string(), "age".to_string()])).unwrap();
+        assert_eq!(parser.headers(), vec!["age", "city"]);
+
+        let err = parser.select_columns(&ColumnSelection::Exclude(vec!["zip".to_string()])).unwrap_err();
+        assert!(err.to_string().contains("available: name, age, city"));
+    }
+
+    #[test]
+    fn test_duplicate_headers() {
+        let file = create_test_csv("id,name,id,id_2,name\n1,a,2,3,b\n");
+
+        let err = CsvParser::from_path(file.path(), b',', true).err().unwrap();
+        assert!(err.to_string().contains("Duplicate column names in header: id, name"), "{}", err);
+
+        let options = CsvOptions { dedup_headers: true, ..Default::default() };
+        let parser = CsvParser::from_path_with(file.path(), options).unwrap();
+        assert_eq!(parser.headers(), vec!["id", "name", "id_3", "id_2", "name_2"]);
+    }
+
+    #[test]
+    fn test_rename_columns() {
+        let file = create_test_csv("Customer ID,name,notes\n1,Alice,x\n");
+        let rename = |pairs: &[(&str, &str)]| {
+            let mut parser = CsvParser::from_path(file.path(), b',', true).unwrap();
+            let pairs: Vec<(String, String)> = pairs.iter().map(|(a, b)| (a.to_string(), b.to_string())).collect();
+            parser.rename_columns(&pairs).map(|_| parser.headers())
+        };
+
+        assert_eq!(
+            rename(&[("Customer ID", "customer_id"), ("name", "full_name")]).unwrap(),
+            vec!["customer_id", "full_name", "notes"]
+        );
+        assert!(rename(&[("missing", "x")]).unwrap_err().to_string().contains("available"));
+        assert!(rename(&[("name", "full name")]).is_err());
+        assert!(rename(&[("name", "notes")]).unwrap_err().to_string().contains("twice"));
+
+        // A dropped column's name is free to reuse
+        let mut parser = CsvParser::from_path(file.path(), b',', true).unwrap();
+        parser.select_columns(&ColumnSelection::Exclude(vec!["notes".to_string()])).unwrap();
+        parser.rename_columns(&[("name".to_string(), "notes".to_string())]).unwrap();
+        assert_eq!(parser.headers(), vec!["Customer ID", "notes"]);
+    }
+
+    #[test]
+    fn test_numbered_records() {
+        let file = create_test_csv("banner\nname,note\nAlice,\"two\nlines\"\nBob,x\n");
+        let options = CsvOptions { skip_rows: 1, ..Default::default() };
+
+        let mut parser = CsvParser::from_path_with(file.path(), options).unwrap();
+        let lines: Vec<u64> = parser.numbered_records().map(|r| r.unwrap().0).collect();
+
+        assert_eq!(lines, vec![3, 5]);
+    }
+
+    #[test]
+    fn test_skip_records_multiline() {
+        let file = create_test_csv("name,note\nAlice,\"two\nlines\"\nBob,\"a\n\nb\"\nCarol,x\n");
+
+        let mut parser = CsvParser::from_path(file.path(), b',', true).unwrap();
+        assert_eq!(parser.skip_records(2).unwrap(), 2);
+
+        let rows: Vec<(u64, Vec<String>)> = parser.numbered_records().map(|r| r.unwrap()).collect();
+        assert_eq!(rows, vec![(7, vec!["Carol".to_string(), "x".to_string()])]);
+
+        // Skipping past the end stops at the last record
+        parser.reset(file.path(), true).unwrap();
+        assert_eq!(parser.skip_records(10).unwrap(), 3);
+    }
+
+    #[test]
+    fn test_buffer_sample_replays_rows() {
+        let file = create_test_csv("id,name\n1,a\n2,b\n3,c\n");
+        let config = InferenceConfig {
+            buffer_sample: true,
+            ..InferenceConfig::new(2, true)
+        };
+
+        let mut parser = CsvParser::from_path(file.path(), b',', true).unwrap();
+        parser.infer_schema("t".to_string(), &config).unwrap();
+
+        let rows: Vec<(u64, Vec<String>)> = parser.numbered_records().map(|r| r.unwrap()).collect();
+        let ids: Vec<&str> = rows.iter().map(|(_, row)| row[0].as_str()).collect();
+        assert_eq!(ids, vec!["1", "2", "3"]);
+        assert_eq!(rows[2].0, 4);
+    }
+
+    #[test]
+    fn test_mmap_inference() {
+        let mut file = create_test_csv("id,name\n1,a\n2,b\n");
+        let options = CsvOptions { mmap: true, ..Default::default() };
+
+        let mut parser = CsvParser::from_path_with(file.path(), options.clone()).unwrap();
+        let schema = parser.infer_schema("t".to_string(), &InferenceConfig::default()).unwrap();
+        assert_eq!(schema.columns[0].sql_type, SqlType::SmallInt);
+        assert_eq!(schema.columns[0].sample_count, 2);
+
+        parser.reset(file.path(), true).unwrap();
+        assert_eq!(parser.records().count(), 2);
+
+        // A file that changes between inference and loading is refused
+        let mut parser = CsvParser::from_path_with(file.path(), options).unwrap();
+        parser.infer_schema("t".to_string(), &InferenceConfig::default()).unwrap();
+        file.write_all(b"3,c\n").unwrap();
+        file.flush().unwrap();
+        let err = parser.reset(file.path(), true).unwrap_err();
+        assert!(err.to_string().contains("changed while its schema was being inferred"), "{}", err);
+    }
+
+    #[test]
+    fn test_infer_schema_no_headers_uses_first_row() {
+        let file = create_test_csv("1,a\n");
+
+        let mut parser = CsvParser::from_path(file.path(), b',', false).unwrap();
+        let schema = parser.infer_schema("t".to_string(), &InferenceConfig::new(10, false)).unwrap();
+        assert_eq!(schema.columns[0].sample_count, 1);
+    }
+
+    #[test]
+    fn test_reservoir_sampling_sorted_file() {
+        let mut content = String::from("id,value\n");
+        for i in 0..500 {
+            content.push_str(&format!("{},{}\n", i, i));
+        }
+        for i in 500..1000 {
+            content.push_str(&format!("{},note {}\n", i, i));
+        }
+        let file = create_test_csv(&content);
+
+        let mut parser = CsvParser::from_path(file.path(), b',', true).unwrap();
+        let schema = parser.infer_schema("t".to_string(), &InferenceConfig::new(100, true)).unwrap();
+        assert_eq!(schema.columns[1].sql_type, SqlType::SmallInt);
+
+        let config = InferenceConfig {
+            sample_strategy: SampleStrategy::Reservoir,
+            ..InferenceConfig::new(100, true)
+        };
+        let mut parser = CsvParser::from_path(file.path(), b',', true).unwrap();
+        let schema = parser.infer_schema("t".to_string(), &config).unwrap();
+        assert_eq!(schema.columns[1].sql_type, SqlType::Text);
+        assert_eq!(schema.columns[1].sample_count, 100);
+    }
+
+    #[test]
+    fn test_spread_sampling_sees_the_tail() {
+        let mut content = String::from("id,value\n");
+        for i in 0..2000 {
+            content.push_str(&format!("{},{}\n", i, i % 100));
+        }
+        for i in 2000..3000 {
+            content.push_str(&format!("{},note {}\n", i, i));
+        }
+        let file = create_test_csv(&content);
+
+        let mut parser = CsvParser::from_path(file.path(), b',', true).unwrap();
+        let schema = parser.infer_schema("t".to_string(), &InferenceConfig::new(300, true)).unwrap();
+        assert_eq!(schema.columns[1].sql_type, SqlType::SmallInt);
+
+        let config = InferenceConfig {
+            sample_strategy: SampleStrategy::Spread,
+            ..InferenceConfig::new(300, true)
+        };
+        let mut parser = CsvParser::from_path(file.path(), b',', true).unwrap();
+        let sample = parser.spread_sample(300).unwrap();
+        assert_eq!(sample.len(), 300);
+        // Head, then middle, then the last rows of the file
+        assert_eq!(sample[0].1[0], "0");
+        assert!((1000..2900).contains(&sample[150].1[0].parse::<u32>().unwrap()));
+        assert_eq!(sample[299].1[0], "2999");
+        // Every row keeps its source line, after the header
+        for (line, row) in &sample {
+            assert_eq!(*line, row[0].parse::<u64>().unwrap() + 2, "{:?}", row);
+        }
+        let banner = create_test_csv(&format!("exported today\n{}", content));
+        let options = CsvOptions { skip_rows: 1, ..Default::default() };
+        let mut parser = CsvParser::from_path_with(banner.path(), options).unwrap();
+        for (line, row) in parser.spread_sample(300).unwrap() {
+            assert_eq!(line, row[0].parse::<u64>().unwrap() + 3, "{:?}", row);
+        }
+
+        let mut parser = CsvParser::from_path(file.path(), b',', true).unwrap();
+        let schema = parser.infer_schema("t".to_string(), &config).unwrap();
+        assert_eq!(schema.columns[1].sql_type, SqlType::Text);
+        assert_eq!(schema.columns[1].sample_count, 300);
+    }
+
+    #[test]
+    fn test_spread_sampling_small_and_compressed_files() {
+        let mut content = String::from("id\n");
+        for i in 0..50 {
+            content.push_str(&format!("{:03}\n", i));
+        }
+        let file = create_test_csv(&content);
+        let mut parser = CsvParser::from_path(file.path(), b',', true).unwrap();
+        assert_eq!(parser.spread_sample(30).unwrap().len(), 50);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.csv.gz");
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(content.as_bytes()).unwrap();
+        std::fs::write(&path, encoder.finish().unwrap()).unwrap();
+        let mut parser = CsvParser::from_path(&path, b',', true).unwrap();
+        let sample = parser.spread_sample(30).unwrap();
+        assert_eq!(sample.len(), 30);
+        assert_eq!(sample[29].1[0], "029");
+    }
+
+    #[test]
+    fn test_byte_counter_counts_decompressed_bytes() {
+        let content = "id,name\n1,alice\n2,bob\n";
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.csv.gz");
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(content.as_bytes()).unwrap();
+        std::fs::write(&path, encoder.finish().unwrap()).unwrap();
+
+        let mut parser = CsvParser::from_path(&path, b',', true).unwrap();
+        let counter = parser.byte_counter();
+        let mut records = parser.numbered_records();
+        records.next().unwrap().unwrap();
+        assert_eq!(counter.get(), "id,name\n1,alice\n".len() as u64);
+        assert!(records.next().is_some() && records.next().is_none());
+        assert_eq!(counter.get(), content.len() as u64);
+    }
+
+    #[test]
+    fn test_full_scan_nullability() {
+        let mut content = String::from("id,note\n");
+        for i in 0..50 {
+            content.push_str(&format!("{},x\n", i));
+        }
+        content.push_str("50,\n");
+        let file = create_test_csv(&content);
+
+        let mut parser = CsvParser::from_path(file.path(), b',', true).unwrap();
+        let schema = parser.infer_schema("t".to_string(), &InferenceConfig::new(10, true)).unwrap();
+        assert!(!schema.columns[1].nullable);
+
+        let mut parser = CsvParser::from_path(file.path(), b',', true).unwrap();
+        let schema = parser.infer_schema("t".to_string(), &InferenceConfig::new(0, true)).unwrap();
+        assert!(schema.columns[1].nullable);
+        assert_eq!(schema.columns[1].sample_count, 51);
+        assert_eq!(schema.columns[1].null_count, 1);
+    }
+
     #[test]
     fn test_parse_delimiter() {
         assert_eq!(parse_delimiter(",").unwrap(), b',');
@@ -197,6 +1804,360 @@ mod tests {
         assert_eq!(parse_delimiter(";").unwrap(), b';');
     }
 
+    #[tokio::test]
+    async fn test_record_stream_matches_iterator() {
+        use futures_util::StreamExt;
+
+        let rows: String = (0..3000).map(|i| format!("{},\"row {}\"\n", i, i)).collect();
+        let file = create_test_csv(&format!("id,name\n{}", rows));
+
+        let mut parser = CsvParser::from_path(file.path(), b',', true).unwrap();
+        let expected: Vec<Vec<String>> = parser.records().map(|r| r.unwrap()).collect();
+
+        let parser = CsvParser::from_path(file.path(), b',', true).unwrap();
+        let streamed: Vec<Vec<String>> = parser
+            .into_stream()
+            .map(|r| r.unwrap())
+            .collect()
+            .await;
+        assert_eq!(streamed.len(), 3000);
+        assert_eq!(streamed, expected);
+
+        // The stream ends at the first error
+        let bad = create_test_csv("id\n1\n2,3\n4\n");
+        let parser = CsvParser::from_path_with(bad.path(), CsvOptions {
+            flexible: true,
+            extra_fields: ExtraFields::Error,
+            ..Default::default()
+        }).unwrap();
+        let results: Vec<Result<Vec<String>>> = parser.into_stream().collect().await;
+        assert_eq!(results.len(), 2);
+        assert!(results[0].is_ok() && results[1].is_err());
+    }
+
+    #[test]
+    fn test_detect_delimiter() {
+        let options = CsvOptions { detect_delimiter: true, ..Default::default() };
+        let detect = |content: &str| {
+            let file = create_test_csv(content);
+            detect_delimiter(file.path(), &options)
+        };
+
+        assert_eq!(detect("id,name,note\n1,a,\"x; y\"\n2,b,z\n").unwrap(), b',');
+        assert_eq!(detect("id\tname\n1\ta,b\n2\tc\n").unwrap(), b'\t');
+        assert_eq!(detect("id;amount\n1;3,5\n2;4,25\n").unwrap(), b';');
+        assert_eq!(detect("id\n1\n2\n").unwrap(), b',');
+
+        let err = detect("a,b;c\n1,2;3\n").unwrap_err();
+        assert!(err.to_string().contains("pass --delimiter"), "{}", err);
+
+        let file = create_test_csv("id|name\n1|\"multi\nline\"\n");
+        let mut parser = CsvParser::from_path_with(file.path(), options.clone()).unwrap();
+        assert_eq!(parser.headers(), vec!["id", "name"]);
+        assert_eq!(parser.records().next().unwrap().unwrap(), vec!["1", "multi\nline"]);
+    }
+
+    #[test]
+    fn test_multi_char_delimiter() {
+        assert_eq!(parse_delimiter_sequence("|").unwrap(), (b'|', None));
+        assert!(parse_delimiter_sequence("\"\"").is_err());
+
+        let (delimiter, delimiter_sequence) = parse_delimiter_sequence("||").unwrap();
+        let file = create_test_csv("id||name\n1||\"a||b\"\n2||c|d\n");
+        let options = CsvOptions { delimiter, delimiter_sequence, ..Default::default() };
+
+        let mut parser = CsvParser::from_path_with(file.path(), options.clone()).unwrap();
+        assert_eq!(parser.headers(), vec!["id", "name"]);
+        let rows: Vec<Vec<String>> = parser.records().map(|r| r.unwrap()).collect();
+        assert_eq!(rows, vec![vec!["1", "a||b"], vec!["2", "c|d"]]);
+        assert_eq!(count_records(file.path(), &options).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_delimiter_split_across_reads() {
+        // One-byte reads split every delimiter between two refills
+        struct Trickle<'a>(&'a [u8]);
+        impl Read for Trickle<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                let n = self.0.len().min(1).min(buf.len());
+                buf[..n].copy_from_slice(&self.0[..n]);
+                self.0 = &self.0[n..];
+                Ok(n)
+            }
+        }
+
+        let input = BufReader::with_capacity(1, Trickle(b"a<>b<<>>c<"));
+        let mut translated = String::new();
+        DelimiterTranslator::new(input, b"<>".to_vec(), b',', b'"', None)
+            .read_to_string(&mut translated)
+            .unwrap();
+        assert_eq!(translated, "a,b<,>c<");
+    }
+
+    #[test]
+    fn test_delimiter_translation_escapes_and_stand_in() {
+        let translate = |input: &[u8], escape: Option<u8>| {
+            let mut translated = String::new();
+            DelimiterTranslator::new(input, b"||".to_vec(), 0x1f, b'"', escape)
+                .read_to_string(&mut translated)
+                .map(|_| translated)
+        };
+
+        // An escaped quote doesn't end the quoted field
+        assert_eq!(
+            translate(b"1||\"a\\\"||b\"||c\n", Some(b'\\')).unwrap(),
+            "1\x1f\"a\\\"||b\"\x1fc\n"
+        );
+        assert_eq!(translate(b"1||\"a\"\"||b\"||c\n", None).unwrap(), "1\x1f\"a\"\"||b\"\x1fc\n");
+
+        let err = translate(b"1||a\x1fb\n", None).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_fixed_width() {
+        let file = create_test_csv("ID  NAME    \n1   alice   \n22  bob\n");
+        let options = CsvOptions {
+            fixed_width: Some(crate::fixed_width::parse_spec("id:0-4,name:4-12").unwrap()),
+            ..Default::default()
+        };
+
+        let mut parser = CsvParser::from_path_with(file.path(), options.clone()).unwrap();
+        assert_eq!(parser.headers(), vec!["id", "name"]);
+        let schema = parser.infer_schema("people".to_string(), &InferenceConfig::default()).unwrap();
+        assert_eq!(schema.columns[0].sql_type, SqlType::SmallInt);
+
+        parser.reset(file.path(), true).unwrap();
+        let rows: Vec<(u64, Vec<String>)> = parser.numbered_records().map(|r| r.unwrap()).collect();
+        assert_eq!(rows[1], (3, vec!["22".to_string(), "bob".to_string()]));
+        assert_eq!(count_records(file.path(), &options).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_count_records() {
+        let file = create_test_csv("name,note\nAlice,\"multi\nline\"\n\nBob,plain\r\nCarol,last");
+
+        let options = CsvOptions::default();
+        assert_eq!(count_records(file.path(), &options).unwrap(), 3);
+
+        let no_headers = CsvOptions { has_headers: false, ..Default::default() };
+        assert_eq!(count_records(file.path(), &no_headers).unwrap(), 4);
+
+        let skip = CsvOptions { skip_rows: 1, ..Default::default() };
+        assert_eq!(count_records(file.path(), &skip).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_quote_and_escape_chars() {
+        let file = create_test_csv("name,note\n'Smith, Jo','it\\'s\nfine'\nBob,plain\n");
+        let options = CsvOptions { quote: b'\'', escape: Some(b'\\'), ..Default::default() };
+
+        let mut parser = CsvParser::from_path_with(file.path(), options.clone()).unwrap();
+        let rows: Vec<Vec<String>> = parser.records().map(|r| r.unwrap()).collect();
+        assert_eq!(rows, vec![vec!["Smith, Jo", "it's\nfine"], vec!["Bob", "plain"]]);
+
+        assert_eq!(count_records(file.path(), &options).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_trim_fields() {
+        let file = create_test_csv("id , name\n 42 ,Alice\n 7,Bob \n");
+        let config = InferenceConfig::new(100, true);
+
+        let mut parser = CsvParser::from_path(file.path(), b',', true).unwrap();
+        let schema = parser.infer_schema("t".to_string(), &config).unwrap();
+        assert_eq!(schema.columns[0].sql_type, SqlType::Text);
+
+        let options = CsvOptions { trim: true, ..Default::default() };
+        let mut parser = CsvParser::from_path_with(file.path(), options).unwrap();
+        let schema = parser.infer_schema("t".to_string(), &config).unwrap();
+        assert_eq!(parser.headers(), vec!["id", "name"]);
+        assert_eq!(schema.columns[0].sql_type, SqlType::SmallInt);
+
+        parser.reset(file.path(), true).unwrap();
+        let rows: Vec<Vec<String>> = parser.records().map(|r| r.unwrap()).collect();
+        assert_eq!(rows[1], vec!["7", "Bob"]);
+    }
+
+    #[test]
+    fn test_flexible_rows() {
+        let file = create_test_csv("a,b,c\n1,2,3\n4,5\n6,7,8,9\n");
+
+        let mut parser = CsvParser::from_path(file.path(), b',', true).unwrap();
+        assert!(parser.records().any(|r| r.is_err()));
+
+        let options = CsvOptions { flexible: true, ..Default::default() };
+        let mut parser = CsvParser::from_path_with(file.path(), options.clone()).unwrap();
+        let rows: Vec<Vec<String>> = parser.records().map(|r| r.unwrap()).collect();
+        assert_eq!(rows[1], vec!["4", "5", ""]);
+        assert_eq!(rows[2], vec!["6", "7", "8"]);
+        assert_eq!(parser.ragged_rows(), RaggedRows { padded: 1, truncated: 1 });
+
+        let options = CsvOptions { extra_fields: ExtraFields::Error, ..options };
+        let mut parser = CsvParser::from_path_with(file.path(), options).unwrap();
+        let err = parser.records().find_map(|r| r.err()).unwrap();
+        assert!(err.to_string().starts_with("line 4: "));
+    }
+
+    #[test]
+    fn test_skip_blank_rows() {
+        // Blank: all-empty fields, whitespace-only fields, a line of spaces
+        let file = create_test_csv("id,name,note\n1,a,x\n,,\n , \t, \n   \n2,b,\n,,\n");
+        let config = InferenceConfig::new(0, true);
+
+        let mut parser = CsvParser::from_path(file.path(), b',', true).unwrap();
+        let schema = parser.infer_schema("t".to_string(), &config).unwrap();
+        // Blank rows don't make `id` or `name` nullable
+        assert!(!schema.columns[0].nullable);
+        assert!(!schema.columns[1].nullable);
+        assert_eq!(schema.columns[0].sample_count, 2);
+        parser.reset(file.path(), true).unwrap();
+        let rows: Vec<(u64, Vec<String>)> = parser.numbered_records().map(|r| r.unwrap()).collect();
+        assert_eq!(rows.iter().map(|(line, _)| *line).collect::<Vec<_>>(), vec![2, 6]);
+
+        parser.reset(file.path(), true).unwrap();
+        assert_eq!(parser.skip_records(1).unwrap(), 1);
+        assert_eq!(parser.numbered_records().next().unwrap().unwrap().0, 6);
+
+        // Kept as rows when turned off; the line of spaces has the wrong width
+        let options = CsvOptions { skip_blank_rows: false, ..Default::default() };
+        let mut parser = CsvParser::from_path_with(file.path(), options).unwrap();
+        let rows: Vec<Result<Vec<String>>> = parser.records().collect();
+        assert_eq!(rows[1].as_ref().unwrap(), &vec!["", "", ""]);
+        assert!(rows[3].as_ref().unwrap_err().to_string().starts_with("line 5: "));
+    }
+
+    #[test]
+    fn test_recover_quotes() {
+        let content = "skip me\nid,note\n1,\"ok\"\n2,\"unterminated\n3,\"multi\nline\"\n4,plain\n";
+        let file = create_test_csv(content);
+        let options = CsvOptions { recover_quotes: true, skip_rows: 1, ..Default::default() };
+
+        let mut parser = CsvParser::from_path_with(file.path(), options.clone()).unwrap();
+        let rows: Vec<(u64, Vec<String>)> = parser.numbered_records().map(|r| r.unwrap()).collect();
+        assert_eq!(
+            rows,
+            vec![
+                (3, vec!["1".to_string(), "ok".to_string()]),
+                (5, vec!["3".to_string(), "multi\nline".to_string()]),
+                (7, vec!["4".to_string(), "plain".to_string()]),
+            ]
+        );
+        let dropped = parser.take_malformed_lines();
+        assert_eq!(dropped.len(), 1);
+        assert_eq!((dropped[0].line, dropped[0].row[0].as_str()), (4, "2,\"unterminated"));
+        assert!(parser.take_malformed_lines().is_empty());
+
+        // Without recovery the stray quote swallows the lines up to the next quote
+        let mut parser = CsvParser::from_path_with(file.path(), CsvOptions { recover_quotes: false, ..options.clone() }).unwrap();
+        let rows: Vec<Result<Vec<String>>> = parser.records().collect();
+        assert_eq!(rows[1].as_ref().unwrap()[1], "unterminated\n3,multi");
+
+        let file = create_test_csv("banner\nid,\"note\nx\n1,a\n");
+        let error = CsvParser::from_path_with(file.path(), options).err().unwrap();
+        assert_eq!(error.to_string(), "line 2: Schema inference failed: header has malformed quoting");
+    }
+
+    #[test]
+    fn test_lone_quoted_empty_field_is_not_blank() {
+        let file = create_test_csv("name\nalice\n\"\"\n\nbob\n");
+
+        let mut parser = CsvParser::from_path(file.path(), b',', true).unwrap();
+        let rows: Vec<Vec<String>> = parser.records().map(|r| r.unwrap()).collect();
+        assert_eq!(rows, vec![vec!["alice"], vec![""], vec!["bob"]]);
+
+        assert!(is_blank(&StringRecord::from(vec!["", ""])));
+        assert!(is_blank(&StringRecord::from(vec!["  "])));
+        assert!(!is_blank(&StringRecord::from(vec![""])));
+        assert!(!is_blank(&StringRecord::from(vec!["", "x"])));
+    }
+
+    #[test]
+    fn test_quoted_empty_fields_are_not_blank() {
+        let file = create_test_csv("id,name,note\n1,a,x\n\"\",,\n,,\n,\" \",\n2,b,\"\"\n,,\"\"");
+
+        let mut parser = CsvParser::from_path(file.path(), b',', true).unwrap();
+        let rows: Vec<(u64, Vec<String>)> = parser.numbered_records().map(|r| r.unwrap()).collect();
+        let lines: Vec<u64> = rows.iter().map(|(line, _)| *line).collect();
+        assert_eq!(lines, vec![2, 3, 5, 6, 7]);
+        assert_eq!(rows[1].1, vec!["", "", ""]);
+
+        parser.reset(file.path(), true).unwrap();
+        assert_eq!(parser.skip_records(2).unwrap(), 2);
+        assert_eq!(parser.numbered_records().next().unwrap().unwrap().0, 5);
+    }
+
+    #[test]
+    fn test_utf8_bom_stripped() {
+        let file = create_test_csv("\u{feff}id,name\n1,Alice\n");
+        let parser = CsvParser::from_path(file.path(), b',', true).unwrap();
+        assert_eq!(parser.headers(), vec!["id", "name"]);
+
+        let file = create_test_csv("id,name\n1,Alice\n");
+        let parser = CsvParser::from_path(file.path(), b',', true).unwrap();
+        assert_eq!(parser.headers(), vec!["id", "name"]);
+
+        // A comment on the first line is still recognized behind a BOM
+        let file = create_test_csv("\u{feff}# export\nid,name\n1,Alice\n");
+        let options = CsvOptions { comment: Some(b'#'), ..Default::default() };
+        let parser = CsvParser::from_path_with(file.path(), options).unwrap();
+        assert_eq!(parser.headers(), vec!["id", "name"]);
+    }
+
+    #[test]
+    fn test_latin1_encoding() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(b"name,city\nRen\xe9,Paris\n").unwrap();
+        file.flush().unwrap();
+
+        let mut parser = CsvParser::from_path(file.path(), b',', true).unwrap();
+        assert!(parser.records().next().unwrap().is_err());
+
+        let options = CsvOptions { encoding: parse_encoding("latin1").unwrap(), ..Default::default() };
+        let mut parser = CsvParser::from_path_with(file.path(), options).unwrap();
+        assert_eq!(parser.records().next().unwrap().unwrap(), vec!["René", "Paris"]);
+    }
+
+    #[test]
+    fn test_parse_encoding() {
+        assert!(parse_encoding("utf-8").unwrap().is_none());
+        assert_eq!(parse_encoding("Windows-1252").unwrap(), Some(encoding_rs::WINDOWS_1252));
+        assert!(parse_encoding("klingon").is_err());
+    }
+
+    #[test]
+    fn test_comment_lines() {
+        let file = create_test_csv("# exported nightly\nname,note\nAlice,\"#1 fan\"\n# midway comment\nBob,plain\n");
+        let options = CsvOptions { comment: Some(b'#'), ..Default::default() };
+
+        let mut parser = CsvParser::from_path_with(file.path(), options.clone()).unwrap();
+        assert_eq!(parser.headers(), vec!["name", "note"]);
+
+        let rows: Vec<Vec<String>> = parser.records().map(|r| r.unwrap()).collect();
+        assert_eq!(rows, vec![vec!["Alice", "#1 fan"], vec!["Bob", "plain"]]);
+
+        assert_eq!(count_records(file.path(), &options).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_skip_rows() {
+        let file = create_test_csv("Quarterly Export\n\nname,age\nAlice,25\nBob,30\n");
+
+        let options = CsvOptions { skip_rows: 2, ..Default::default() };
+        let mut parser = CsvParser::from_path_with(file.path(), options.clone()).unwrap();
+        assert_eq!(parser.headers(), vec!["name", "age"]);
+
+        parser.reset(file.path(), true).unwrap();
+        let rows: Vec<Vec<String>> = parser.records().map(|r| r.unwrap()).collect();
+        assert_eq!(rows, vec![vec!["Alice", "25"], vec!["Bob", "30"]]);
+
+        // Without a header, the line after the skipped ones is data
+        let mut parser = CsvParser::from_path_with(file.path(), CsvOptions { has_headers: false, ..options }).unwrap();
+        assert_eq!(parser.headers(), vec!["col_0", "col_1"]);
+        parser.reset(file.path(), false).unwrap();
+        assert_eq!(parser.records().count(), 3);
+    }
+
     #[test]
     fn test_empty_file_error() {
         let file = create_test_csv("");
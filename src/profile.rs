@@ -0,0 +1,169 @@
+//! Value statistics gathered during inference with `--profile`
+
+use crate::schema::InferenceConfig;
+use crate::types::SqlType;
+use chrono::NaiveDateTime;
+use std::collections::HashSet;
+
+/// Distinct values counted per column before giving up
+pub const DISTINCT_CAP: usize = 10_000;
+
+/// Ranges and distinct count of a column's non-null sampled values
+///
+/// Numeric and temporal ranges are both tracked, since the column's type
+/// isn't settled until inference ends; [`ColumnProfile::range`] picks the
+/// one matching the final type.
+#[derive(Debug, Clone, Default)]
+pub struct ColumnProfile {
+    numbers: Option<(f64, f64)>,
+    times: Option<(NaiveDateTime, NaiveDateTime)>,
+    distinct: HashSet<String>,
+    distinct_capped: bool,
+}
+
+impl ColumnProfile {
+    /// Record a non-null value whose own inferred type is `inferred`
+    pub fn update(&mut self, value: &str, inferred: &SqlType, config: &InferenceConfig) {
+        if inferred.is_numeric() {
+            let normalized = SqlType::normalize_number(value, config.decimal_separator, config.thousands_separator);
+            if let Ok(number) = normalized.parse::<f64>() {
+                if number.is_finite() {
+                    self.numbers = Some(widen(self.numbers, number, number));
+                }
+            }
+        }
+
+        let time = match inferred {
+            SqlType::Date => SqlType::parse_date(value, &config.date_formats).and_then(|d| d.and_hms_opt(0, 0, 0)),
+            SqlType::Timestamp => SqlType::parse_timestamp(value, &config.timestamp_formats),
+            SqlType::TimestampTz => {
+                SqlType::parse_timestamptz(value, &config.timestamp_formats).map(|t| t.naive_utc())
+            }
+            _ => None,
+        };
+        if let Some(time) = time {
+            self.times = Some(widen(self.times, time, time));
+        }
+
+        if !self.distinct_capped && !self.distinct.contains(value) {
+            if self.distinct.len() == DISTINCT_CAP {
+                self.distinct_capped = true;
+            } else {
+                self.distinct.insert(value.to_string());
+            }
+        }
+    }
+
+    /// Fold in the profile of the same column from another file
+    pub fn merge(&mut self, other: &ColumnProfile) {
+        if let Some((min, max)) = other.numbers {
+            self.numbers = Some(widen(self.numbers, min, max));
+        }
+        if let Some((min, max)) = other.times {
+            self.times = Some(widen(self.times, min, max));
+        }
+        for value in &other.distinct {
+            if self.distinct.len() == DISTINCT_CAP {
+                self.distinct_capped = true;
+                break;
+            }
+            self.distinct.insert(value.clone());
+        }
+        self.distinct_capped |= other.distinct_capped;
+    }
+
+    /// Distinct values seen, up to [`DISTINCT_CAP`]
+    pub fn distinct(&self) -> usize {
+        self.distinct.len()
+    }
+
+    /// More than [`DISTINCT_CAP`] distinct values were seen
+    pub fn distinct_capped(&self) -> bool {
+        self.distinct_capped
+    }
+
+    /// Smallest and largest value, for numeric and date/time types
+    pub fn range(&self, sql_type: &SqlType) -> Option<(String, String)> {
+        match sql_type {
+            t if t.is_numeric() => self.numbers.map(|(min, max)| (min.to_string(), max.to_string())),
+            SqlType::Date => self.times.map(|(min, max)| {
+                (min.date().to_string(), max.date().to_string())
+            }),
+            SqlType::Timestamp | SqlType::TimestampTz => self.times.map(|(min, max)| {
+                (min.to_string(), max.to_string())
+            }),
+            _ => None,
+        }
+    }
+
+    /// One-line summary for the schema display
+    pub fn summary(&self, sql_type: &SqlType, max_length: usize) -> String {
+        let mut parts = Vec::new();
+        if let Some((min, max)) = self.range(sql_type) {
+            parts.push(format!("min {}", min));
+            parts.push(format!("max {}", max));
+        }
+        let plus = if self.distinct_capped { "+" } else { "" };
+        parts.push(format!("{}{} distinct", self.distinct(), plus));
+        parts.push(format!("longest {}", max_length));
+        parts.join(", ")
+    }
+}
+
+fn widen<T: PartialOrd + Copy>(range: Option<(T, T)>, min: T, max: T) -> (T, T) {
+    match range {
+        Some((lo, hi)) => (
+            if min < lo { min } else { lo },
+            if max > hi { max } else { hi },
+        ),
+        None => (min, max),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::TableSchema;
+
+    #[test]
+    fn test_profile_ranges() {
+        let config = InferenceConfig { profile: true, ..Default::default() };
+        let mut schema = TableSchema::new(
+            "t".to_string(),
+            vec!["n".to_string(), "d".to_string(), "s".to_string()],
+        );
+        for row in [["10", "2024-03-01", "b"], ["-2.5", "2023-12-31", "a"], ["7", "", "b"]] {
+            let row: Vec<String> = row.iter().map(|v| v.to_string()).collect();
+            schema.update_row_with(&row, &config).unwrap();
+        }
+        schema.finalize_with(&config);
+
+        let profile = |i: usize| schema.columns[i].profile.as_ref().unwrap();
+        assert_eq!(profile(0).range(&schema.columns[0].sql_type), Some(("-2.5".to_string(), "10".to_string())));
+        assert_eq!(
+            profile(1).range(&schema.columns[1].sql_type),
+            Some(("2023-12-31".to_string(), "2024-03-01".to_string()))
+        );
+        assert_eq!(profile(1).distinct(), 2);
+        assert_eq!(profile(2).range(&schema.columns[2].sql_type), None);
+        assert_eq!(profile(2).summary(&schema.columns[2].sql_type, 1), "2 distinct, longest 1");
+
+        // Off by default
+        let mut plain = TableSchema::new("t".to_string(), vec!["n".to_string()]);
+        plain.update_row(&["1".to_string()]).unwrap();
+        assert!(plain.columns[0].profile.is_none());
+    }
+
+    #[test]
+    fn test_distinct_cap() {
+        let config = InferenceConfig::default();
+        let mut profile = ColumnProfile::default();
+        for i in 0..DISTINCT_CAP + 5 {
+            profile.update(&i.to_string(), &SqlType::Integer, &config);
+        }
+
+        assert_eq!(profile.distinct(), DISTINCT_CAP);
+        assert!(profile.distinct_capped());
+        assert!(profile.summary(&SqlType::Integer, 5).contains("10000+ distinct"));
+    }
+}
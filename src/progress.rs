@@ -1,14 +1,155 @@
 //! Progress tracking and display
 
-use indicatif::{ProgressBar, ProgressStyle};
-use std::time::Instant;
+use crate::errors::LoaderError;
+use crate::loader::LoadStats;
+use indicatif::{HumanBytes, ProgressBar, ProgressStyle};
+use serde_json::json;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How load progress is displayed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ProgressFormat {
+    /// Interactive progress bar
+    #[default]
+    Bar,
+    /// One JSON object per batch on stderr, then a JSON summary
+    Json,
+}
+
+/// Receives progress updates while a load runs
+///
+/// Implement this to render progress somewhere other than the terminal,
+/// e.g. a GUI or a metrics pipeline.
+pub trait ProgressSink: Send + Sync {
+    /// Called after each batch with the rows processed so far and the
+    /// expected total, when the rows were counted up front
+    fn on_batch(&self, rows_loaded: u64, total: Option<u64>);
+
+    /// Called before each `on_batch` with the CSV bytes read so far,
+    /// counted after decompression
+    fn on_bytes(&self, _bytes_read: u64) {}
+
+    /// Called once when the load succeeds
+    fn on_finish(&self, stats: &LoadStats);
+
+    /// Called once when the load fails
+    fn on_error(&self, _error: &LoaderError) {}
+}
+
+/// Sink that ignores all progress
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoProgress;
+
+impl ProgressSink for NoProgress {
+    fn on_batch(&self, _rows_loaded: u64, _total: Option<u64>) {}
+
+    fn on_finish(&self, _stats: &LoadStats) {}
+}
+
+/// Sink writing JSON lines to stderr for orchestrators and log collectors
+#[derive(Debug)]
+pub struct JsonProgress {
+    start_time: Instant,
+    bytes: AtomicU64,
+}
+
+impl JsonProgress {
+    pub fn new() -> Self {
+        Self {
+            start_time: Instant::now(),
+            bytes: AtomicU64::new(0),
+        }
+    }
+
+    /// JSON object describing progress after a batch
+    fn batch_line(&self, rows_loaded: u64, total: Option<u64>) -> serde_json::Value {
+        let elapsed = self.start_time.elapsed().as_secs_f64();
+        let pct = total
+            .filter(|&total| total > 0)
+            .map(|total| (rows_loaded as f64 * 1000.0 / total as f64).round() / 10.0);
+        let per_sec = |count: u64| if elapsed > 0.0 { (count as f64 / elapsed).round() as u64 } else { 0 };
+        let bytes = self.bytes.load(Ordering::Relaxed);
+
+        json!({
+            "rows": rows_loaded,
+            "total": total,
+            "pct": pct,
+            "rows_per_sec": per_sec(rows_loaded),
+            "bytes": bytes,
+            "bytes_per_sec": per_sec(bytes),
+            "elapsed_s": (elapsed * 100.0).round() / 100.0,
+        })
+    }
+}
+
+impl Default for JsonProgress {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProgressSink for JsonProgress {
+    fn on_batch(&self, rows_loaded: u64, total: Option<u64>) {
+        eprintln!("{}", self.batch_line(rows_loaded, total));
+    }
+
+    fn on_bytes(&self, bytes_read: u64) {
+        self.bytes.store(bytes_read, Ordering::Relaxed);
+    }
+
+    fn on_finish(&self, stats: &LoadStats) {
+        eprintln!("{}", json!({
+            "table": stats.table_name,
+            "rows_loaded": stats.rows_loaded,
+            "rows_rejected": stats.rows_rejected,
+            "rows_padded": stats.rows_padded,
+            "rows_truncated": stats.rows_truncated,
+            "rows_per_sec": stats.throughput.round() as u64,
+            "bytes_read": stats.bytes_read,
+            "bytes_per_sec": stats.bytes_per_sec.round() as u64,
+            "elapsed_s": (stats.elapsed.as_secs_f64() * 100.0).round() / 100.0,
+        }));
+    }
+
+    fn on_error(&self, error: &LoaderError) {
+        eprintln!("{}", json!({ "error": error.to_string() }));
+    }
+}
+
+/// Rows and bytes processed, and their rates, at one instant
+///
+/// Bytes are only counted when the tracker is told them through
+/// [`ProgressSink::on_bytes`]. Unlike rows, they measure throughput
+/// steadily when row widths vary.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProgressSnapshot {
+    pub rows: u64,
+    pub bytes: u64,
+    pub elapsed: Duration,
+    pub rows_per_sec: f64,
+    pub bytes_per_sec: f64,
+}
 
 /// Progress tracker for CSV loading
+///
+/// Clones share the same bar and counter, so parallel workers can each
+/// hold one and call `inc` concurrently.
+#[derive(Clone)]
 pub struct ProgressTracker {
+    state: Arc<TrackerState>,
+}
+
+struct TrackerState {
     bar: ProgressBar,
     start_time: Instant,
     #[allow(dead_code)]
     total_rows: Option<u64>,
+    rows: AtomicU64,
+    bytes: AtomicU64,
+    /// Final snapshot, fixed when the tracker finishes
+    finished: Mutex<Option<ProgressSnapshot>>,
 }
 
 impl ProgressTracker {
@@ -24,84 +165,143 @@ impl ProgressTracker {
 
         let style = if total_rows.is_some() {
             ProgressStyle::default_bar()
-                .template("[{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} rows ({percent}%) | {per_sec} | ETA: {eta}")
+                .template("[{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} rows ({percent}%) | {per_sec} {msg} | ETA: {eta}")
                 .unwrap()
                 .progress_chars("=>-")
         } else {
             ProgressStyle::default_spinner()
-                .template("[{elapsed_precise}] {spinner} {pos} rows | {per_sec}")
+                .template("[{elapsed_precise}] {spinner} {pos} rows | {per_sec} {msg}")
                 .unwrap()
         };
 
         bar.set_style(style);
 
         Self {
-            bar,
-            start_time: Instant::now(),
-            total_rows,
+            state: Arc::new(TrackerState {
+                bar,
+                start_time: Instant::now(),
+                total_rows,
+                rows: AtomicU64::new(0),
+                bytes: AtomicU64::new(0),
+                finished: Mutex::new(None),
+            }),
         }
     }
 
     /// Update progress with row count
-    #[allow(dead_code)]
     pub fn update(&self, rows_processed: u64) {
-        self.bar.set_position(rows_processed);
+        self.state.rows.store(rows_processed, Ordering::Relaxed);
+        self.state.bar.set_position(rows_processed);
     }
 
     /// Increment progress by delta
     pub fn inc(&self, delta: u64) {
-        self.bar.inc(delta);
+        self.state.rows.fetch_add(delta, Ordering::Relaxed);
+        self.state.bar.inc(delta);
+    }
+
+    /// Record the bytes read so far, showing their rate on the bar
+    pub fn update_bytes(&self, bytes_read: u64) {
+        self.state.bytes.store(bytes_read, Ordering::Relaxed);
+        let snapshot = self.take_snapshot();
+        if snapshot.bytes_per_sec > 0.0 {
+            self.state.bar.set_message(format!("({}/s)", HumanBytes(snapshot.bytes_per_sec as u64)));
+        }
     }
 
     /// Set a status message
-    #[allow(dead_code)]
     pub fn set_message(&self, msg: String) {
-        self.bar.set_message(msg);
+        self.state.bar.set_message(msg);
     }
 
-    /// Finish and show completion message
-    pub fn finish(&self) {
-        let elapsed = self.start_time.elapsed();
-        let rows = self.bar.position();
+    /// Rows, elapsed time and throughput read together
+    ///
+    /// After `finish` this always returns the final figures.
+    pub fn snapshot(&self) -> ProgressSnapshot {
+        if let Some(snapshot) = *self.state.finished.lock().unwrap() {
+            return snapshot;
+        }
+        self.take_snapshot()
+    }
 
-        let throughput = if elapsed.as_secs() > 0 {
-            rows / elapsed.as_secs()
-        } else {
-            rows
-        };
+    fn take_snapshot(&self) -> ProgressSnapshot {
+        let rows = self.state.rows.load(Ordering::Relaxed);
+        let bytes = self.state.bytes.load(Ordering::Relaxed);
+        let elapsed = self.state.start_time.elapsed();
+        let secs = elapsed.as_secs_f64();
+        let per_sec = |count: u64| if secs > 0.0 { count as f64 / secs } else { 0.0 };
 
-        let message = format!(
-            "Completed! {} rows in {:.2}s ({} rows/sec)",
+        ProgressSnapshot {
             rows,
-            elapsed.as_secs_f64(),
-            throughput
-        );
+            bytes,
+            elapsed,
+            rows_per_sec: per_sec(rows),
+            bytes_per_sec: per_sec(bytes),
+        }
+    }
 
-        self.bar.finish_with_message(message);
+    /// Fix the final snapshot and close the bar with `message`
+    fn finish_bar(&self, message: impl FnOnce(&ProgressSnapshot) -> String) {
+        let mut finished = self.state.finished.lock().unwrap();
+        let snapshot = *finished.get_or_insert_with(|| self.take_snapshot());
+        self.state.bar.finish_with_message(message(&snapshot));
+    }
+
+    /// Finish and show completion message
+    pub fn finish(&self) {
+        self.finish_bar(|snapshot| {
+            let mut message = format!(
+                "Completed! {} rows in {:.2}s ({:.0} rows/sec",
+                snapshot.rows,
+                snapshot.elapsed.as_secs_f64(),
+                snapshot.rows_per_sec
+            );
+            if snapshot.bytes > 0 {
+                message.push_str(&format!(", {}/s", HumanBytes(snapshot.bytes_per_sec as u64)));
+            }
+            message.push(')');
+            message
+        });
     }
 
     /// Finish with error message
     pub fn finish_with_error(&self, error: &str) {
-        self.bar.finish_with_message(format!("Failed: {}", error));
+        self.finish_bar(|_| format!("Failed: {}", error));
     }
 
     /// Get elapsed time
-    pub fn elapsed(&self) -> std::time::Duration {
-        self.start_time.elapsed()
+    pub fn elapsed(&self) -> Duration {
+        self.snapshot().elapsed
     }
 
     /// Get current throughput (rows/sec)
     pub fn throughput(&self) -> f64 {
-        let elapsed = self.start_time.elapsed().as_secs_f64();
-        if elapsed > 0.0 {
-            self.bar.position() as f64 / elapsed
-        } else {
-            0.0
+        self.snapshot().rows_per_sec
+    }
+}
+
+impl ProgressSink for ProgressTracker {
+    fn on_batch(&self, rows_loaded: u64, total: Option<u64>) {
+        if let Some(total) = total {
+            self.state.bar.set_length(total);
         }
+        self.update(rows_loaded);
+    }
+
+    fn on_bytes(&self, bytes_read: u64) {
+        self.update_bytes(bytes_read);
+    }
+
+    fn on_finish(&self, _stats: &LoadStats) {
+        self.finish();
+    }
+
+    fn on_error(&self, error: &LoaderError) {
+        self.finish_with_error(&error.to_string());
     }
 }
 
-impl Drop for ProgressTracker {
+impl Drop for TrackerState {
     fn drop(&mut self) {
         // Ensure progress bar is cleared on drop
         if !self.bar.is_finished() {
@@ -117,14 +317,14 @@ mod tests {
     #[test]
     fn test_progress_tracker_creation() {
         let tracker = ProgressTracker::new(Some(100), true);
-        assert_eq!(tracker.total_rows, Some(100));
+        assert_eq!(tracker.state.total_rows, Some(100));
     }
 
     #[test]
     fn test_progress_update() {
         let tracker = ProgressTracker::new(Some(100), true);
         tracker.update(50);
-        assert_eq!(tracker.bar.position(), 50);
+        assert_eq!(tracker.state.bar.position(), 50);
     }
 
     #[test]
@@ -132,7 +332,83 @@ mod tests {
         let tracker = ProgressTracker::new(None, true);
         tracker.inc(10);
         tracker.inc(5);
-        assert_eq!(tracker.bar.position(), 15);
+        assert_eq!(tracker.state.bar.position(), 15);
+    }
+
+    #[test]
+    fn test_tracker_as_sink() {
+        let tracker = ProgressTracker::new(Some(100), true);
+        tracker.on_batch(40, Some(100));
+        tracker.on_batch(90, Some(100));
+        assert_eq!(tracker.state.bar.position(), 90);
+
+        tracker.on_finish(&LoadStats::default());
+        assert!(tracker.state.bar.is_finished());
+    }
+
+    #[test]
+    fn test_concurrent_increments() {
+        let tracker = ProgressTracker::new(None, true);
+
+        std::thread::scope(|scope| {
+            for _ in 0..16 {
+                let tracker = tracker.clone();
+                scope.spawn(move || {
+                    for _ in 0..1000 {
+                        tracker.inc(3);
+                    }
+                });
+            }
+        });
+
+        assert_eq!(tracker.snapshot().rows, 48_000);
+        assert_eq!(tracker.state.bar.position(), 48_000);
+    }
+
+    #[test]
+    fn test_snapshot_fixed_after_finish() {
+        let tracker = ProgressTracker::new(None, true);
+        tracker.inc(10);
+        tracker.finish();
+
+        let snapshot = tracker.snapshot();
+        tracker.inc(5);
+        assert_eq!(tracker.snapshot(), snapshot);
+        assert_eq!(snapshot.rows, 10);
+    }
+
+    #[test]
+    fn test_json_batch_line() {
+        let progress = JsonProgress::new();
+
+        let line = progress.batch_line(120_000, Some(500_000));
+        assert_eq!(line["rows"], 120_000);
+        assert_eq!(line["total"], 500_000);
+        assert_eq!(line["pct"], 24.0);
+        assert!(line["elapsed_s"].is_number());
+
+        let line = progress.batch_line(10, None);
+        assert!(line["total"].is_null());
+        assert!(line["pct"].is_null());
+    }
+
+    #[test]
+    fn test_bytes_in_snapshot() {
+        let tracker = ProgressTracker::new(None, true);
+        assert_eq!(tracker.snapshot().bytes, 0);
+
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        tracker.on_bytes(4096);
+        tracker.on_batch(10, None);
+        let snapshot = tracker.snapshot();
+        assert_eq!(snapshot.bytes, 4096);
+        assert!(snapshot.bytes_per_sec > 0.0);
+
+        let progress = JsonProgress::new();
+        progress.on_bytes(2048);
+        let line = progress.batch_line(10, None);
+        assert_eq!(line["bytes"], 2048);
+        assert!(line["bytes_per_sec"].is_number());
     }
 
     #[test]
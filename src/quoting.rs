@@ -0,0 +1,295 @@
+//! Recovering from malformed quoting, line by line
+//!
+//! The CSV reader never fails on bad quoting: an unterminated quote makes
+//! the rest of the file, or everything up to the next quote, one field.
+//! [`QuoteGuard`] sits below the reader and checks each record's quoting
+//! the way the reader will parse it. A record that is malformed has its
+//! first line replaced with a lone delimiter and reported, and checking
+//! starts again on the next line, so one stray quote costs one line. The
+//! parser drops the placeholder row by its line number.
+//!
+//! Limitations:
+//! - A record is malformed when a closing quote is followed by anything
+//!   but a delimiter, whitespace or the end of the line, when the file ends
+//!   inside a quoted field, or when a quoted field runs past
+//!   [`MAX_QUOTED_LINES`] lines. A legitimate field that long is lost.
+//! - Only the line the bad record starts on is dropped. When the stray
+//!   quote is on a later line of a multi-line record, the lines before it
+//!   come through as rows of their own, usually with the wrong width.
+//! - A malformed header line can't be recovered.
+
+use crate::reject::RejectedRow;
+use std::collections::{HashSet, VecDeque};
+use std::io::{self, BufRead, Read};
+use std::sync::{Arc, Mutex};
+
+/// Lines a quoted field may span before it is taken to be unterminated;
+/// this bounds the lines held while a record is checked
+pub const MAX_QUOTED_LINES: usize = 10_000;
+
+/// Lines dropped for malformed quoting, shared between a [`QuoteGuard`]
+/// and whoever reports them
+#[derive(Debug, Clone, Default)]
+pub struct MalformedLines(Arc<Mutex<Dropped>>);
+
+#[derive(Debug, Default)]
+struct Dropped {
+    /// Not yet taken for reporting
+    rows: Vec<RejectedRow>,
+    /// Every line dropped so far
+    lines: HashSet<u64>,
+}
+
+impl MalformedLines {
+    /// Remove and return the lines reported since the last call
+    pub fn take(&self) -> Vec<RejectedRow> {
+        std::mem::take(&mut self.0.lock().unwrap().rows)
+    }
+
+    /// Whether `line` was dropped
+    pub fn contains(&self, line: u64) -> bool {
+        self.0.lock().unwrap().lines.contains(&line)
+    }
+
+    fn push(&self, rejected: RejectedRow) {
+        let mut dropped = self.0.lock().unwrap();
+        dropped.lines.insert(rejected.line);
+        dropped.rows.push(rejected);
+    }
+}
+
+/// The CSV dialect bytes that affect quoting
+#[derive(Debug, Clone, Copy)]
+pub struct Dialect {
+    pub delimiter: u8,
+    pub quote: u8,
+    pub escape: Option<u8>,
+    pub comment: Option<u8>,
+}
+
+/// Where the scan of a record stands after a line
+enum Scan {
+    /// The record ended with the line
+    Complete,
+    /// The line ended inside a quoted field
+    Open(State),
+    Malformed(&'static str),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    StartField,
+    Unquoted,
+    Quoted,
+    /// Just after the escape byte inside a quoted field
+    Escaped,
+    /// Just after a quote inside a quoted field: the closing quote, or the
+    /// first of a doubled one
+    QuoteInQuoted,
+    /// Whitespace after a closing quote
+    AfterQuoted,
+}
+
+/// Passes through well-quoted records and replaces the first line of
+/// malformed ones
+pub struct QuoteGuard<R> {
+    inner: R,
+    dialect: Dialect,
+    /// Source line number of the first pending line
+    line: u64,
+    /// Lines read but not yet passed on, starting at a record boundary
+    pending: VecDeque<Vec<u8>>,
+    eof: bool,
+    out: Vec<u8>,
+    pos: usize,
+    malformed: MalformedLines,
+}
+
+impl<R: BufRead> QuoteGuard<R> {
+    /// Wrap `inner`, whose next line is source line `first_line`
+    pub fn new(inner: R, dialect: Dialect, first_line: u64, malformed: MalformedLines) -> Self {
+        Self {
+            inner,
+            dialect,
+            line: first_line,
+            pending: VecDeque::new(),
+            eof: false,
+            out: Vec::new(),
+            pos: 0,
+            malformed,
+        }
+    }
+
+    /// Read one more line into `pending`; false at the end of the input
+    fn read_line(&mut self) -> io::Result<bool> {
+        if self.eof {
+            return Ok(false);
+        }
+        let mut line = Vec::new();
+        if self.inner.read_until(b'\n', &mut line)? == 0 {
+            self.eof = true;
+            return Ok(false);
+        }
+        self.pending.push_back(line);
+        Ok(true)
+    }
+
+    /// Move the next record, or the placeholder for a malformed line, to `out`
+    fn refill(&mut self) -> io::Result<()> {
+        self.out.clear();
+        self.pos = 0;
+
+        if self.pending.is_empty() && !self.read_line()? {
+            return Ok(());
+        }
+
+        let mut state = State::StartField;
+        let mut index = 0;
+        let problem = loop {
+            match scan(&self.pending[index], state, index == 0, &self.dialect) {
+                Scan::Complete => break None,
+                Scan::Malformed(problem) => break Some(problem),
+                Scan::Open(open) => state = open,
+            }
+
+            index += 1;
+            if index == self.pending.len() && !self.read_line()? {
+                break Some("unterminated quoted field");
+            }
+            if index == MAX_QUOTED_LINES {
+                break Some("quoted field runs past the line limit");
+            }
+        };
+
+        match problem {
+            None => {
+                for line in self.pending.drain(..=index) {
+                    self.out.extend_from_slice(&line);
+                }
+                self.line += index as u64 + 1;
+            }
+            Some(problem) => {
+                let line = self.pending.pop_front().expect("a line was scanned");
+                let text = String::from_utf8_lossy(&line);
+                self.malformed.push(RejectedRow {
+                    line: self.line,
+                    row: vec![text.trim_end_matches(['\r', '\n']).to_string()],
+                    error: problem.to_string(),
+                });
+                // Not an empty line: the reader skips those but then
+                // misnumbers the next record
+                self.out.extend_from_slice(&[self.dialect.delimiter, b'\n']);
+                self.line += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Scan one line of a record, starting in `state`
+fn scan(line: &[u8], mut state: State, first: bool, dialect: &Dialect) -> Scan {
+    if first && dialect.comment.is_some_and(|c| line.first() == Some(&c)) {
+        return Scan::Complete;
+    }
+
+    for &byte in line {
+        state = match state {
+            State::StartField | State::Unquoted if byte == b'\n' => return Scan::Complete,
+            State::StartField if byte == dialect.quote => State::Quoted,
+            State::StartField | State::Unquoted if byte == dialect.delimiter => State::StartField,
+            State::StartField | State::Unquoted => State::Unquoted,
+            State::Quoted if Some(byte) == dialect.escape => State::Escaped,
+            State::Quoted if byte == dialect.quote => State::QuoteInQuoted,
+            State::Quoted | State::Escaped => State::Quoted,
+            State::QuoteInQuoted if byte == dialect.quote => State::Quoted,
+            State::QuoteInQuoted | State::AfterQuoted => match byte {
+                b'\n' => return Scan::Complete,
+                b if b == dialect.delimiter => State::StartField,
+                b' ' | b'\t' | b'\r' => State::AfterQuoted,
+                _ => return Scan::Malformed("unexpected character after a closing quote"),
+            },
+        };
+    }
+
+    match state {
+        State::Quoted | State::Escaped => Scan::Open(state),
+        // The last line of the input, without a newline
+        _ => Scan::Complete,
+    }
+}
+
+impl<R: BufRead> Read for QuoteGuard<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for QuoteGuard<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.pos >= self.out.len() {
+            self.refill()?;
+        }
+        Ok(&self.out[self.pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.out.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIALECT: Dialect = Dialect { delimiter: b',', quote: b'"', escape: None, comment: None };
+
+    fn guard(input: &str) -> (String, Vec<RejectedRow>) {
+        let malformed = MalformedLines::default();
+        let mut guard = QuoteGuard::new(input.as_bytes(), DIALECT, 1, malformed.clone());
+        let mut out = String::new();
+        guard.read_to_string(&mut out).unwrap();
+        (out, malformed.take())
+    }
+
+    #[test]
+    fn test_well_quoted_input_passes_through() {
+        let input = "id,note\n1,\"a, \"\"quoted\"\"\nmulti-line\"\n2,\"b\" \n# not a comment\n3,x\"y";
+        let (out, malformed) = guard(input);
+
+        assert_eq!(out, input);
+        assert!(malformed.is_empty());
+    }
+
+    #[test]
+    fn test_unterminated_quote_drops_one_line() {
+        let (out, malformed) = guard("id,note\n1,ok\n2,\"bad\n3,ok\n4,\"fine\"\n");
+
+        // The closing quote after `4,` is followed by `fine`
+        assert_eq!(out, "id,note\n1,ok\n,\n3,ok\n4,\"fine\"\n");
+        assert_eq!(malformed.len(), 1);
+        assert_eq!(malformed[0].line, 3);
+        assert_eq!(malformed[0].row, vec!["2,\"bad"]);
+        assert_eq!(malformed[0].error, "unexpected character after a closing quote");
+
+        let (out, malformed) = guard("id,note\n1,\"bad\n2,ok\n");
+        assert_eq!(out, "id,note\n,\n2,ok\n");
+        assert_eq!(malformed[0].error, "unterminated quoted field");
+    }
+
+    #[test]
+    fn test_escape_and_comment() {
+        let dialect = Dialect { escape: Some(b'\\'), comment: Some(b'#'), ..DIALECT };
+        let malformed = MalformedLines::default();
+        let input = "a,b\n# a \"stray quote\n1,\"x\\\"y\"\n";
+        let mut guard = QuoteGuard::new(input.as_bytes(), dialect, 1, malformed.clone());
+        let mut out = String::new();
+        guard.read_to_string(&mut out).unwrap();
+
+        assert_eq!(out, input);
+        assert!(malformed.take().is_empty());
+    }
+}
@@ -0,0 +1,76 @@
+//! Reject file for rows that could not be loaded
+
+use crate::errors::Result;
+use csv::Writer;
+use std::fs::File;
+use std::path::Path;
+
+/// A row that failed to load, with its source location and error
+#[derive(Debug, Clone)]
+pub struct RejectedRow {
+    pub line: u64,
+    pub row: Vec<String>,
+    pub error: String,
+}
+
+/// Writes rejected rows to a CSV file
+///
+/// Each output record is `line, error, <original fields...>` so the
+/// rejects can be inspected, fixed, and re-loaded.
+pub struct RejectWriter {
+    writer: Writer<File>,
+}
+
+impl RejectWriter {
+    /// Create a reject file, truncating any existing file
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let writer = csv::WriterBuilder::new()
+            .flexible(true)
+            .from_path(path)?;
+
+        Ok(Self { writer })
+    }
+
+    /// Append a rejected row
+    pub fn write(&mut self, rejected: &RejectedRow) -> Result<()> {
+        let line = rejected.line.to_string();
+        let mut record: Vec<&str> = vec![&line, &rejected.error];
+        record.extend(rejected.row.iter().map(String::as_str));
+
+        self.writer.write_record(&record)?;
+        Ok(())
+    }
+
+    /// Flush buffered rows to disk
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_reject_writer() {
+        let file = NamedTempFile::new().unwrap();
+
+        let mut writer = RejectWriter::create(file.path()).unwrap();
+        writer
+            .write(&RejectedRow {
+                line: 4,
+                row: vec!["abc".to_string(), "x,y".to_string()],
+                error: "invalid input syntax for type integer".to_string(),
+            })
+            .unwrap();
+        writer.flush().unwrap();
+
+        let content = std::fs::read_to_string(file.path()).unwrap();
+        assert_eq!(
+            content,
+            "4,invalid input syntax for type integer,abc,\"x,y\"\n"
+        );
+    }
+}
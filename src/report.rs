@@ -0,0 +1,269 @@
+//! Machine-readable summary of schema inference
+
+use crate::errors::{LoaderError, Result};
+use crate::schema::TableSchema;
+use crate::types::SqlType;
+use serde::Serialize;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// Something about a column's inferred type worth double-checking
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnWarning {
+    /// Mixed values forced the column to a wider type than most of them need
+    TypeDegraded { column: String, detail: String },
+    /// Confidence is below the requested minimum
+    LowConfidence { column: String, confidence: f64 },
+    /// No non-null values were sampled, so the column defaulted to TEXT
+    AllNull { column: String },
+}
+
+impl ColumnWarning {
+    /// Name of the column the warning is about
+    pub fn column(&self) -> &str {
+        match self {
+            ColumnWarning::TypeDegraded { column, .. }
+            | ColumnWarning::LowConfidence { column, .. }
+            | ColumnWarning::AllNull { column } => column,
+        }
+    }
+}
+
+impl fmt::Display for ColumnWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColumnWarning::TypeDegraded { column, detail } => write!(f, "Column '{}': {}", column, detail),
+            ColumnWarning::LowConfidence { column, confidence } => {
+                write!(f, "Column '{}' inferred with low confidence ({:.2})", column, confidence)
+            }
+            ColumnWarning::AllNull { column } => {
+                write!(f, "Column '{}' had no values in the sample; defaulting to TEXT", column)
+            }
+        }
+    }
+}
+
+/// Warnings for every column of a finalized schema, in column order
+///
+/// Columns with no non-null samples get [`ColumnWarning::AllNull`] rather
+/// than also being reported as low confidence.
+pub fn column_warnings(schema: &TableSchema, min_confidence: f64) -> Vec<ColumnWarning> {
+    let mut warnings = Vec::new();
+    for column in &schema.columns {
+        for detail in &column.warnings {
+            warnings.push(ColumnWarning::TypeDegraded { column: column.name.clone(), detail: detail.clone() });
+        }
+        if column.null_count == column.sample_count {
+            warnings.push(ColumnWarning::AllNull { column: column.name.clone() });
+        } else if column.confidence() < min_confidence {
+            warnings.push(ColumnWarning::LowConfidence {
+                column: column.name.clone(),
+                confidence: column.confidence(),
+            });
+        }
+    }
+    warnings
+}
+
+/// An inferred schema along with what inference wasn't sure about
+#[derive(Debug, Clone)]
+pub struct InferenceResult {
+    pub schema: TableSchema,
+    pub warnings: Vec<ColumnWarning>,
+}
+
+impl InferenceResult {
+    /// Collect the warnings for a finalized schema, flagging columns below
+    /// `min_confidence`
+    pub fn new(schema: TableSchema, min_confidence: f64) -> Self {
+        let warnings = column_warnings(&schema, min_confidence);
+        Self { schema, warnings }
+    }
+
+    /// Mean confidence of the columns that had values, 0.0 if none did
+    pub fn overall_confidence(&self) -> f64 {
+        let scores: Vec<f64> = self.schema.columns
+            .iter()
+            .filter(|c| c.null_count < c.sample_count)
+            .map(|c| c.confidence())
+            .collect();
+
+        if scores.is_empty() {
+            return 0.0;
+        }
+        scores.iter().sum::<f64>() / scores.len() as f64
+    }
+
+    /// Warnings about the named column
+    pub fn warnings_for<'a>(&'a self, column: &'a str) -> impl Iterator<Item = &'a ColumnWarning> {
+        self.warnings.iter().filter(move |w| w.column() == column)
+    }
+}
+
+/// Per-column inference results
+#[derive(Debug, Clone, Serialize)]
+pub struct ColumnReport {
+    pub name: String,
+    pub sql_type: SqlType,
+    pub nullable: bool,
+    pub confidence: f64,
+    pub sample_count: usize,
+    pub null_count: usize,
+    /// Confidence is below the requested minimum
+    pub low_confidence: bool,
+    pub warnings: Vec<String>,
+    /// Value statistics, with `--profile`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<ProfileReport>,
+}
+
+/// Value statistics for one column
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileReport {
+    /// Smallest value, for numeric and date/time columns
+    pub min: Option<String>,
+    pub max: Option<String>,
+    /// Distinct non-null values, counted up to `profile::DISTINCT_CAP`
+    pub distinct: usize,
+    /// There were more distinct values than were counted
+    pub distinct_capped: bool,
+    /// Longest non-null value, in characters
+    pub max_length: usize,
+}
+
+/// Inference report for a whole table
+#[derive(Debug, Clone, Serialize)]
+pub struct InferenceReport {
+    pub table_name: String,
+    pub min_confidence: Option<f64>,
+    pub columns: Vec<ColumnReport>,
+}
+
+impl InferenceReport {
+    /// Build a report from a finalized schema
+    ///
+    /// Columns are flagged as low confidence only when `min_confidence` is set.
+    pub fn new(schema: &TableSchema, min_confidence: Option<f64>) -> Self {
+        let columns = schema.columns
+            .iter()
+            .map(|column| {
+                let confidence = column.confidence();
+                ColumnReport {
+                    name: column.name.clone(),
+                    sql_type: column.sql_type.clone(),
+                    nullable: column.nullable,
+                    confidence,
+                    sample_count: column.sample_count,
+                    null_count: column.null_count,
+                    low_confidence: min_confidence.is_some_and(|min| confidence < min),
+                    warnings: column.warnings.clone(),
+                    profile: column.profile.as_ref().map(|profile| {
+                        let (min, max) = profile.range(&column.sql_type).unzip();
+                        ProfileReport {
+                            min,
+                            max,
+                            distinct: profile.distinct(),
+                            distinct_capped: profile.distinct_capped(),
+                            max_length: column.max_length,
+                        }
+                    }),
+                }
+            })
+            .collect();
+
+        Self {
+            table_name: schema.table_name.clone(),
+            min_confidence,
+            columns,
+        }
+    }
+
+    /// Write the report as pretty-printed JSON
+    pub fn to_json_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| LoaderError::SchemaFileError(e.to_string()))?;
+        fs::write(path, json + "\n")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    fn sample_schema() -> TableSchema {
+        let mut schema = TableSchema::new("t".to_string(), vec!["id".to_string(), "mixed".to_string()]);
+        for row in [["1", "5"], ["2", "abc"], ["3", ""]] {
+            let row: Vec<String> = row.iter().map(|v| v.to_string()).collect();
+            schema.update_row(&row).unwrap();
+        }
+        schema.finalize();
+        schema
+    }
+
+    #[test]
+    fn test_report_flags_low_confidence() {
+        let report = InferenceReport::new(&sample_schema(), Some(0.7));
+
+        assert!(!report.columns[0].low_confidence);
+        assert_eq!(report.columns[0].confidence, 1.0);
+        assert!(report.columns[1].low_confidence);
+        assert_eq!(report.columns[1].null_count, 1);
+        assert_eq!(report.columns[1].warnings.len(), 1);
+
+        let unflagged = InferenceReport::new(&sample_schema(), None);
+        assert!(unflagged.columns.iter().all(|c| !c.low_confidence));
+    }
+
+    #[test]
+    fn test_inference_result_warnings() {
+        let mut schema = TableSchema::new("t".to_string(), vec!["id".to_string(), "mixed".to_string(), "empty".to_string()]);
+        for row in [["1", "5", ""], ["2", "abc", ""], ["3", "", ""]] {
+            let row: Vec<String> = row.iter().map(|v| v.to_string()).collect();
+            schema.update_row(&row).unwrap();
+        }
+        schema.finalize();
+
+        let result = InferenceResult::new(schema, 0.7);
+        assert_eq!(result.warnings_for("id").count(), 0);
+        let mixed: Vec<&ColumnWarning> = result.warnings_for("mixed").collect();
+        assert!(matches!(mixed[0], ColumnWarning::TypeDegraded { .. }));
+        assert!(matches!(mixed[1], ColumnWarning::LowConfidence { confidence, .. } if *confidence < 0.7));
+        assert_eq!(result.warnings_for("empty").collect::<Vec<_>>(), vec![&ColumnWarning::AllNull { column: "empty".to_string() }]);
+
+        // The all-null column doesn't drag the average down
+        let mixed_confidence = result.schema.columns[1].confidence();
+        assert!((result.overall_confidence() - (1.0 + mixed_confidence) / 2.0).abs() < 1e-9);
+        assert_eq!(
+            result.warnings.last().unwrap().to_string(),
+            "Column 'empty' had no values in the sample; defaulting to TEXT"
+        );
+    }
+
+    #[test]
+    fn test_report_profile() {
+        let config = crate::schema::InferenceConfig { profile: true, ..Default::default() };
+        let mut schema = TableSchema::new("t".to_string(), vec!["id".to_string()]);
+        for id in ["3", "1", "3"] {
+            schema.update_row_with(&[id.to_string()], &config).unwrap();
+        }
+        schema.finalize_with(&config);
+
+        let profile = InferenceReport::new(&schema, None).columns[0].profile.clone().unwrap();
+        assert_eq!((profile.min.as_deref(), profile.max.as_deref()), (Some("1"), Some("3")));
+        assert_eq!(profile.distinct, 2);
+    }
+
+    #[test]
+    fn test_report_json() {
+        let file = NamedTempFile::new().unwrap();
+        InferenceReport::new(&sample_schema(), Some(0.7)).to_json_file(file.path()).unwrap();
+
+        let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(file.path()).unwrap()).unwrap();
+        assert_eq!(json["columns"][0]["sql_type"], "SmallInt");
+        assert_eq!(json["columns"][1]["low_confidence"], true);
+        assert!(json["columns"][0].get("profile").is_none());
+    }
+}
@@ -1,16 +1,67 @@
 //! Schema inference from CSV data
 
 use crate::errors::{LoaderError, Result};
-use crate::types::SqlType;
+use crate::profile::ColumnProfile;
+use crate::types::{IntegerCap, SqlType, BOOL_FALSE_TOKENS, BOOL_TRUE_TOKENS};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+/// Rows inferred by each worker when a batch of rows is spread across
+/// threads
+///
+/// Fixed, so a given batch is always split the same way whatever the
+/// number of cores.
+pub const INFERENCE_PART_ROWS: usize = 4096;
+
+/// Distinct values a profiled column needs before index detection calls it
+/// high cardinality
+pub const INDEX_MIN_DISTINCT: usize = 100;
+
+/// Longest value, in characters, of a column index detection will suggest
+/// for its cardinality; longer ones are usually free text
+pub const INDEX_MAX_LENGTH: usize = 100;
 
 /// Column schema with inferred type
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ColumnSchema {
     pub name: String,
     pub sql_type: SqlType,
     pub nullable: bool,
+    #[serde(default)]
     pub sample_count: usize,
+    #[serde(default)]
     pub null_count: usize,
+    /// Longest non-null value seen, in characters
+    #[serde(default)]
+    pub max_length: usize,
+    /// Notes on type promotions seen during inference
+    #[serde(skip)]
+    pub warnings: Vec<String>,
+    /// Every sampled value was a distinct, non-null integer, making the
+    /// column a primary key candidate (set with `detect_pk`)
+    #[serde(skip)]
+    pub unique: bool,
+    /// Degraded to TEXT because an integer exceeded `max_int_type`
+    #[serde(skip)]
+    pub int_capped: bool,
+    /// Value statistics, gathered with `InferenceConfig::profile`
+    #[serde(skip)]
+    pub profile: Option<ColumnProfile>,
+    /// Values seen so far, while checking uniqueness; dropped once the
+    /// column stops being a candidate
+    #[serde(skip)]
+    seen_values: Option<HashSet<String>>,
+    /// Widest type of the values seen, unlike `sql_type` not degraded by
+    /// the integer cap
+    #[serde(skip)]
+    value_type: Option<SqlType>,
+    /// Value types that widened `value_type`, in order, so merging partial
+    /// schemas notes the promotions one pass over their rows would
+    #[serde(skip)]
+    widened_by: Vec<SqlType>,
 }
 
 impl ColumnSchema {
@@ -21,31 +72,188 @@ impl ColumnSchema {
             nullable: true,
             sample_count: 0,
             null_count: 0,
+            max_length: 0,
+            warnings: Vec::new(),
+            unique: true,
+            int_capped: false,
+            profile: None,
+            seen_values: None,
+            value_type: None,
+            widened_by: Vec::new(),
         }
     }
 
     /// Update schema with a new value
     pub fn update(&mut self, value: &str) {
+        self.update_with(value, &InferenceConfig::default());
+    }
+
+    /// Update schema with a new value using inference settings
+    pub fn update_with(&mut self, value: &str, config: &InferenceConfig) {
         self.sample_count += 1;
 
-        let inferred_type = SqlType::infer_from_str(value);
+        let inferred_type = SqlType::infer_from_str_with(value, config);
 
         if inferred_type == SqlType::Null {
             self.null_count += 1;
+        } else {
+            self.max_length = self.max_length.max(value.chars().count());
+        }
+
+        if config.profile {
+            let profile = self.profile.get_or_insert_with(ColumnProfile::default);
+            if inferred_type != SqlType::Null {
+                profile.update(value, &inferred_type, config);
+            }
+        }
+
+        if config.detect_pk && self.unique {
+            let is_integer = matches!(inferred_type, SqlType::SmallInt | SqlType::Integer | SqlType::BigInt);
+            self.unique = is_integer && self.seen_values.get_or_insert_with(HashSet::new).insert(value.to_string());
+            if !self.unique {
+                self.seen_values = None;
+            }
+        }
+
+        self.widen(&inferred_type, config);
+    }
+
+    /// Widen the column's type to hold a value of the `inferred` type,
+    /// noting promotions and applying the integer cap
+    fn widen(&mut self, inferred: &SqlType, config: &InferenceConfig) {
+        if *inferred == SqlType::Null {
+            return;
+        }
+
+        let value_type = match &self.value_type {
+            Some(seen) => seen.merge(inferred),
+            None => inferred.clone(),
+        };
+        if self.value_type.as_ref() != Some(&value_type) {
+            self.widened_by.push(inferred.clone());
+            self.value_type = Some(value_type);
+        }
+
+        let merged = self.sql_type.merge(inferred);
+        if merged.exceeds(config.max_int_type) {
+            let warning = format!(
+                "integer widening to {} capped at {}; degraded to TEXT",
+                merged.to_sql(),
+                config.max_int_type.sql_type().to_sql()
+            );
+            if !self.warnings.contains(&warning) {
+                self.warnings.push(warning);
+            }
+            self.int_capped = true;
+            self.sql_type = SqlType::Text;
+            self.unique = false;
+            self.seen_values = None;
+            return;
+        }
+        if merged != self.sql_type && self.sql_type != SqlType::Null {
+            self.note_promotion(inferred, &merged);
         }
+        self.sql_type = merged;
+    }
 
-        self.sql_type = self.sql_type.merge(&inferred_type);
+    /// Combine two partial schemas of the same column inferred from
+    /// different rows
+    pub fn merge(&self, other: &ColumnSchema) -> ColumnSchema {
+        self.merge_with(other, &InferenceConfig::default())
+    }
+
+    /// Combine two partial schemas of the same column using inference
+    /// settings
+    ///
+    /// Both sides must be unfinalized. Counts add up, the longest value and
+    /// profile ranges widen, types widen as if `other`'s values followed
+    /// this side's, noting the same promotions, and uniqueness holds only if
+    /// no value was seen on both sides, so merging partials in order gives
+    /// what one pass over all the rows would.
+    pub fn merge_with(&self, other: &ColumnSchema, config: &InferenceConfig) -> ColumnSchema {
+        let mut merged = self.clone();
+        merged.merge_from(other, config);
+        merged
+    }
+
+    /// In-place [`ColumnSchema::merge_with`], which avoids copying the values
+    /// kept for uniqueness
+    pub fn merge_from(&mut self, other: &ColumnSchema, config: &InferenceConfig) {
+        debug_assert_eq!(self.name, other.name, "merging schemas of different columns");
+
+        self.sample_count += other.sample_count;
+        self.null_count += other.null_count;
+        self.max_length = self.max_length.max(other.max_length);
+
+        match (&mut self.profile, &other.profile) {
+            (Some(profile), Some(theirs)) => profile.merge(theirs),
+            (None, Some(theirs)) => self.profile = Some(theirs.clone()),
+            _ => {}
+        }
+
+        if config.detect_pk {
+            let theirs = other.seen_values.as_ref();
+            let seen = self.seen_values.get_or_insert_with(HashSet::new);
+            self.unique = self.unique
+                && other.unique
+                && theirs.is_none_or(|values| values.iter().all(|v| !seen.contains(v)));
+            if self.unique {
+                seen.extend(theirs.into_iter().flatten().cloned());
+            } else {
+                self.seen_values = None;
+            }
+        }
+
+        // Only values that widened `other` can widen this side
+        for inferred in &other.widened_by {
+            self.widen(inferred, config);
+        }
+    }
+
+    /// Record why a value widened the column's type, once per kind
+    fn note_promotion(&mut self, inferred: &SqlType, merged: &SqlType) {
+        let warning = match merged {
+            SqlType::Text => format!(
+                "mixed types degraded to TEXT ({} value in a {} column)",
+                inferred.to_sql(),
+                self.sql_type.to_sql()
+            ),
+            SqlType::BigInt => "integer overflow to BIGINT".to_string(),
+            SqlType::Integer => return,
+            other => format!("widened from {} to {}", self.sql_type.to_sql(), other.to_sql()),
+        };
+
+        if !self.warnings.contains(&warning) {
+            self.warnings.push(warning);
+        }
     }
 
     /// Finalize the schema after all samples
     pub fn finalize(&mut self) {
+        self.finalize_with(&InferenceConfig::default());
+    }
+
+    /// Finalize the schema after all samples using inference settings
+    pub fn finalize_with(&mut self, config: &InferenceConfig) {
         // If all values were null, default to TEXT
-        if self.sql_type == SqlType::Null {
-            self.sql_type = SqlType::Text;
+        match &mut self.sql_type {
+            SqlType::Null => self.sql_type = SqlType::Text,
+            SqlType::Array(element) if **element == SqlType::Null => **element = SqlType::Text,
+            _ => {}
+        }
+
+        // Bound text columns by the longest value seen, with headroom
+        if config.varchar && self.sql_type == SqlType::Text && self.max_length > 0 {
+            self.sql_type = SqlType::Varchar {
+                len: varchar_length(self.max_length, config.varchar_padding),
+            };
         }
 
         // Column is nullable if we saw any nulls
         self.nullable = self.null_count > 0;
+
+        self.unique = config.detect_pk && self.unique && self.sample_count > 0;
+        self.seen_values = None;
     }
 
     /// Get confidence score (0.0 to 1.0)
@@ -59,7 +267,7 @@ impl ColumnSchema {
 
         // TEXT type has lower confidence (could be anything)
         let type_confidence = match self.sql_type {
-            SqlType::Text => 0.6,
+            SqlType::Text | SqlType::Varchar { .. } => 0.6,
             SqlType::Null => 0.3,
             _ => 1.0,
         };
@@ -69,10 +277,12 @@ impl ColumnSchema {
 }
 
 /// Table schema
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TableSchema {
     pub table_name: String,
     pub columns: Vec<ColumnSchema>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub primary_key: Option<String>,
 }
 
 impl TableSchema {
@@ -85,11 +295,27 @@ impl TableSchema {
         Self {
             table_name,
             columns,
+            primary_key: None,
         }
     }
 
+    /// A schema of nullable TEXT columns, without any inference
+    pub fn all_text(table_name: String, column_names: Vec<String>) -> Self {
+        let mut schema = Self::new(table_name, column_names);
+        for column in &mut schema.columns {
+            column.sql_type = SqlType::Text;
+            column.unique = false;
+        }
+        schema
+    }
+
     /// Update all columns with a row of data
     pub fn update_row(&mut self, row: &[String]) -> Result<()> {
+        self.update_row_with(row, &InferenceConfig::default())
+    }
+
+    /// Update all columns with a row of data using inference settings
+    pub fn update_row_with(&mut self, row: &[String], config: &InferenceConfig) -> Result<()> {
         if row.len() != self.columns.len() {
             return Err(LoaderError::SchemaInferenceError(format!(
                 "Row has {} columns but schema expects {}",
@@ -99,39 +325,495 @@ impl TableSchema {
         }
 
         for (column, value) in self.columns.iter_mut().zip(row.iter()) {
-            column.update(value);
+            column.update_with(value, config);
         }
 
         Ok(())
     }
 
+    /// Update all columns with numbered rows, inferring parts of them on
+    /// separate threads and merging the results in order
+    ///
+    /// Gives the same schema as calling `update_row_with` on each row.
+    /// Errors carry the line of the offending row.
+    pub fn update_rows_with(&mut self, rows: &[(u64, Vec<String>)], config: &InferenceConfig) -> Result<()> {
+        let infer = |schema: &mut TableSchema, part: &[(u64, Vec<String>)]| {
+            part.iter().try_for_each(|(line, row)| {
+                schema.update_row_with(row, config).map_err(|e| e.at_line(*line))
+            })
+        };
+
+        if rows.len() <= INFERENCE_PART_ROWS {
+            return infer(self, rows);
+        }
+
+        let names: Vec<String> = self.columns.iter().map(|c| c.name.clone()).collect();
+        let partials: Vec<Result<TableSchema>> = rows
+            .par_chunks(INFERENCE_PART_ROWS)
+            .map(|part| {
+                let mut partial = TableSchema::new(self.table_name.clone(), names.clone());
+                infer(&mut partial, part).map(|_| partial)
+            })
+            .collect();
+
+        for partial in partials {
+            for (column, theirs) in self.columns.iter_mut().zip(&partial?.columns) {
+                column.merge_from(theirs, config);
+            }
+        }
+        Ok(())
+    }
+
     /// Finalize schema after all samples
     pub fn finalize(&mut self) {
+        self.finalize_with(&InferenceConfig::default());
+    }
+
+    /// Finalize schema after all samples using inference settings
+    pub fn finalize_with(&mut self, config: &InferenceConfig) {
+        for column in &mut self.columns {
+            column.finalize_with(config);
+        }
+
+        if config.detect_pk {
+            self.primary_key = self.columns
+                .iter()
+                .find(|c| c.unique && matches!(c.sql_type, SqlType::SmallInt | SqlType::Integer | SqlType::BigInt))
+                .map(|c| c.name.clone());
+        }
+    }
+
+    /// Combine with the schema of another file loaded into the same table
+    pub fn merge(&self, other: &TableSchema) -> Result<TableSchema> {
+        self.merge_with(other, false)
+    }
+
+    /// Combine with another schema, aligning columns by name
+    ///
+    /// Types are widened with `SqlType::merge`, ignoring columns with no
+    /// non-null samples, and nullability is ORed. Columns present in only
+    /// one schema become nullable, with new columns appended; with `strict`
+    /// they are an error instead. Uniqueness was only checked within each
+    /// schema, so the result has no primary key or unique columns.
+    pub fn merge_with(&self, other: &TableSchema, strict: bool) -> Result<TableSchema> {
+        if self.table_name != other.table_name {
+            return Err(LoaderError::SchemaInferenceError(format!(
+                "Cannot merge schemas of different tables: '{}' and '{}'",
+                self.table_name, other.table_name
+            )));
+        }
+
+        let same_columns = self.columns.len() == other.columns.len()
+            && self.columns.iter().all(|c| other.columns.iter().any(|o| o.name == c.name));
+        if strict && !same_columns {
+            return Err(LoaderError::SchemaInferenceError(format!(
+                "Column sets differ: ({}) and ({})",
+                self.column_names(),
+                other.column_names()
+            )));
+        }
+
+        // A column with no values says nothing about its type
+        let observed = |column: &ColumnSchema| {
+            if column.null_count == column.sample_count {
+                SqlType::Null
+            } else {
+                column.sql_type.clone()
+            }
+        };
+
+        let mut merged = self.clone();
+        for column in &mut merged.columns {
+            match other.columns.iter().find(|c| c.name == column.name) {
+                Some(theirs) => {
+                    column.sql_type = match observed(column).merge(&observed(theirs)) {
+                        SqlType::Null => SqlType::Text,
+                        sql_type => sql_type,
+                    };
+                    column.nullable |= theirs.nullable;
+                    column.sample_count += theirs.sample_count;
+                    column.null_count += theirs.null_count;
+                    column.max_length = column.max_length.max(theirs.max_length);
+                    if let (Some(profile), Some(other)) = (&mut column.profile, &theirs.profile) {
+                        profile.merge(other);
+                    }
+                    for warning in &theirs.warnings {
+                        if !column.warnings.contains(warning) {
+                            column.warnings.push(warning.clone());
+                        }
+                    }
+                }
+                None => column.nullable = true,
+            }
+        }
+
+        for theirs in &other.columns {
+            if !merged.columns.iter().any(|c| c.name == theirs.name) {
+                let mut column = theirs.clone();
+                column.nullable = true;
+                merged.columns.push(column);
+            }
+        }
+
+        // Key values may repeat across the two, like ids restarting per file
+        merged.forget_uniqueness();
+
+        Ok(merged)
+    }
+
+    /// Drop the detected primary key and every column's uniqueness, for
+    /// rows beyond the ones checked
+    pub fn forget_uniqueness(&mut self) {
+        self.primary_key = None;
+        for column in &mut self.columns {
+            column.unique = false;
+        }
+    }
+
+    /// Check that every column named in DDL options exists, either in the
+    /// schema or among the omitted columns
+    pub fn validate_ddl_options(&self, options: &DdlOptions) -> Result<()> {
+        for (name, _) in &options.defaults {
+            if !self.columns.iter().any(|c| &c.name == name) && !options.omitted_columns.contains(name) {
+                return Err(LoaderError::ConfigError(format!(
+                    "Unknown column '{}' in default (columns: {})",
+                    name,
+                    self.column_names()
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Use a named column as the primary key
+    pub fn set_primary_key(&mut self, name: &str) -> Result<()> {
+        if !self.columns.iter().any(|c| c.name == name) {
+            return Err(LoaderError::ConfigError(format!(
+                "Unknown primary key column '{}' (columns: {})",
+                name,
+                self.column_names()
+            )));
+        }
+
+        self.primary_key = Some(name.to_string());
+        Ok(())
+    }
+
+    /// Columns worth indexing after a load, by index, with the reason
+    ///
+    /// Names like `customer_id` or `customerId` look like foreign keys.
+    /// Profiled columns count as high cardinality when at least nine in ten
+    /// of their values are distinct, over [`INDEX_MIN_DISTINCT`] values. The
+    /// primary key is already indexed, and booleans, arrays and long text
+    /// are left out.
+    pub fn index_candidates(&self) -> Vec<(usize, &'static str)> {
+        self.columns
+            .iter()
+            .enumerate()
+            .filter_map(|(index, col)| {
+                if self.primary_key.as_deref() == Some(col.name.as_str())
+                    || matches!(col.sql_type, SqlType::Null | SqlType::Boolean | SqlType::Array(_))
+                {
+                    return None;
+                }
+                if looks_like_foreign_key(&col.name) {
+                    return Some((index, "looks like a foreign key"));
+                }
+
+                let profile = col.profile.as_ref()?;
+                let values = col.sample_count - col.null_count;
+                let distinct = profile.distinct_capped()
+                    || (profile.distinct() >= INDEX_MIN_DISTINCT && profile.distinct() * 10 >= values * 9);
+                (distinct && col.max_length <= INDEX_MAX_LENGTH).then_some((index, "high cardinality"))
+            })
+            .collect()
+    }
+
+    /// Columns whose inference confidence is below `min`
+    ///
+    /// Columns with no non-null samples are left out: they score 0.0 and
+    /// default to TEXT, but that reflects missing data rather than a messy
+    /// column.
+    pub fn low_confidence_columns(&self, min: f64) -> Vec<&ColumnSchema> {
+        self.columns
+            .iter()
+            .filter(|c| c.null_count < c.sample_count && c.confidence() < min)
+            .collect()
+    }
+
+    /// Replace the inferred type of named columns
+    pub fn apply_type_overrides(&mut self, overrides: &[(String, SqlType)]) -> Result<()> {
+        for (name, sql_type) in overrides {
+            let valid = self.column_names();
+            let column = self.columns
+                .iter_mut()
+                .find(|c| &c.name == name)
+                .ok_or_else(|| LoaderError::ConfigError(format!(
+                    "Unknown column '{}' in type override (columns: {})",
+                    name, valid
+                )))?;
+
+            column.sql_type = sql_type.clone();
+        }
+
+        Ok(())
+    }
+
+    /// Make every column nullable, or every column NOT NULL, whatever
+    /// inference decided
+    ///
+    /// Returns the columns that were nullable before, which will fail a
+    /// NOT NULL load if their nulls are real.
+    pub fn set_nullability(&mut self, nullable: bool) -> Vec<String> {
+        let mut had_nulls = Vec::new();
         for column in &mut self.columns {
-            column.finalize();
+            if column.nullable {
+                had_nulls.push(column.name.clone());
+            }
+            column.nullable = nullable;
+        }
+        had_nulls
+    }
+
+    /// Rewrite column names into safe unquoted Postgres identifiers
+    ///
+    /// Returns the `(original, sanitized)` pairs for every renamed column.
+    pub fn sanitize_column_names(&mut self) -> Vec<(String, String)> {
+        let mut renamed = Vec::new();
+        let mut seen = std::collections::HashSet::new();
+
+        for column in &mut self.columns {
+            let base = sanitize_identifier(&column.name);
+            let mut candidate = base.clone();
+            let mut suffix = 2;
+            while !seen.insert(candidate.clone()) {
+                candidate = format!("{}_{}", base, suffix);
+                suffix += 1;
+            }
+
+            if candidate != column.name {
+                renamed.push((std::mem::replace(&mut column.name, candidate.clone()), candidate));
+            }
+        }
+
+        renamed
+    }
+
+    /// Resolve a CSV-to-table column mapping against the target table
+    ///
+    /// Returns `(csv_index, table_column)` pairs in the table's column order.
+    /// CSV columns without an explicit mapping are matched by name; table
+    /// columns with no source are left out so they take their DEFAULT.
+    pub fn map_to_table_columns(
+        &self,
+        map: &[(String, String)],
+        table_columns: &[String],
+    ) -> Result<Vec<(usize, String)>> {
+        let csv_index = |name: &str| self.columns.iter().position(|c| c.name == name);
+
+        for (csv_col, table_col) in map {
+            if csv_index(csv_col).is_none() {
+                return Err(LoaderError::ConfigError(format!(
+                    "Unknown CSV column '{}' in mapping (columns: {})",
+                    csv_col,
+                    self.column_names()
+                )));
+            }
+            if !table_columns.contains(table_col) {
+                return Err(LoaderError::ConfigError(format!(
+                    "Table '{}' has no column '{}' (columns: {})",
+                    self.table_name,
+                    table_col,
+                    table_columns.join(", ")
+                )));
+            }
+            if map.iter().filter(|(_, t)| t == table_col).count() > 1 {
+                return Err(LoaderError::ConfigError(format!(
+                    "Table column '{}' is mapped more than once",
+                    table_col
+                )));
+            }
         }
+
+        let mut targets = Vec::new();
+        for table_col in table_columns {
+            let explicit = map.iter().find(|(_, t)| t == table_col).map(|(c, _)| c.as_str());
+            let by_name = csv_index(table_col)
+                .filter(|_| !map.iter().any(|(c, _)| c == table_col))
+                .map(|_| table_col.as_str());
+
+            if let Some(index) = explicit.or(by_name).and_then(csv_index) {
+                targets.push((index, table_col.clone()));
+            }
+        }
+
+        for (index, column) in self.columns.iter().enumerate() {
+            if !targets.iter().any(|(i, _)| *i == index) {
+                tracing::warn!("CSV column '{}' is not mapped to the table and will be skipped", column.name);
+            }
+        }
+
+        Ok(targets)
+    }
+
+    /// Load into an explicit list of table columns
+    ///
+    /// Without a mapping, CSV columns feed `targets` by position, so their
+    /// counts must match. With one, each target takes its mapped or
+    /// same-named CSV column and must get one. Table columns not listed
+    /// keep their DEFAULT.
+    pub fn target_table_columns(
+        &self,
+        targets: &[String],
+        map: &[(String, String)],
+        table_columns: &[String],
+    ) -> Result<Vec<(usize, String)>> {
+        for (i, target) in targets.iter().enumerate() {
+            if !table_columns.contains(target) {
+                return Err(LoaderError::ConfigError(format!(
+                    "Table '{}' has no column '{}' (columns: {})",
+                    self.table_name,
+                    target,
+                    table_columns.join(", ")
+                )));
+            }
+            if targets[..i].contains(target) {
+                return Err(LoaderError::ConfigError(format!(
+                    "Target column '{}' is listed more than once",
+                    target
+                )));
+            }
+        }
+
+        if map.is_empty() {
+            if targets.len() != self.columns.len() {
+                return Err(LoaderError::ConfigError(format!(
+                    "--target-columns lists {} columns but the CSV has {} ({}); use --map to pick them by name",
+                    targets.len(),
+                    self.columns.len(),
+                    self.column_names()
+                )));
+            }
+            return Ok(targets.iter().cloned().enumerate().collect());
+        }
+
+        let mapped = self.map_to_table_columns(map, targets)?;
+        if let Some(unfed) = targets.iter().find(|t| !mapped.iter().any(|(_, m)| m == *t)) {
+            return Err(LoaderError::ConfigError(format!(
+                "Target column '{}' has no CSV column; map one to it with --map",
+                unfed
+            )));
+        }
+        Ok(mapped)
+    }
+
+    /// Target every CSV column in the table's column order
+    ///
+    /// Fails if the table lacks any CSV column, rather than dropping it.
+    pub fn order_by_table_columns(&self, table_columns: &[String]) -> Result<Vec<(usize, String)>> {
+        if let Some(missing) = self.columns.iter().find(|c| !table_columns.contains(&c.name)) {
+            return Err(LoaderError::ConfigError(format!(
+                "Table '{}' has no column '{}' (columns: {})",
+                self.table_name,
+                missing.name,
+                table_columns.join(", ")
+            )));
+        }
+
+        Ok(table_columns
+            .iter()
+            .filter_map(|table_col| {
+                let index = self.columns.iter().position(|c| c.name == *table_col)?;
+                Some((index, table_col.clone()))
+            })
+            .collect())
+    }
+
+    /// Write the schema to a JSON file
+    pub fn to_json_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| LoaderError::SchemaFileError(e.to_string()))?;
+        fs::write(path, json + "\n")?;
+        Ok(())
+    }
+
+    /// Read a schema previously written by `to_json_file`
+    pub fn from_json_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path).map_err(|_| {
+            LoaderError::FileNotFound(path.display().to_string())
+        })?;
+
+        serde_json::from_str(&json).map_err(|e| {
+            LoaderError::SchemaFileError(format!("{}: {}", path.display(), e))
+        })
+    }
+
+    /// Check that the schema matches the CSV's column count
+    pub fn validate_headers(&self, headers: &[String]) -> Result<()> {
+        if headers.len() != self.columns.len() {
+            return Err(LoaderError::SchemaFileError(format!(
+                "Schema has {} columns but the CSV has {} ({})",
+                self.columns.len(),
+                headers.len(),
+                headers.join(", ")
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Write the CREATE TABLE statement to a file runnable with `psql -f`
+    pub fn to_ddl_file<P: AsRef<Path>>(&self, path: P, options: &DdlOptions) -> Result<()> {
+        fs::write(path, self.to_create_table_sql_with(options) + "\n")?;
+        Ok(())
     }
 
     /// Generate CREATE TABLE SQL statement
     pub fn to_create_table_sql(&self) -> String {
-        let mut sql = format!("CREATE TABLE {} (\n", self.table_name);
+        self.to_create_table_sql_with(&DdlOptions::default())
+    }
+
+    /// Generate CREATE TABLE SQL statement with DDL options
+    pub fn to_create_table_sql_with(&self, options: &DdlOptions) -> String {
+        let mut sql = format!("CREATE TABLE {} (\n", quote_table_name(&self.table_name));
 
         let column_defs: Vec<String> = self.columns
             .iter()
             .map(|col| {
                 let nullable = if col.nullable { "" } else { " NOT NULL" };
-                format!("  {} {}{}", col.name, col.sql_type.to_sql(), nullable)
+                let name = column_identifier(&col.name, options.quote_identifiers);
+                let default = options.defaults
+                    .iter()
+                    .find(|(column, _)| *column == col.name)
+                    .map(|(_, value)| format!(" DEFAULT {}", default_expression(value)))
+                    .unwrap_or_default();
+                format!("  {} {}{}{}", name, col.sql_type.to_sql(), default, nullable)
             })
+            .chain(options.omitted_columns.iter().filter_map(|column| {
+                let (_, value) = options.defaults.iter().find(|(name, _)| name == column)?;
+                Some(format!(
+                    "  {} TEXT DEFAULT {}",
+                    column_identifier(column, options.quote_identifiers),
+                    default_expression(value)
+                ))
+            }))
             .collect();
 
         sql.push_str(&column_defs.join(",\n"));
+        if let Some(pk) = &self.primary_key {
+            sql.push_str(&format!(
+                ",\n  PRIMARY KEY ({})",
+                column_identifier(pk, options.quote_identifiers)
+            ));
+        }
         sql.push_str("\n);");
 
         sql
     }
 
     /// Get column names as comma-separated string
-    #[allow(dead_code)]
     pub fn column_names(&self) -> String {
         self.columns
             .iter()
@@ -141,43 +823,307 @@ impl TableSchema {
     }
 
     /// Validate table name (basic SQL injection prevention)
+    ///
+    /// Accepts an optional schema prefix (`schema.table`); each part is
+    /// validated independently.
     pub fn validate_table_name(name: &str) -> Result<()> {
+        let parts: Vec<&str> = name.split('.').collect();
+        if parts.len() > 2 {
+            return Err(LoaderError::InvalidTableName(
+                format!("Table name has too many parts (expected schema.table): {}", name)
+            ));
+        }
+
+        let (schema, table) = split_table_name(name);
+        if let Some(schema) = schema {
+            Self::validate_identifier(schema, "Schema")?;
+        }
+        Self::validate_identifier(table, "Table")?;
+
+        // Reject SQL keywords (basic protection)
+        let keywords = ["SELECT", "INSERT", "UPDATE", "DELETE", "DROP", "CREATE", "ALTER", "EXEC"];
+        if keywords.iter().any(|k| table.eq_ignore_ascii_case(k)) {
+            return Err(LoaderError::InvalidTableName(
+                format!("Table name cannot be SQL keyword: {}", table)
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Validate a column name given by the user, e.g. with `--rename`
+    pub fn validate_column_name(name: &str) -> Result<()> {
+        Self::validate_identifier(name, "Column")
+    }
+
+    /// Validate a single unqualified identifier
+    fn validate_identifier(name: &str, kind: &str) -> Result<()> {
         if name.is_empty() {
-            return Err(LoaderError::InvalidTableName("Table name cannot be empty".to_string()));
+            return Err(LoaderError::InvalidTableName(format!("{} name cannot be empty", kind)));
         }
 
         // Must start with letter or underscore
         if !name.chars().next().unwrap().is_alphabetic() && !name.starts_with('_') {
             return Err(LoaderError::InvalidTableName(
-                format!("Table name must start with letter or underscore: {}", name)
+                format!("{} name must start with letter or underscore: {}", kind, name)
             ));
         }
 
         // Only alphanumeric and underscore allowed
         if !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
             return Err(LoaderError::InvalidTableName(
-                format!("Table name contains invalid characters: {}", name)
+                format!("{} name contains invalid characters: {}", kind, name)
             ));
         }
 
-        // Reject SQL keywords (basic protection)
-        let keywords = ["SELECT", "INSERT", "UPDATE", "DELETE", "DROP", "CREATE", "ALTER", "EXEC"];
-        if keywords.iter().any(|k| name.eq_ignore_ascii_case(k)) {
-            return Err(LoaderError::InvalidTableName(
-                format!("Table name cannot be SQL keyword: {}", name)
-            ));
+        Ok(())
+    }
+}
+
+/// Options for generated DDL
+#[derive(Debug, Clone)]
+pub struct DdlOptions {
+    /// Double-quote column names so reserved words and mixed case work
+    pub quote_identifiers: bool,
+    /// `(column, value)` pairs rendered as `DEFAULT` clauses
+    pub defaults: Vec<(String, String)>,
+    /// CSV columns left out of the load but still created, as nullable
+    /// `TEXT` holding their default
+    pub omitted_columns: Vec<String>,
+}
+
+impl Default for DdlOptions {
+    fn default() -> Self {
+        Self {
+            quote_identifiers: true,
+            defaults: Vec::new(),
+            omitted_columns: Vec::new(),
         }
+    }
+}
 
-        Ok(())
+/// Render a `--default` value as a SQL expression
+///
+/// Numeric literals, `true`/`false`/`NULL`, already-quoted literals,
+/// `CURRENT_*` keywords and function calls such as `now()` or
+/// `pg_catalog.gen_random_uuid()` pass through verbatim; anything else,
+/// e.g. `Acme (UK)` or `nan`, becomes a quoted string literal.
+pub fn default_expression(value: &str) -> String {
+    let value = value.trim();
+    let upper = value.to_ascii_uppercase();
+    let function_call = value.ends_with(')')
+        && !value.contains(';')
+        && value
+            .split_once('(')
+            .is_some_and(|(name, _)| name.split('.').all(is_plain_identifier));
+
+    let verbatim = (value.len() >= 2 && value.starts_with('\'') && value.ends_with('\''))
+        || is_numeric_literal(value)
+        || matches!(upper.as_str(), "TRUE" | "FALSE" | "NULL" | "LOCALTIME" | "LOCALTIMESTAMP")
+        || (upper.starts_with("CURRENT_") && is_plain_identifier(value))
+        || function_call;
+
+    if verbatim {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', "''"))
+    }
+}
+
+/// Whether `value` is a letter or underscore followed by letters, digits
+/// and underscores
+fn is_plain_identifier(value: &str) -> bool {
+    value.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Whether `value` is a SQL numeric literal such as `42`, `-1.5` or `1e6`
+///
+/// Unlike Rust's float parsing, `nan` and `inf` don't count.
+fn is_numeric_literal(value: &str) -> bool {
+    let unsigned = value.strip_prefix(['+', '-']).unwrap_or(value);
+    let (mantissa, exponent) = match unsigned.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, Some(exponent)),
+        None => (unsigned, None),
+    };
+    let mantissa_ok = mantissa.chars().any(|c| c.is_ascii_digit())
+        && mantissa.chars().all(|c| c.is_ascii_digit() || c == '.')
+        && mantissa.matches('.').count() <= 1;
+    let exponent_ok = exponent.is_none_or(|exponent| {
+        let digits = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+        !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit())
+    });
+
+    mantissa_ok && exponent_ok
+}
+
+/// Double-quote an identifier, escaping embedded quotes by doubling them
+pub fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Split `schema.table` into its optional schema and table parts
+pub fn split_table_name(name: &str) -> (Option<&str>, &str) {
+    match name.split_once('.') {
+        Some((schema, table)) => (Some(schema), table),
+        None => (None, name),
+    }
+}
+
+/// Quote a possibly schema-qualified table name, e.g. `"analytics"."events"`
+pub fn quote_table_name(name: &str) -> String {
+    match split_table_name(name) {
+        (Some(schema), table) => format!("{}.{}", quote_identifier(schema), quote_identifier(table)),
+        (None, table) => quote_identifier(table),
+    }
+}
+
+/// Generated names of the indexes on `columns`, e.g. `events_user_id_idx`
+///
+/// Built from the sanitized table and column names and kept within
+/// PostgreSQL's 63-byte identifier limit. A column whose name comes out
+/// the same as an earlier one's gets a numbered suffix, e.g.
+/// `events_user_id_2_idx`.
+pub fn index_names(table_name: &str, columns: &[String]) -> Vec<String> {
+    let (_, table) = split_table_name(table_name);
+    let mut names: Vec<String> = Vec::with_capacity(columns.len());
+    for column in columns {
+        let base = format!("{}_{}", sanitize_identifier(table), sanitize_identifier(column));
+        let mut name = index_name(&base, "");
+        let mut suffix = 2;
+        while names.contains(&name) {
+            name = index_name(&base, &format!("_{}", suffix));
+            suffix += 1;
+        }
+        names.push(name);
+    }
+    names
+}
+
+/// `base`, cut to leave room for `suffix`, then `suffix` and `_idx`
+fn index_name(base: &str, suffix: &str) -> String {
+    let mut name = base.to_string();
+    name.truncate(59 - suffix.len());
+    format!("{}{}_idx", name.trim_end_matches('_'), suffix)
+}
+
+/// `CREATE INDEX` named `index_name` on one column, skipped if an index
+/// of that name exists
+pub fn create_index_sql(table_name: &str, index_name: &str, column: &str, quote: bool) -> String {
+    format!(
+        "CREATE INDEX IF NOT EXISTS {} ON {} ({})",
+        quote_identifier(index_name),
+        quote_table_name(table_name),
+        column_identifier(column, quote)
+    )
+}
+
+fn looks_like_foreign_key(name: &str) -> bool {
+    name.len() > 3 && (name.to_ascii_lowercase().ends_with("_id") || name.ends_with("Id"))
+}
+
+/// Render a column name, quoted unless quoting is disabled
+pub fn column_identifier(name: &str, quote: bool) -> String {
+    if quote {
+        quote_identifier(name)
+    } else {
+        name.to_string()
+    }
+}
+
+/// Lowercase a name and collapse runs of non-alphanumeric characters to `_`
+///
+/// Names starting with a digit get a leading `_`; names with no usable
+/// characters become `column`.
+pub fn sanitize_identifier(name: &str) -> String {
+    let mut sanitized = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            sanitized.push(c.to_ascii_lowercase());
+        } else if !sanitized.ends_with('_') {
+            sanitized.push('_');
+        }
+    }
+
+    let sanitized = sanitized.trim_matches('_');
+    if sanitized.is_empty() {
+        "column".to_string()
+    } else if sanitized.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{}", sanitized)
+    } else {
+        sanitized.to_string()
     }
 }
 
 /// Schema inference configuration
 #[derive(Debug, Clone)]
-#[allow(dead_code)]
 pub struct InferenceConfig {
+    /// Rows to sample; 0 means every row
     pub sample_size: usize,
     pub has_headers: bool,
+    /// Extra values treated as NULL in addition to empty, `null` and `\N`
+    pub null_tokens: Vec<String>,
+    /// Infer zero-padded digit strings like `00123` as TEXT
+    pub keep_leading_zeros: bool,
+    /// Extra chrono formats tried after the built-in date formats
+    pub date_formats: Vec<String>,
+    /// Extra chrono formats tried after the built-in timestamp formats
+    pub timestamp_formats: Vec<String>,
+    /// Decimal separator for numeric values
+    pub decimal_separator: char,
+    /// Optional digit grouping separator for numeric values
+    pub thousands_separator: Option<char>,
+    /// Values recognized as boolean true (case-insensitive)
+    pub bool_true: Vec<String>,
+    /// Values recognized as boolean false (case-insensitive)
+    pub bool_false: Vec<String>,
+    /// Which rows make up the inference sample
+    pub sample_strategy: SampleStrategy,
+    /// Emit VARCHAR(n) instead of TEXT, sized from the longest value
+    pub varchar: bool,
+    /// Track distinct values so a unique integer column can become the
+    /// primary key
+    pub detect_pk: bool,
+    /// Multiplier applied to the longest value before rounding up to a
+    /// power of two
+    pub varchar_padding: f64,
+    /// Keep the head sample in memory so loading replays it rather than
+    /// re-reading it from the file
+    pub buffer_sample: bool,
+    /// Infer IP addresses as INET and networks as CIDR
+    pub inet: bool,
+    /// Infer `0x`-prefixed hexadecimal values as integers
+    pub hex: bool,
+    /// Treat `inf`/`NaN` tokens as TEXT instead of floating point
+    pub reject_nonfinite: bool,
+    /// Infer `$1,234.50` as NUMERIC and `45%` as a number
+    pub strip_symbols: bool,
+    /// Divide stripped percentages by 100
+    pub percent_as_fraction: bool,
+    /// Widest integer type a column may widen to before degrading to TEXT
+    pub max_int_type: IntegerCap,
+    /// Track min/max and distinct counts for each column
+    pub profile: bool,
+}
+
+/// VARCHAR length for a column whose longest value has `max_length`
+/// characters: padded, then rounded up to the next power of two
+fn varchar_length(max_length: usize, padding: f64) -> u32 {
+    let padded = (max_length as f64 * padding.max(1.0)).ceil() as u32;
+    padded.max(1).checked_next_power_of_two().unwrap_or(u32::MAX)
+}
+
+/// How rows are chosen for type inference
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum SampleStrategy {
+    /// The first `sample_size` rows
+    #[default]
+    Head,
+    /// A uniform random sample of `sample_size` rows from the whole file
+    Reservoir,
+    /// A third of `sample_size` rows each from the head, middle and tail of
+    /// the file, seeking rather than reading through it
+    Spread,
 }
 
 impl Default for InferenceConfig {
@@ -185,6 +1131,26 @@ impl Default for InferenceConfig {
         Self {
             sample_size: 1000,
             has_headers: true,
+            null_tokens: Vec::new(),
+            keep_leading_zeros: true,
+            date_formats: Vec::new(),
+            timestamp_formats: Vec::new(),
+            decimal_separator: '.',
+            thousands_separator: None,
+            bool_true: BOOL_TRUE_TOKENS.iter().map(|t| t.to_string()).collect(),
+            bool_false: BOOL_FALSE_TOKENS.iter().map(|t| t.to_string()).collect(),
+            sample_strategy: SampleStrategy::Head,
+            varchar: false,
+            detect_pk: false,
+            varchar_padding: 1.0,
+            buffer_sample: false,
+            inet: true,
+            hex: true,
+            reject_nonfinite: false,
+            strip_symbols: false,
+            percent_as_fraction: false,
+            max_int_type: IntegerCap::BigInt,
+            profile: false,
         }
     }
 }
@@ -194,6 +1160,7 @@ impl InferenceConfig {
         Self {
             sample_size,
             has_headers,
+            ..Default::default()
         }
     }
 }
@@ -228,6 +1195,24 @@ mod tests {
         assert_eq!(col.null_count, 1);
     }
 
+    #[test]
+    fn test_column_schema_null_tokens() {
+        let config = InferenceConfig {
+            null_tokens: vec!["NA".to_string()],
+            ..Default::default()
+        };
+        let mut col = ColumnSchema::new("score".to_string());
+
+        col.update_with("10", &config);
+        col.update_with("NA", &config);
+        col.update_with("12", &config);
+        col.finalize();
+
+        assert_eq!(col.sql_type, SqlType::SmallInt);
+        assert_eq!(col.null_count, 1);
+        assert!(col.nullable);
+    }
+
     #[test]
     fn test_table_schema_create_sql() {
         let mut schema = TableSchema::new(
@@ -241,10 +1226,652 @@ mod tests {
         schema.finalize();
 
         let sql = schema.to_create_table_sql();
-        assert!(sql.contains("CREATE TABLE users"));
-        assert!(sql.contains("id SMALLINT NOT NULL"));
-        assert!(sql.contains("name TEXT NOT NULL"));
-        assert!(sql.contains("age SMALLINT NOT NULL"));
+        assert!(sql.contains("CREATE TABLE \"users\""));
+        assert!(sql.contains("\"id\" SMALLINT NOT NULL"));
+        assert!(sql.contains("\"name\" TEXT NOT NULL"));
+        assert!(sql.contains("\"age\" SMALLINT NOT NULL"));
+    }
+
+    #[test]
+    fn test_apply_type_overrides() {
+        let mut schema = TableSchema::new(
+            "places".to_string(),
+            vec!["zip".to_string(), "name".to_string()],
+        );
+        schema.update_row(&["02134".to_string(), "Boston".to_string()]).unwrap();
+        schema.finalize();
+
+        schema.apply_type_overrides(&[("zip".to_string(), SqlType::Text)]).unwrap();
+        assert_eq!(schema.columns[0].sql_type, SqlType::Text);
+        assert!(schema.to_create_table_sql().contains("\"zip\" TEXT NOT NULL"));
+
+        let err = schema
+            .apply_type_overrides(&[("zipcode".to_string(), SqlType::Text)])
+            .unwrap_err();
+        assert!(err.to_string().contains("zip, name"));
+    }
+
+    #[test]
+    fn test_sanitize_identifier() {
+        assert_eq!(sanitize_identifier("First Name"), "first_name");
+        assert_eq!(sanitize_identifier("amount ($)"), "amount");
+        assert_eq!(sanitize_identifier("2024-total"), "_2024_total");
+        assert_eq!(sanitize_identifier("already_ok"), "already_ok");
+        assert_eq!(sanitize_identifier("%%%"), "column");
+    }
+
+    #[test]
+    fn test_sanitize_column_names_dedup() {
+        let mut schema = TableSchema::new(
+            "t".to_string(),
+            vec!["First Name".to_string(), "first-name".to_string(), "id".to_string(), "FIRST NAME".to_string()],
+        );
+
+        let renamed = schema.sanitize_column_names();
+
+        assert_eq!(schema.column_names(), "first_name, first_name_2, id, first_name_3");
+        assert_eq!(renamed.len(), 3);
+        assert_eq!(renamed[0], ("First Name".to_string(), "first_name".to_string()));
+        assert!(schema.to_create_table_sql().contains("\"first_name_2\" TEXT"));
+    }
+
+    #[test]
+    fn test_low_confidence_columns() {
+        let mut schema = TableSchema::new(
+            "t".to_string(),
+            vec!["id".to_string(), "mixed".to_string(), "empty".to_string()],
+        );
+        for row in [["1", "5", ""], ["2", "abc", ""]] {
+            let row: Vec<String> = row.iter().map(|v| v.to_string()).collect();
+            schema.update_row(&row).unwrap();
+        }
+        schema.finalize();
+
+        let low: Vec<&str> = schema.low_confidence_columns(0.7).iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(low, vec!["mixed"]);
+        assert!(schema.low_confidence_columns(0.5).is_empty());
+    }
+
+    #[test]
+    fn test_varchar_inference() {
+        let config = InferenceConfig { varchar: true, ..Default::default() };
+
+        let mut col = ColumnSchema::new("city".to_string());
+        for value in ["Oslo", "Zürich", "", "São Paulo"] {
+            col.update_with(value, &config);
+        }
+        col.finalize_with(&config);
+
+        // 9 characters, though "São Paulo" is 10 bytes
+        assert_eq!(col.max_length, 9);
+        assert_eq!(col.sql_type, SqlType::Varchar { len: 16 });
+
+        let padded = InferenceConfig { varchar_padding: 2.0, ..config.clone() };
+        let mut col = ColumnSchema::new("code".to_string());
+        col.update_with("ABCDEFGH", &padded);
+        col.finalize_with(&padded);
+        assert_eq!(col.sql_type, SqlType::Varchar { len: 16 });
+
+        // Off by default
+        let mut col = ColumnSchema::new("city".to_string());
+        col.update("Oslo");
+        col.finalize();
+        assert_eq!(col.sql_type, SqlType::Text);
+    }
+
+    #[test]
+    fn test_detect_primary_key() {
+        let config = InferenceConfig { detect_pk: true, ..Default::default() };
+        let mut schema = TableSchema::new(
+            "t".to_string(),
+            vec!["code".to_string(), "group_id".to_string(), "id".to_string()],
+        );
+        for row in [["a", "1", "10"], ["b", "1", "11"], ["c", "2", "12"]] {
+            let row: Vec<String> = row.iter().map(|v| v.to_string()).collect();
+            schema.update_row_with(&row, &config).unwrap();
+        }
+        schema.finalize_with(&config);
+
+        // `code` is distinct but not an integer; `group_id` repeats
+        assert!(!schema.columns[0].unique);
+        assert!(!schema.columns[1].unique);
+        assert_eq!(schema.primary_key.as_deref(), Some("id"));
+        assert!(schema.to_create_table_sql().ends_with(",\n  PRIMARY KEY (\"id\")\n);"));
+
+        // Values are only kept while a column could still be the key
+        assert!(schema.columns.iter().all(|c| c.seen_values.is_none()));
+        let mut column = ColumnSchema::new("id".to_string());
+        column.update_with("1", &config);
+        assert!(column.seen_values.is_some());
+        column.update_with("x", &config);
+        assert!(column.seen_values.is_none());
+        column.update_with("2", &config);
+        assert!(!column.unique && column.seen_values.is_none());
+
+        assert!(schema.set_primary_key("nope").is_err());
+        schema.set_primary_key("group_id").unwrap();
+        assert_eq!(schema.primary_key.as_deref(), Some("group_id"));
+    }
+
+    #[test]
+    fn test_detect_primary_key_with_null() {
+        let config = InferenceConfig { detect_pk: true, ..Default::default() };
+        let mut schema = TableSchema::new("t".to_string(), vec!["id".to_string()]);
+        for value in ["1", "", "3"] {
+            schema.update_row_with(&[value.to_string()], &config).unwrap();
+        }
+        schema.finalize_with(&config);
+
+        assert_eq!(schema.primary_key, None);
+    }
+
+    #[test]
+    fn test_index_candidates() {
+        let config = InferenceConfig { detect_pk: true, profile: true, ..Default::default() };
+        let names = ["id", "customerId", "email", "country", "active", "note"];
+        let mut schema = TableSchema::new("t".to_string(), names.iter().map(|n| n.to_string()).collect());
+        for i in 0..200 {
+            let row = vec![
+                i.to_string(),
+                (i % 7).to_string(),
+                format!("user{}@example.com", i),
+                ["NO", "SE", "DK"][i % 3].to_string(),
+                (i % 2 == 0).to_string(),
+                format!("{} {}", "long free text".repeat(10), i),
+            ];
+            schema.update_row_with(&row, &config).unwrap();
+        }
+        schema.finalize_with(&config);
+
+        // `id` is the primary key, `country` repeats, `note` is long text
+        let candidates: Vec<(&str, &str)> = schema
+            .index_candidates()
+            .into_iter()
+            .map(|(index, reason)| (schema.columns[index].name.as_str(), reason))
+            .collect();
+        assert_eq!(
+            candidates,
+            vec![("customerId", "looks like a foreign key"), ("email", "high cardinality")]
+        );
+
+        // Without a profile only names count
+        let mut plain = schema.clone();
+        plain.columns.iter_mut().for_each(|c| c.profile = None);
+        assert_eq!(plain.index_candidates(), vec![(1, "looks like a foreign key")]);
+    }
+
+    #[test]
+    fn test_index_names() {
+        let names = |table: &str, columns: &[&str]| {
+            index_names(table, &columns.iter().map(|c| c.to_string()).collect::<Vec<_>>())
+        };
+
+        assert_eq!(names("analytics.events", &["User ID"]), vec!["events_user_id_idx"]);
+        assert_eq!(
+            names("t", &["user_id", "User ID", "user-id"]),
+            vec!["t_user_id_idx", "t_user_id_2_idx", "t_user_id_3_idx"]
+        );
+
+        // Names cut to the length limit stay distinct
+        let long = names("t", &[&format!("{}a", "c".repeat(100)), &format!("{}b", "c".repeat(100))]);
+        assert_eq!(long[0].len(), 63);
+        assert_eq!(long[1].len(), 63);
+        assert!(long[1].ends_with("_2_idx"));
+
+        assert_eq!(
+            create_index_sql("analytics.events", "events_user_id_idx", "user_id", true),
+            "CREATE INDEX IF NOT EXISTS \"events_user_id_idx\" ON \"analytics\".\"events\" (\"user_id\")"
+        );
+    }
+
+    #[test]
+    fn test_column_defaults() {
+        let mut schema = TableSchema::new(
+            "t".to_string(),
+            vec!["status".to_string(), "created_at".to_string(), "n".to_string()],
+        );
+        schema.update_row(&["active".to_string(), "2024-01-01 00:00:00".to_string(), "1".to_string()]).unwrap();
+        schema.finalize();
+
+        let options = DdlOptions {
+            defaults: vec![
+                ("status".to_string(), "it's new".to_string()),
+                ("created_at".to_string(), "now()".to_string()),
+                ("n".to_string(), "0".to_string()),
+            ],
+            ..Default::default()
+        };
+        schema.validate_ddl_options(&options).unwrap();
+
+        let sql = schema.to_create_table_sql_with(&options);
+        assert!(sql.contains("\"status\" TEXT DEFAULT 'it''s new' NOT NULL"));
+        assert!(sql.contains("\"created_at\" TIMESTAMP DEFAULT now() NOT NULL"));
+        assert!(sql.contains("\"n\" SMALLINT DEFAULT 0 NOT NULL"));
+
+        let unknown = DdlOptions { defaults: vec![("x".to_string(), "1".to_string())], ..Default::default() };
+        assert!(schema.validate_ddl_options(&unknown).is_err());
+
+        // A column left out of the load is still created with its default
+        let omitted = DdlOptions {
+            defaults: vec![("x".to_string(), "pending".to_string())],
+            omitted_columns: vec!["x".to_string()],
+            ..Default::default()
+        };
+        schema.validate_ddl_options(&omitted).unwrap();
+        let sql = schema.to_create_table_sql_with(&omitted);
+        assert!(sql.contains("\"n\" SMALLINT NOT NULL,\n  \"x\" TEXT DEFAULT 'pending'\n);"));
+    }
+
+    #[test]
+    fn test_default_expression() {
+        assert_eq!(default_expression("'active'"), "'active'");
+        assert_eq!(default_expression("active"), "'active'");
+        assert_eq!(default_expression("-1.5"), "-1.5");
+        assert_eq!(default_expression("true"), "true");
+        assert_eq!(default_expression("CURRENT_TIMESTAMP"), "CURRENT_TIMESTAMP");
+        assert_eq!(default_expression("gen_random_uuid()"), "gen_random_uuid()");
+        assert_eq!(default_expression("pg_catalog.now()"), "pg_catalog.now()");
+        assert_eq!(default_expression("1e6"), "1e6");
+        assert_eq!(default_expression("Acme (UK)"), "'Acme (UK)'");
+        assert_eq!(default_expression("nan"), "'nan'");
+        assert_eq!(default_expression("inf"), "'inf'");
+        assert_eq!(default_expression("1.2.3"), "'1.2.3'");
+        assert_eq!(default_expression("CURRENT_USER; DROP TABLE t"), "'CURRENT_USER; DROP TABLE t'");
+    }
+
+    #[test]
+    fn test_promotion_warnings() {
+        let mut column = ColumnSchema::new("c".to_string());
+        for value in ["1", "40000", "9999999999", "x", "y"] {
+            column.update(value);
+        }
+
+        assert_eq!(column.sql_type, SqlType::Text);
+        assert_eq!(
+            column.warnings,
+            vec![
+                "integer overflow to BIGINT",
+                "mixed types degraded to TEXT (TEXT value in a BIGINT column)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_max_int_type_cap() {
+        let config = InferenceConfig {
+            max_int_type: IntegerCap::Integer,
+            ..Default::default()
+        };
+        let mut column = ColumnSchema::new("c".to_string());
+        for value in ["1", "40000", "2147483648", "5"] {
+            column.update_with(value, &config);
+        }
+
+        assert_eq!(column.sql_type, SqlType::Text);
+        assert!(column.int_capped);
+        assert_eq!(column.warnings, vec!["integer widening to BIGINT capped at INTEGER; degraded to TEXT"]);
+
+        // Widening within the cap is untouched
+        let mut column = ColumnSchema::new("c".to_string());
+        column.update_with("40000", &config);
+        assert_eq!(column.sql_type, SqlType::Integer);
+        assert!(!column.int_capped);
+    }
+
+    #[test]
+    fn test_set_nullability() {
+        let mut schema = TableSchema::new("t".to_string(), vec!["id".to_string(), "note".to_string()]);
+        schema.update_row(&["1".to_string(), "".to_string()]).unwrap();
+        schema.finalize();
+        assert!(!schema.columns[0].nullable && schema.columns[1].nullable);
+
+        assert_eq!(schema.set_nullability(false), vec!["note"]);
+        assert!(schema.to_create_table_sql().contains("\"note\" TEXT NOT NULL"));
+
+        schema.set_nullability(true);
+        assert!(!schema.to_create_table_sql().contains("NOT NULL"));
+    }
+
+    #[test]
+    fn test_map_to_table_columns() {
+        let schema = TableSchema::new(
+            "people".to_string(),
+            vec!["full_name".to_string(), "id".to_string(), "junk".to_string()],
+        );
+        let table_columns = vec!["id".to_string(), "name".to_string(), "created_at".to_string()];
+        let map = vec![("full_name".to_string(), "name".to_string())];
+
+        let targets = schema.map_to_table_columns(&map, &table_columns).unwrap();
+
+        // Table order; `id` matched by name; `created_at` left to its DEFAULT
+        assert_eq!(targets, vec![(1, "id".to_string()), (0, "name".to_string())]);
+    }
+
+    #[test]
+    fn test_order_by_table_columns() {
+        let schema = TableSchema::new("t".to_string(), vec!["name".to_string(), "id".to_string()]);
+        let table_columns = vec!["id".to_string(), "created_at".to_string(), "name".to_string()];
+
+        let targets = schema.order_by_table_columns(&table_columns).unwrap();
+        assert_eq!(targets, vec![(1, "id".to_string()), (0, "name".to_string())]);
+
+        let err = schema.order_by_table_columns(&["id".to_string()]).unwrap_err();
+        assert!(err.to_string().contains("no column 'name'"), "{}", err);
+    }
+
+    #[test]
+    fn test_map_to_table_columns_errors() {
+        let schema = TableSchema::new("t".to_string(), vec!["a".to_string(), "b".to_string()]);
+        let table_columns = vec!["x".to_string()];
+
+        let unknown_csv = schema.map_to_table_columns(&[("c".to_string(), "x".to_string())], &table_columns);
+        assert!(unknown_csv.unwrap_err().to_string().contains("a, b"));
+
+        let unknown_table = schema.map_to_table_columns(&[("a".to_string(), "y".to_string())], &table_columns);
+        assert!(unknown_table.unwrap_err().to_string().contains("no column 'y'"));
+
+        let twice = [("a".to_string(), "x".to_string()), ("b".to_string(), "x".to_string())];
+        assert!(schema.map_to_table_columns(&twice, &table_columns).is_err());
+    }
+
+    #[test]
+    fn test_all_text_schema() {
+        let schema = TableSchema::all_text("t".to_string(), vec!["id".to_string(), "amount".to_string()]);
+        assert!(schema.columns.iter().all(|c| c.sql_type == SqlType::Text && c.nullable));
+        let sql = schema.to_create_table_sql();
+        assert!(sql.contains("\"id\" TEXT"));
+        assert!(sql.contains("\"amount\" TEXT"));
+        assert!(!sql.contains("NOT NULL"));
+    }
+
+    #[test]
+    fn test_schema_json_round_trip() {
+        let mut schema = TableSchema::new(
+            "events".to_string(),
+            vec!["id".to_string(), "happened_at".to_string(), "note".to_string()],
+        );
+        schema.update_row(&["1".to_string(), "2024-01-15 10:30:00".to_string(), "".to_string()]).unwrap();
+        schema.update_row(&["70000".to_string(), "2024-01-16 11:00:00".to_string(), "hi".to_string()]).unwrap();
+        schema.finalize();
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        schema.to_json_file(file.path()).unwrap();
+        let loaded = TableSchema::from_json_file(file.path()).unwrap();
+
+        assert_eq!(loaded.to_create_table_sql(), schema.to_create_table_sql());
+    }
+
+    #[test]
+    fn test_ddl_file() {
+        let mut schema = TableSchema::new("events".to_string(), vec!["id".to_string()]);
+        schema.update_row(&["1".to_string()]).unwrap();
+        schema.finalize();
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        schema.to_ddl_file(file.path(), &DdlOptions::default()).unwrap();
+        let ddl = std::fs::read_to_string(file.path()).unwrap();
+
+        assert_eq!(ddl, schema.to_create_table_sql() + "\n");
+        assert!(ddl.ends_with(");\n"));
+    }
+
+    #[test]
+    fn test_schema_json_minimal_columns() {
+        let json = r#"{"table_name":"t","columns":[{"name":"id","sql_type":"Integer","nullable":false}]}"#;
+        let schema: TableSchema = serde_json::from_str(json).unwrap();
+
+        assert_eq!(schema.columns[0].sql_type, SqlType::Integer);
+        assert!(schema.validate_headers(&["id".to_string()]).is_ok());
+        assert!(schema.validate_headers(&["id".to_string(), "x".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_create_sql_quotes_reserved_words() {
+        let mut schema = TableSchema::new(
+            "orders".to_string(),
+            vec!["order".to_string(), "say \"hi\"".to_string()],
+        );
+        schema.update_row(&["1".to_string(), "x".to_string()]).unwrap();
+        schema.finalize();
+
+        let sql = schema.to_create_table_sql();
+        assert!(sql.contains("  \"order\" SMALLINT NOT NULL"));
+        assert!(sql.contains("  \"say \"\"hi\"\"\" TEXT NOT NULL"));
+
+        let unquoted = schema.to_create_table_sql_with(&DdlOptions { quote_identifiers: false, ..Default::default() });
+        assert!(unquoted.contains("  order SMALLINT NOT NULL"));
+    }
+
+    fn inferred(names: &[&str], rows: &[&[&str]]) -> TableSchema {
+        let mut schema = TableSchema::new("t".to_string(), names.iter().map(|n| n.to_string()).collect());
+        for row in rows {
+            schema.update_row(&row.iter().map(|v| v.to_string()).collect::<Vec<_>>()).unwrap();
+        }
+        schema.finalize();
+        schema
+    }
+
+    #[test]
+    fn test_merge_schemas() {
+        let left = inferred(&["id", "note"], &[&["1", ""], &["2", ""]]);
+        let right = inferred(&["id", "note", "extra"], &[&["3000000000", "true", "x"]]);
+
+        let merged = left.merge(&right).unwrap();
+        let names: Vec<&str> = merged.columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["id", "note", "extra"]);
+        assert_eq!(merged.columns[0].sql_type, SqlType::BigInt);
+        assert!(!merged.columns[0].nullable);
+        assert_eq!(merged.columns[0].sample_count, 3);
+        // The empty column doesn't widen the other file's type
+        assert_eq!(merged.columns[1].sql_type, SqlType::Boolean);
+        assert!(merged.columns[1].nullable);
+        assert!(merged.columns[2].nullable);
+
+        let right_only = inferred(&["id"], &[&["1"]]).merge(&right).unwrap();
+        assert!(right_only.columns.iter().skip(1).all(|c| c.nullable));
+    }
+
+    #[test]
+    fn test_merge_schemas_drops_detected_key() {
+        let config = InferenceConfig { detect_pk: true, ..Default::default() };
+        let daily = |rows: &[&str]| {
+            let mut schema = TableSchema::new("t".to_string(), vec!["id".to_string()]);
+            for row in rows {
+                schema.update_row_with(&[row.to_string()], &config).unwrap();
+            }
+            schema.finalize_with(&config);
+            schema
+        };
+        let monday = daily(&["1", "2", "3"]);
+        let tuesday = daily(&["1", "2"]);
+        assert_eq!(monday.primary_key.as_deref(), Some("id"));
+        assert_eq!(tuesday.primary_key.as_deref(), Some("id"));
+
+        // Each file's ids are unique, but together they repeat
+        let merged = monday.merge(&tuesday).unwrap();
+        assert_eq!(merged.primary_key, None);
+        assert!(!merged.columns[0].unique);
+        assert!(!merged.to_create_table_sql().contains("PRIMARY KEY"));
+    }
+
+    fn column(values: &[&str]) -> ColumnSchema {
+        let mut column = ColumnSchema::new("c".to_string());
+        for value in values {
+            column.update(value);
+        }
+        column
+    }
+
+    #[test]
+    fn test_column_merge() {
+        let cases: &[(&[&str], &[&str], SqlType)] = &[
+            (&["", ""], &["1", "2"], SqlType::SmallInt),
+            (&["1", "2"], &["", ""], SqlType::SmallInt),
+            (&["", ""], &["", ""], SqlType::Null),
+            (&["1"], &["2.5"], SqlType::DoublePrecision),
+            (&["1.5"], &["70000"], SqlType::DoublePrecision),
+            (&["1"], &["70000"], SqlType::Integer),
+            (&["1"], &["3000000000"], SqlType::BigInt),
+            (&["2.5"], &["true"], SqlType::Text),
+            (&[], &["2024-01-15"], SqlType::Date),
+        ];
+
+        for (left, right, expected) in cases {
+            let (a, b) = (column(left), column(right));
+            let merged = a.merge(&b);
+            assert_eq!(&merged.sql_type, expected, "{:?} + {:?}", left, right);
+            assert_eq!(b.merge(&a).sql_type, merged.sql_type, "{:?} + {:?} flipped", left, right);
+
+            // Same as seeing every value in one column
+            let all: Vec<&str> = left.iter().chain(right.iter()).copied().collect();
+            let single = column(&all);
+            assert_eq!(merged.sql_type, single.sql_type);
+            assert_eq!((merged.sample_count, merged.null_count), (single.sample_count, single.null_count));
+            assert_eq!(merged.max_length, single.max_length);
+        }
+
+        // Nullability follows once finalized
+        let mut merged = column(&["", ""]).merge(&column(&["7"]));
+        merged.finalize();
+        assert_eq!(merged.sql_type, SqlType::SmallInt);
+        assert!(merged.nullable);
+        assert_eq!((merged.sample_count, merged.null_count, merged.max_length), (3, 2, 1));
+    }
+
+    #[test]
+    fn test_column_merge_capped_and_unique() {
+        let config = InferenceConfig {
+            max_int_type: IntegerCap::Integer,
+            detect_pk: true,
+            ..Default::default()
+        };
+        let column = |values: &[&str]| {
+            let mut column = ColumnSchema::new("c".to_string());
+            for value in values {
+                column.update_with(value, &config);
+            }
+            column
+        };
+
+        let capped = column(&["1"]).merge_with(&column(&["3000000000"]), &config);
+        assert_eq!(capped.sql_type, SqlType::Text);
+        assert!(capped.int_capped);
+
+        let mut distinct = column(&["1", "2"]).merge_with(&column(&["3"]), &config);
+        let mut repeated = column(&["1", "2"]).merge_with(&column(&["2"]), &config);
+        distinct.finalize_with(&config);
+        repeated.finalize_with(&config);
+        assert!(distinct.unique);
+        assert!(!repeated.unique);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "merging schemas of different columns")]
+    fn test_column_merge_names_must_match() {
+        ColumnSchema::new("a".to_string()).merge(&ColumnSchema::new("b".to_string()));
+    }
+
+    #[test]
+    fn test_parallel_inference_matches_serial() {
+        let names: Vec<String> = ["id", "amount", "code", "when", "late", "mixed"].iter().map(|n| n.to_string()).collect();
+        let total = INFERENCE_PART_ROWS * 3 + 17;
+        let rows: Vec<(u64, Vec<String>)> = (0..total)
+            .map(|i| {
+                let row = vec![
+                    i.to_string(),
+                    if i % 5 == 0 { String::new() } else if i < INFERENCE_PART_ROWS { i.to_string() } else { format!("{}.5", i) },
+                    // Unique until a value repeats in the last part
+                    if i == total - 1 { "7".to_string() } else { (i * 2 + 1).to_string() },
+                    format!("2024-01-{:02}", i % 28 + 1),
+                    // Only the last part holds values, one past the integer cap
+                    if i < INFERENCE_PART_ROWS * 3 { String::new() } else { "3000000000".to_string() },
+                    // Widened then degraded within the second part
+                    match i.checked_sub(INFERENCE_PART_ROWS) {
+                        Some(3) => "1.5".to_string(),
+                        Some(5) => "x".to_string(),
+                        _ => (i % 100).to_string(),
+                    },
+                ];
+                (i as u64 + 2, row)
+            })
+            .collect();
+
+        for detect_pk in [false, true] {
+            let config = InferenceConfig {
+                detect_pk,
+                max_int_type: IntegerCap::Integer,
+                profile: true,
+                ..Default::default()
+            };
+
+            let mut serial = TableSchema::new("t".to_string(), names.clone());
+            for (_, row) in &rows {
+                serial.update_row_with(row, &config).unwrap();
+            }
+            serial.finalize_with(&config);
+
+            let mut parallel = TableSchema::new("t".to_string(), names.clone());
+            parallel.update_rows_with(&rows, &config).unwrap();
+            parallel.finalize_with(&config);
+
+            for (s, p) in serial.columns.iter().zip(&parallel.columns) {
+                assert_eq!(s.sql_type, p.sql_type, "{}", s.name);
+                assert_eq!(s.warnings, p.warnings, "{}", s.name);
+                assert_eq!(
+                    (s.nullable, s.sample_count, s.null_count, s.max_length, s.unique, s.int_capped),
+                    (p.nullable, p.sample_count, p.null_count, p.max_length, p.unique, p.int_capped),
+                    "{}", s.name
+                );
+                let (sp, pp) = (s.profile.as_ref().unwrap(), p.profile.as_ref().unwrap());
+                assert_eq!((sp.range(&s.sql_type), sp.distinct()), (pp.range(&p.sql_type), pp.distinct()), "{}", s.name);
+            }
+            assert_eq!(serial.primary_key, parallel.primary_key);
+        }
+
+        // Errors point at the row wherever it falls
+        let mut bad = rows.clone();
+        bad[INFERENCE_PART_ROWS * 2 + 1].1.pop();
+        let mut schema = TableSchema::new("t".to_string(), names);
+        let err = schema.update_rows_with(&bad, &InferenceConfig::default()).unwrap_err();
+        assert!(matches!(err, LoaderError::AtLine { line, .. } if line == (INFERENCE_PART_ROWS * 2 + 3) as u64));
+    }
+
+    #[test]
+    fn test_target_table_columns() {
+        let schema = TableSchema::new("t".to_string(), vec!["a".to_string(), "b".to_string()]);
+        let table: Vec<String> = ["id", "x", "y", "b", "created"].iter().map(|c| c.to_string()).collect();
+        let names = |v: &[&str]| v.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+
+        // By position
+        assert_eq!(
+            schema.target_table_columns(&names(&["y", "x"]), &[], &table).unwrap(),
+            vec![(0, "y".to_string()), (1, "x".to_string())]
+        );
+        assert!(schema.target_table_columns(&names(&["x"]), &[], &table).unwrap_err().to_string().contains("lists 1 columns but the CSV has 2"));
+        assert!(schema.target_table_columns(&names(&["x", "nope"]), &[], &table).is_err());
+        assert!(schema.target_table_columns(&names(&["x", "x"]), &[], &table).is_err());
+
+        // By name through the mapping, in target order
+        let map = vec![("a".to_string(), "y".to_string())];
+        assert_eq!(
+            schema.target_table_columns(&names(&["b", "y"]), &map, &table).unwrap(),
+            vec![(1, "b".to_string()), (0, "y".to_string())]
+        );
+        assert!(schema.target_table_columns(&names(&["y", "x"]), &map, &table).unwrap_err().to_string().contains("'x' has no CSV column"));
+    }
+
+    #[test]
+    fn test_merge_schemas_rejects_mismatches() {
+        let left = inferred(&["id"], &[&["1"]]);
+        let right = inferred(&["id", "name"], &[&["2", "b"]]);
+
+        assert!(left.merge_with(&right, true).is_err());
+        assert!(left.merge_with(&left, true).is_ok());
+
+        let mut other_table = left.clone();
+        other_table.table_name = "other".to_string();
+        assert!(left.merge(&other_table).is_err());
     }
 
     #[test]
@@ -258,4 +1885,23 @@ mod tests {
         assert!(TableSchema::validate_table_name("user-data").is_err());
         assert!(TableSchema::validate_table_name("SELECT").is_err());
     }
+
+    #[test]
+    fn test_validate_qualified_table_name() {
+        assert!(TableSchema::validate_table_name("public.users").is_ok());
+        assert!(TableSchema::validate_table_name("order.events").is_ok());
+
+        assert!(TableSchema::validate_table_name("a.b.c").is_err());
+        assert!(TableSchema::validate_table_name("public.").is_err());
+        assert!(TableSchema::validate_table_name(".users").is_err());
+        assert!(TableSchema::validate_table_name("public.SELECT").is_err());
+    }
+
+    #[test]
+    fn test_quote_table_name() {
+        assert_eq!(quote_table_name("users"), "\"users\"");
+        assert_eq!(quote_table_name("public.users"), "\"public\".\"users\"");
+        assert_eq!(quote_table_name("order.events"), "\"order\".\"events\"");
+        assert_eq!(split_table_name("analytics.events"), (Some("analytics"), "events"));
+    }
 }
@@ -1,10 +1,42 @@
 //! SQL type system for schema inference
 
-use chrono::NaiveDateTime;
+use crate::schema::InferenceConfig;
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::fmt;
+use std::net::IpAddr;
+
+/// Values inferred as `true` unless configured otherwise
+pub const BOOL_TRUE_TOKENS: &[&str] = &["true", "t", "yes", "y"];
+
+/// Values inferred as `false` unless configured otherwise
+pub const BOOL_FALSE_TOKENS: &[&str] = &["false", "f", "no", "n"];
+
+/// Widest integer type inference may widen a column to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, clap::ValueEnum)]
+pub enum IntegerCap {
+    #[value(name = "smallint")]
+    SmallInt,
+    Integer,
+    #[default]
+    #[value(name = "bigint")]
+    BigInt,
+}
+
+impl IntegerCap {
+    /// The capped SQL type
+    pub fn sql_type(self) -> SqlType {
+        match self {
+            IntegerCap::SmallInt => SqlType::SmallInt,
+            IntegerCap::Integer => SqlType::Integer,
+            IntegerCap::BigInt => SqlType::BigInt,
+        }
+    }
+}
 
 /// Represents PostgreSQL data types we can infer
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum SqlType {
     Null,
     Boolean,
@@ -13,15 +45,26 @@ pub enum SqlType {
     BigInt,
     Real,
     DoublePrecision,
+    Numeric,
     Timestamp,
+    TimestampTz,
     Date,
+    Time,
+    /// IPv4 or IPv6 host address, optionally with a netmask
+    Inet,
+    /// IPv4 or IPv6 network
+    Cidr,
+    /// Length-limited text, in characters
+    Varchar { len: u32 },
+    /// One-dimensional array of the element type
+    Array(Box<SqlType>),
     Text,
 }
 
 impl SqlType {
     /// Get the PostgreSQL type name
-    pub fn to_sql(&self) -> &str {
-        match self {
+    pub fn to_sql(&self) -> Cow<'static, str> {
+        let name = match self {
             SqlType::Null => "TEXT", // Default to TEXT for NULL columns
             SqlType::Boolean => "BOOLEAN",
             SqlType::SmallInt => "SMALLINT",
@@ -29,63 +72,546 @@ impl SqlType {
             SqlType::BigInt => "BIGINT",
             SqlType::Real => "REAL",
             SqlType::DoublePrecision => "DOUBLE PRECISION",
+            SqlType::Numeric => "NUMERIC",
             SqlType::Timestamp => "TIMESTAMP",
+            SqlType::TimestampTz => "TIMESTAMPTZ",
             SqlType::Date => "DATE",
+            SqlType::Time => "TIME",
+            SqlType::Inet => "INET",
+            SqlType::Cidr => "CIDR",
+            SqlType::Varchar { len } => return Cow::Owned(format!("VARCHAR({})", len)),
+            SqlType::Array(element) => return Cow::Owned(format!("{}[]", element.to_sql())),
             SqlType::Text => "TEXT",
+        };
+        Cow::Borrowed(name)
+    }
+
+    /// Parse a PostgreSQL type name, accepting common aliases
+    pub fn from_sql(name: &str) -> Option<Self> {
+        let normalized = name.split_whitespace().collect::<Vec<_>>().join(" ").to_ascii_uppercase();
+
+        // `INTEGER[]`, or `_int4` as information_schema reports array types
+        if let Some(element) = normalized.strip_suffix("[]").or_else(|| normalized.strip_prefix('_')) {
+            return match Self::from_sql(element)? {
+                SqlType::Array(_) => None,
+                element => Some(SqlType::Array(Box::new(element))),
+            };
         }
+
+        let sql_type = match normalized.as_str() {
+            "BOOLEAN" | "BOOL" => SqlType::Boolean,
+            "SMALLINT" | "INT2" => SqlType::SmallInt,
+            "INTEGER" | "INT" | "INT4" => SqlType::Integer,
+            "BIGINT" | "INT8" => SqlType::BigInt,
+            "REAL" | "FLOAT4" => SqlType::Real,
+            "DOUBLE PRECISION" | "FLOAT8" => SqlType::DoublePrecision,
+            "NUMERIC" | "DECIMAL" => SqlType::Numeric,
+            "TIMESTAMP" => SqlType::Timestamp,
+            "TIMESTAMPTZ" | "TIMESTAMP WITH TIME ZONE" => SqlType::TimestampTz,
+            "DATE" => SqlType::Date,
+            "TIME" => SqlType::Time,
+            "INET" => SqlType::Inet,
+            "CIDR" => SqlType::Cidr,
+            "TEXT" | "VARCHAR" | "CHARACTER VARYING" => SqlType::Text,
+            other => {
+                let len = other
+                    .strip_prefix("VARCHAR")
+                    .or_else(|| other.strip_prefix("CHARACTER VARYING"))?
+                    .trim()
+                    .strip_prefix('(')?
+                    .strip_suffix(')')?
+                    .trim()
+                    .parse()
+                    .ok()
+                    .filter(|len| *len > 0)?;
+                SqlType::Varchar { len }
+            }
+        };
+
+        Some(sql_type)
     }
 
     /// Infer type from a string value
     pub fn infer_from_str(value: &str) -> Self {
+        Self::infer_from_str_with(value, &InferenceConfig::default())
+    }
+
+    /// Infer type from a string value using inference settings
+    pub fn infer_from_str_with(value: &str, config: &InferenceConfig) -> Self {
         // Empty or null-like values
-        if value.is_empty() || value.eq_ignore_ascii_case("null") || value.eq_ignore_ascii_case("\\N") {
+        if Self::is_null(value, &config.null_tokens) {
             return SqlType::Null;
         }
 
         // Boolean
-        if let Ok(_) = value.parse::<bool>() {
+        if Self::parse_bool(value, &config.bool_true, &config.bool_false).is_some() {
             return SqlType::Boolean;
         }
 
-        // Try integers (from smallest to largest)
-        if let Ok(_val) = value.parse::<i16>() {
-            return SqlType::SmallInt;
+        // `{1,2,3}` or `[a,b,c]`
+        if Self::is_delimited_array(value) {
+            return Self::infer_array(value, config);
         }
-        if let Ok(_val) = value.parse::<i32>() {
-            return SqlType::Integer;
+
+        // Zero-padded codes (zip, phone) would lose their leading zeros as numbers
+        if config.keep_leading_zeros && Self::has_leading_zero(value) {
+            return SqlType::Text;
         }
-        if let Ok(_val) = value.parse::<i64>() {
-            return SqlType::BigInt;
+
+        // Hexadecimal integers like 0xFF
+        if config.hex {
+            if let Some(number) = Self::parse_hex(value) {
+                return Self::integer_type(number);
+            }
         }
 
-        // Try floats
-        if let Ok(val) = value.parse::<f32>() {
-            if !val.is_infinite() && !val.is_nan() {
-                return SqlType::Real;
+        // Currency amounts and percentages; money stays exact
+        if config.strip_symbols {
+            let stripped = Self::strip_symbols(
+                value,
+                config.decimal_separator,
+                config.thousands_separator,
+                config.percent_as_fraction,
+            );
+            if let Some((number, symbol)) = stripped {
+                if let Some(number_type) = Self::number_type(&number) {
+                    return if symbol == '%' { number_type } else { SqlType::Numeric };
+                }
             }
         }
-        if let Ok(val) = value.parse::<f64>() {
-            if !val.is_infinite() && !val.is_nan() {
-                return SqlType::DoublePrecision;
+
+        // Apply locale separators before numeric parsing
+        let number = Self::normalize_number(value, config.decimal_separator, config.thousands_separator);
+        if let Some(number_type) = Self::number_type(&number) {
+            return number_type;
+        }
+
+        // Spelled-out infinity and NaN, which float columns accept
+        if !config.reject_nonfinite && Self::parse_nonfinite(value).is_some() {
+            return SqlType::Real;
+        }
+
+        // IP addresses, and networks when there are no host bits
+        if config.inet {
+            if let Some((_, _, network)) = Self::parse_inet(value) {
+                return if network { SqlType::Cidr } else { SqlType::Inet };
             }
         }
 
-        // Try timestamp formats
-        if Self::is_timestamp(value) {
+        // Try timestamp formats (offset-bearing first)
+        if Self::is_timestamptz(value, &config.timestamp_formats) {
+            return SqlType::TimestampTz;
+        }
+        if Self::is_timestamp(value, &config.timestamp_formats) {
             return SqlType::Timestamp;
         }
 
         // Try date formats
-        if Self::is_date(value) {
+        if Self::is_date(value, &config.date_formats) {
             return SqlType::Date;
         }
 
+        // Try clock times without a date
+        if Self::is_time(value) {
+            return SqlType::Time;
+        }
+
         // Default to text
         SqlType::Text
     }
 
-    /// Check if value looks like a timestamp
-    fn is_timestamp(value: &str) -> bool {
+    /// Check if value is a built-in null marker or one of the configured tokens
+    pub fn is_null(value: &str, null_tokens: &[String]) -> bool {
+        value.is_empty()
+            || value.eq_ignore_ascii_case("null")
+            || value.eq_ignore_ascii_case("\\N")
+            || null_tokens.iter().any(|token| token == value)
+    }
+
+    /// Match a value against boolean tokens, ignoring ASCII case
+    pub fn parse_bool(value: &str, true_tokens: &[String], false_tokens: &[String]) -> Option<bool> {
+        let matches = |tokens: &[String]| tokens.iter().any(|token| token.eq_ignore_ascii_case(value));
+
+        if matches(true_tokens) {
+            Some(true)
+        } else if matches(false_tokens) {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    /// Type of a number in `1234.56` form, if it is one
+    fn number_type(number: &str) -> Option<Self> {
+        // Try integers (from smallest to largest)
+        if let Ok(_val) = number.parse::<i16>() {
+            return Some(SqlType::SmallInt);
+        }
+        if let Ok(_val) = number.parse::<i32>() {
+            return Some(SqlType::Integer);
+        }
+        if let Ok(_val) = number.parse::<i64>() {
+            return Some(SqlType::BigInt);
+        }
+
+        // Whole numbers in scientific notation, like 1e3
+        if let Some(number) = Self::parse_scientific_integer(number) {
+            return Some(Self::integer_type(number));
+        }
+
+        // Try floats
+        if let Ok(val) = number.parse::<f32>() {
+            if !val.is_infinite() && !val.is_nan() {
+                return Some(SqlType::Real);
+            }
+        }
+        if let Ok(val) = number.parse::<f64>() {
+            if !val.is_infinite() && !val.is_nan() {
+                return Some(SqlType::DoublePrecision);
+            }
+        }
+
+        None
+    }
+
+    /// Remove a leading or trailing `$`, `€`, `£` or `%` and digit grouping
+    ///
+    /// Returns the number in `1234.56` form with the symbol removed, or
+    /// `None` if there was no symbol. Grouping defaults to `,` unless that's
+    /// the decimal separator. With `percent_as_fraction`, percentages are
+    /// divided by 100.
+    pub fn strip_symbols(
+        value: &str,
+        decimal: char,
+        thousands: Option<char>,
+        percent_as_fraction: bool,
+    ) -> Option<(String, char)> {
+        const SYMBOLS: [char; 4] = ['$', '€', '£', '%'];
+
+        let (sign, unsigned) = match value.strip_prefix('-') {
+            Some(rest) => ("-", rest),
+            None => ("", value.strip_prefix('+').unwrap_or(value)),
+        };
+        let (symbol, number) = if let Some(rest) = unsigned.strip_prefix(SYMBOLS) {
+            (unsigned.chars().next()?, rest)
+        } else if let Some(rest) = unsigned.strip_suffix(SYMBOLS) {
+            (unsigned.chars().next_back()?, rest)
+        } else {
+            return None;
+        };
+
+        let number = format!("{}{}", sign, number.trim());
+        let thousands = thousands.or((decimal != ',').then_some(','));
+        let number = Self::normalize_number(&number, decimal, thousands).into_owned();
+
+        if symbol == '%' && percent_as_fraction {
+            return Some((Self::shift_decimal_left(&number, 2)?, symbol));
+        }
+        Some((number, symbol))
+    }
+
+    /// Divide a plain decimal number by a power of ten by moving its point
+    fn shift_decimal_left(number: &str, places: usize) -> Option<String> {
+        let (sign, unsigned) = match number.strip_prefix('-') {
+            Some(rest) => ("-", rest),
+            None => ("", number),
+        };
+        let (int_part, frac_part) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+        if int_part.is_empty() || !int_part.bytes().chain(frac_part.bytes()).all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+
+        let padded = format!("{:0>width$}", int_part, width = places + 1);
+        let (int_part, moved) = padded.split_at(padded.len() - places);
+        Some(format!("{}{}.{}{}", sign, int_part, moved, frac_part))
+    }
+
+    /// Postgres spelling of `inf`, `-infinity`, `NaN` and similar tokens
+    pub fn parse_nonfinite(value: &str) -> Option<&'static str> {
+        let (negative, unsigned) = match value.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, value.strip_prefix('+').unwrap_or(value)),
+        };
+
+        if unsigned.eq_ignore_ascii_case("inf") || unsigned.eq_ignore_ascii_case("infinity") {
+            Some(if negative { "-Infinity" } else { "Infinity" })
+        } else if unsigned.eq_ignore_ascii_case("nan") && !negative {
+            Some("NaN")
+        } else {
+            None
+        }
+    }
+
+    /// Smallest integer type that holds a value
+    fn integer_type(value: i64) -> Self {
+        if i16::try_from(value).is_ok() {
+            SqlType::SmallInt
+        } else if i32::try_from(value).is_ok() {
+            SqlType::Integer
+        } else {
+            SqlType::BigInt
+        }
+    }
+
+    /// Parse a `0x`-prefixed hexadecimal integer, optionally signed
+    pub fn parse_hex(value: &str) -> Option<i64> {
+        let (negative, unsigned) = match value.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, value.strip_prefix('+').unwrap_or(value)),
+        };
+        let digits = unsigned.strip_prefix("0x").or_else(|| unsigned.strip_prefix("0X"))?;
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+
+        let magnitude = i64::from_str_radix(digits, 16).ok()?;
+        Some(if negative { -magnitude } else { magnitude })
+    }
+
+    /// Parse scientific notation that denotes a whole number, like `1.5e2`
+    ///
+    /// Works on the decimal digits rather than through `f64`, so large
+    /// values stay exact.
+    pub fn parse_scientific_integer(value: &str) -> Option<i64> {
+        let (mantissa, exponent) = value.split_once(['e', 'E'])?;
+        let exponent: i64 = exponent.parse().ok()?;
+        let (sign, mantissa) = match mantissa.strip_prefix('-') {
+            Some(rest) => ("-", rest),
+            None => ("", mantissa.strip_prefix('+').unwrap_or(mantissa)),
+        };
+        let (int_part, frac_part) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        if int_part.is_empty() && frac_part.is_empty() {
+            return None;
+        }
+        if !int_part.bytes().chain(frac_part.bytes()).all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+
+        let digits = format!("{}{}", int_part, frac_part);
+        let digits = digits.trim_start_matches('0');
+        if digits.is_empty() {
+            return Some(0);
+        }
+        let shift = exponent - frac_part.len() as i64;
+        let whole = if shift >= 0 {
+            // i64 has at most 19 digits
+            if digits.len() as i64 + shift > 19 {
+                return None;
+            }
+            format!("{}{}", digits, "0".repeat(shift as usize))
+        } else {
+            let kept = digits.len().saturating_sub(shift.unsigned_abs() as usize);
+            if !digits[kept..].bytes().all(|b| b == b'0') {
+                return None;
+            }
+            digits[..kept].to_string()
+        };
+
+        format!("{}{}", sign, whole).parse().ok()
+    }
+
+    /// Whether a value is wrapped in array braces or brackets
+    fn is_delimited_array(value: &str) -> bool {
+        (value.starts_with('{') && value.ends_with('}')) || (value.starts_with('[') && value.ends_with(']'))
+    }
+
+    /// Infer an array type from its elements; nested or malformed arrays are TEXT
+    fn infer_array(value: &str, config: &InferenceConfig) -> Self {
+        let Some(elements) = Self::parse_array(value) else {
+            return SqlType::Text;
+        };
+
+        let element = elements.iter().fold(SqlType::Null, |acc, element| {
+            let element = match element {
+                Some(element) => Self::infer_from_str_with(element, config),
+                None => SqlType::Null,
+            };
+            acc.merge(&element)
+        });
+
+        match element {
+            SqlType::Array(_) => SqlType::Text,
+            element => SqlType::Array(Box::new(element)),
+        }
+    }
+
+    /// Split a flat `{a,b}` or `[a,b]` literal into its elements
+    ///
+    /// Elements may be double-quoted with backslash escapes; an unquoted
+    /// empty or `NULL` element is `None`. Returns `None` for values that
+    /// aren't a flat array.
+    pub fn parse_array(value: &str) -> Option<Vec<Option<String>>> {
+        let inner = value
+            .strip_prefix('{')
+            .and_then(|v| v.strip_suffix('}'))
+            .or_else(|| value.strip_prefix('[').and_then(|v| v.strip_suffix(']')))?;
+        if inner.trim().is_empty() {
+            return Some(Vec::new());
+        }
+
+        let mut elements = Vec::new();
+        let mut chars = inner.chars();
+        loop {
+            let mut element = String::new();
+            let mut quoted = false;
+            if let Some(rest) = chars.as_str().trim_start().strip_prefix('"') {
+                chars = rest.chars();
+                quoted = true;
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => element.push(chars.next()?),
+                        c => element.push(c),
+                    }
+                }
+            }
+
+            // Unquoted text, or what trails a quoted element, up to the comma
+            let mut tail = String::new();
+            let mut last = true;
+            for c in chars.by_ref() {
+                match c {
+                    ',' => {
+                        last = false;
+                        break;
+                    }
+                    '{' | '}' | '[' | ']' | '"' => return None,
+                    c => tail.push(c),
+                }
+            }
+
+            let tail = tail.trim();
+            if quoted {
+                if !tail.is_empty() {
+                    return None;
+                }
+                elements.push(Some(element));
+            } else if tail.is_empty() || tail.eq_ignore_ascii_case("null") {
+                elements.push(None);
+            } else {
+                elements.push(Some(tail.to_string()));
+            }
+
+            if last {
+                return Some(elements);
+            }
+        }
+    }
+
+    /// Whether this is an integer type wider than `cap`
+    pub fn exceeds(&self, cap: IntegerCap) -> bool {
+        let width = match self {
+            SqlType::SmallInt => IntegerCap::SmallInt,
+            SqlType::Integer => IntegerCap::Integer,
+            SqlType::BigInt => IntegerCap::BigInt,
+            _ => return false,
+        };
+        width > cap
+    }
+
+    /// Whether values inferred as this type load into a column of type
+    /// `column` without error or loss
+    ///
+    /// Any number fits a floating point or NUMERIC column and anything fits
+    /// TEXT; otherwise the column must be at least as general.
+    pub fn loads_into(&self, column: &SqlType) -> bool {
+        match (self, column) {
+            (SqlType::Varchar { len }, SqlType::Varchar { len: max }) => len <= max,
+            (_, SqlType::Text | SqlType::Varchar { .. }) => true,
+            (value, SqlType::Real | SqlType::DoublePrecision | SqlType::Numeric) if value.is_numeric() => true,
+            _ => self.merge(column) == *column,
+        }
+    }
+
+    /// Whether this is a numeric type
+    pub fn is_numeric(&self) -> bool {
+        matches!(
+            self,
+            SqlType::SmallInt | SqlType::Integer | SqlType::BigInt
+                | SqlType::Real | SqlType::DoublePrecision | SqlType::Numeric
+        )
+    }
+
+    /// Rewrite a locale-formatted number into Postgres's `1234.56` form
+    ///
+    /// Grouping separators are only stripped when every group after the
+    /// first has exactly three digits; otherwise the value is returned
+    /// unchanged so it fails numeric parsing.
+    pub fn normalize_number(value: &str, decimal: char, thousands: Option<char>) -> Cow<'_, str> {
+        if decimal == '.' && thousands.is_none() {
+            return Cow::Borrowed(value);
+        }
+
+        let (int_part, frac_part) = match value.split_once(decimal) {
+            Some((int_part, frac_part)) => (int_part, Some(frac_part)),
+            None => (value, None),
+        };
+
+        let int_part = match thousands {
+            Some(sep) if int_part.contains(sep) => {
+                let mut groups = int_part.split(sep);
+                let first = groups.next().unwrap_or("").trim_start_matches(['-', '+']);
+                let valid = !first.is_empty()
+                    && first.len() <= 3
+                    && groups.all(|g| g.len() == 3 && g.bytes().all(|b| b.is_ascii_digit()));
+                if !valid {
+                    return Cow::Borrowed(value);
+                }
+                int_part.replace(sep, "")
+            }
+            _ => int_part.to_string(),
+        };
+
+        match frac_part {
+            Some(frac_part) => Cow::Owned(format!("{}.{}", int_part, frac_part)),
+            None => Cow::Owned(int_part),
+        }
+    }
+
+    /// Check if value is an all-digit string with a significant leading zero
+    fn has_leading_zero(value: &str) -> bool {
+        value.len() > 1 && value.starts_with('0') && value.bytes().all(|b| b.is_ascii_digit())
+    }
+
+    /// Check if value looks like a timestamp with a timezone offset
+    fn is_timestamptz(value: &str, extra_formats: &[String]) -> bool {
+        Self::parse_timestamptz(value, extra_formats).is_some()
+    }
+
+    /// Parse a timestamp with a timezone offset (RFC 3339 and close variants)
+    pub fn parse_timestamptz(value: &str, extra_formats: &[String]) -> Option<DateTime<FixedOffset>> {
+        if let Ok(parsed) = DateTime::parse_from_rfc3339(value) {
+            return Some(parsed);
+        }
+
+        // Space-separated variants of RFC 3339 and compact offsets
+        let formats = [
+            "%Y-%m-%d %H:%M:%S%:z",
+            "%Y-%m-%d %H:%M:%S%.f%:z",
+            "%Y-%m-%d %H:%M:%S%z",
+            "%Y-%m-%d %H:%M:%S%.f%z",
+            "%Y-%m-%dT%H:%M:%S%z",
+            "%Y-%m-%dT%H:%M:%S%.f%z",
+        ];
+
+        if let Some(parsed) = formats.iter().find_map(|fmt| DateTime::parse_from_str(value, fmt).ok()) {
+            return Some(parsed);
+        }
+
+        // Trailing `Z` for UTC
+        value
+            .strip_suffix('Z')
+            .and_then(|naive| Self::parse_timestamp(naive, extra_formats))
+            .map(|naive| naive.and_utc().fixed_offset())
+    }
+
+    /// Check if value looks like a timestamp, trying built-in then extra formats
+    fn is_timestamp(value: &str, extra_formats: &[String]) -> bool {
+        Self::parse_timestamp(value, extra_formats).is_some()
+    }
+
+    /// Parse a timestamp, trying built-in then extra formats
+    pub fn parse_timestamp(value: &str, extra_formats: &[String]) -> Option<NaiveDateTime> {
         // Common timestamp formats
         let formats = [
             "%Y-%m-%d %H:%M:%S",
@@ -97,13 +623,41 @@ impl SqlType {
             "%m/%d/%Y %H:%M:%S",
         ];
 
-        formats.iter().any(|fmt| {
-            NaiveDateTime::parse_from_str(value, fmt).is_ok()
+        formats.iter().copied().chain(extra_formats.iter().map(String::as_str)).find_map(|fmt| {
+            NaiveDateTime::parse_from_str(value, fmt).ok()
         })
     }
 
-    /// Check if value looks like a date
-    fn is_date(value: &str) -> bool {
+    /// Parse a value loaded into a TIMESTAMP column
+    ///
+    /// Inference widens a column mixing timestamps and dates to TIMESTAMP,
+    /// so a date is accepted as midnight.
+    pub fn parse_timestamp_column(value: &str, date_formats: &[String], timestamp_formats: &[String]) -> Option<NaiveDateTime> {
+        Self::parse_timestamp(value, timestamp_formats)
+            .or_else(|| Self::parse_date(value, date_formats).map(|date| date.and_time(NaiveTime::MIN)))
+    }
+
+    /// Parse a value loaded into a TIMESTAMPTZ column
+    ///
+    /// Inference widens timestamps and dates mixed with offset timestamps
+    /// to TIMESTAMPTZ, so those without an offset are taken as UTC.
+    pub fn parse_timestamptz_column(
+        value: &str,
+        date_formats: &[String],
+        timestamp_formats: &[String],
+    ) -> Option<DateTime<FixedOffset>> {
+        Self::parse_timestamptz(value, timestamp_formats).or_else(|| {
+            Self::parse_timestamp_column(value, date_formats, timestamp_formats).map(|naive| naive.and_utc().fixed_offset())
+        })
+    }
+
+    /// Check if value looks like a date, trying built-in then extra formats
+    fn is_date(value: &str, extra_formats: &[String]) -> bool {
+        Self::parse_date(value, extra_formats).is_some()
+    }
+
+    /// Parse a date, trying built-in then extra formats
+    pub fn parse_date(value: &str, extra_formats: &[String]) -> Option<NaiveDate> {
         // Common date formats
         let formats = [
             "%Y-%m-%d",
@@ -113,16 +667,58 @@ impl SqlType {
             "%d/%m/%Y",
         ];
 
-        formats.iter().any(|fmt| {
-            chrono::NaiveDate::parse_from_str(value, fmt).is_ok()
+        formats.iter().copied().chain(extra_formats.iter().map(String::as_str)).find_map(|fmt| {
+            NaiveDate::parse_from_str(value, fmt).ok()
         })
     }
 
+    /// Check if value looks like a time of day
+    fn is_time(value: &str) -> bool {
+        Self::parse_time(value).is_some()
+    }
+
+    /// Parse a time of day
+    pub fn parse_time(value: &str) -> Option<NaiveTime> {
+        let formats = ["%H:%M:%S", "%H:%M", "%H:%M:%S%.f"];
+
+        formats.iter().find_map(|fmt| {
+            NaiveTime::parse_from_str(value, fmt).ok()
+        })
+    }
+
+    /// Parse an IPv4 or IPv6 address with an optional `/prefix`
+    ///
+    /// Returns the address, the prefix length (the full width when absent),
+    /// and whether the value names a network: a prefix with no host bits set.
+    pub fn parse_inet(value: &str) -> Option<(IpAddr, u8, bool)> {
+        let (address, prefix) = match value.split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix)),
+            None => (value, None),
+        };
+        let address: IpAddr = address.parse().ok()?;
+
+        let (width, bits) = match address {
+            IpAddr::V4(v4) => (32, u32::from(v4) as u128),
+            IpAddr::V6(v6) => (128, u128::from(v6)),
+        };
+        let Some(prefix) = prefix else {
+            return Some((address, width, false));
+        };
+        if prefix.is_empty() || !prefix.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let prefix: u8 = prefix.parse().ok().filter(|p| *p <= width)?;
+
+        let host_bits = width - prefix;
+        let network = host_bits == 0 || bits & (u128::MAX >> (128 - u32::from(host_bits))) == 0;
+        Some((address, prefix, network))
+    }
+
     /// Merge two types to find the most general type
     pub fn merge(&self, other: &SqlType) -> SqlType {
         use SqlType::*;
 
-        // Ordering: Null < Boolean < SmallInt < Integer < BigInt < Real < DoublePrecision < Timestamp < Date < Text
+        // Ordering: Null < Boolean < SmallInt < Integer < BigInt < Real < DoublePrecision < Numeric < Timestamp < TimestampTz < Date < Time < Varchar < Text
         // If types differ, promote to the more general type
 
         match (self, other) {
@@ -135,11 +731,23 @@ impl SqlType {
             // Same types
             (a, b) if a == b => a.clone(),
 
+            // Arrays widen their elements; an array and a scalar need TEXT
+            (Array(a), Array(b)) => Array(Box::new(a.merge(b))),
+            (Array(_), _) | (_, Array(_)) => Text,
+
+            // Varchar widens to fit the longer value; anything else needs TEXT
+            (Varchar { len: a }, Varchar { len: b }) => Varchar { len: *a.max(b) },
+            (Varchar { .. }, _) | (_, Varchar { .. }) => Text,
+
             // Numeric promotions
             (SmallInt, Integer) | (Integer, SmallInt) => Integer,
             (SmallInt, BigInt) | (BigInt, SmallInt) => BigInt,
             (Integer, BigInt) | (BigInt, Integer) => BigInt,
 
+            // Exact numeric absorbs any other number
+            (Numeric, SmallInt | Integer | BigInt | Real | DoublePrecision) |
+            (SmallInt | Integer | BigInt | Real | DoublePrecision, Numeric) => Numeric,
+
             // Any int with any float -> float
             (SmallInt | Integer | BigInt, Real | DoublePrecision) |
             (Real | DoublePrecision, SmallInt | Integer | BigInt) => DoublePrecision,
@@ -147,16 +755,21 @@ impl SqlType {
             // Float promotions
             (Real, DoublePrecision) | (DoublePrecision, Real) => DoublePrecision,
 
+            // A network is also a valid INET value
+            (Inet, Cidr) | (Cidr, Inet) => Inet,
+
             // Date/Timestamp
             (Date, Timestamp) | (Timestamp, Date) => Timestamp,
+            (Date | Timestamp, TimestampTz) | (TimestampTz, Date | Timestamp) => TimestampTz,
 
             // Boolean with anything else -> Text
             (Boolean, _) | (_, Boolean) => Text,
 
             // Date/Timestamp with numbers -> Text
-            (Date | Timestamp, SmallInt | Integer | BigInt | Real | DoublePrecision) |
-            (SmallInt | Integer | BigInt | Real | DoublePrecision, Date | Timestamp) => Text,
+            (Date | Timestamp | TimestampTz, SmallInt | Integer | BigInt | Real | DoublePrecision | Numeric) |
+            (SmallInt | Integer | BigInt | Real | DoublePrecision | Numeric, Date | Timestamp | TimestampTz) => Text,
 
+            // Time only merges with itself (handled above)
             // Catchall for any remaining combinations
             _ => Text,
         }
@@ -181,10 +794,38 @@ mod tests {
         assert_eq!(SqlType::infer_from_str("\\N"), SqlType::Null);
     }
 
+    #[test]
+    fn test_infer_custom_null_tokens() {
+        let config = InferenceConfig {
+            null_tokens: vec!["NA".to_string(), "N/A".to_string(), "-".to_string()],
+            ..Default::default()
+        };
+
+        assert_eq!(SqlType::infer_from_str_with("NA", &config), SqlType::Null);
+        assert_eq!(SqlType::infer_from_str_with("N/A", &config), SqlType::Null);
+        assert_eq!(SqlType::infer_from_str_with("-", &config), SqlType::Null);
+        assert_eq!(SqlType::infer_from_str_with("", &config), SqlType::Null);
+
+        // Tokens only apply when configured
+        assert_eq!(SqlType::infer_from_str("NA"), SqlType::Text);
+    }
+
     #[test]
     fn test_infer_boolean() {
         assert_eq!(SqlType::infer_from_str("true"), SqlType::Boolean);
         assert_eq!(SqlType::infer_from_str("false"), SqlType::Boolean);
+        assert_eq!(SqlType::infer_from_str("Y"), SqlType::Boolean);
+        assert_eq!(SqlType::infer_from_str("no"), SqlType::Boolean);
+
+        // 1/0 stay integers unless opted in
+        assert_eq!(SqlType::infer_from_str("1"), SqlType::SmallInt);
+        let config = InferenceConfig {
+            bool_true: vec!["1".to_string()],
+            bool_false: vec!["0".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(SqlType::infer_from_str_with("0", &config), SqlType::Boolean);
+        assert_eq!(SqlType::infer_from_str_with("yes", &config), SqlType::Text);
     }
 
     #[test]
@@ -195,12 +836,135 @@ mod tests {
         assert_eq!(SqlType::infer_from_str("2147483648"), SqlType::BigInt);
     }
 
+    #[test]
+    fn test_infer_hex_and_scientific() {
+        assert_eq!(SqlType::infer_from_str("0x10"), SqlType::SmallInt);
+        assert_eq!(SqlType::infer_from_str("0xFFFFFFFF"), SqlType::BigInt);
+        assert_eq!(SqlType::infer_from_str("0x"), SqlType::Text);
+        assert_eq!(SqlType::infer_from_str("1e3"), SqlType::SmallInt);
+        assert_eq!(SqlType::infer_from_str("1.5e2"), SqlType::SmallInt);
+        assert_eq!(SqlType::infer_from_str("1.5e9"), SqlType::Integer);
+        assert_eq!(SqlType::infer_from_str("1.25e1"), SqlType::Real);
+
+        let config = InferenceConfig {
+            hex: false,
+            ..Default::default()
+        };
+        assert_eq!(SqlType::infer_from_str_with("0x10", &config), SqlType::Text);
+
+        assert_eq!(SqlType::parse_hex("-0x1f"), Some(-31));
+        assert_eq!(SqlType::parse_hex("0x+1"), None);
+        assert_eq!(SqlType::parse_scientific_integer("1e3"), Some(1000));
+        assert_eq!(SqlType::parse_scientific_integer("-1.50E2"), Some(-150));
+        assert_eq!(SqlType::parse_scientific_integer("1234567890123456789e0"), Some(1234567890123456789));
+        assert_eq!(SqlType::parse_scientific_integer("100e-3"), None);
+        assert_eq!(SqlType::parse_scientific_integer("0e99999"), Some(0));
+        assert_eq!(SqlType::parse_scientific_integer("1e99999"), None);
+        assert_eq!(SqlType::parse_scientific_integer("150"), None);
+    }
+
+    #[test]
+    fn test_infer_nonfinite() {
+        assert_eq!(SqlType::infer_from_str("NaN"), SqlType::Real);
+        assert_eq!(SqlType::infer_from_str("-inf"), SqlType::Real);
+        assert_eq!(SqlType::infer_from_str("Infinity"), SqlType::Real);
+        assert_eq!(SqlType::infer_from_str("-nan"), SqlType::Text);
+
+        // One NaN doesn't turn a float column into TEXT
+        let column = ["1.5", "2.25", "NaN", "3"]
+            .iter()
+            .map(|v| SqlType::infer_from_str(v))
+            .fold(SqlType::Null, |acc, t| acc.merge(&t));
+        assert_eq!(column, SqlType::DoublePrecision);
+
+        let config = InferenceConfig {
+            reject_nonfinite: true,
+            ..Default::default()
+        };
+        assert_eq!(SqlType::infer_from_str_with("inf", &config), SqlType::Text);
+
+        assert_eq!(SqlType::parse_nonfinite("+INF"), Some("Infinity"));
+        assert_eq!(SqlType::parse_nonfinite("-infinity"), Some("-Infinity"));
+        assert_eq!(SqlType::parse_nonfinite("nan"), Some("NaN"));
+    }
+
+    #[test]
+    fn test_infer_currency_and_percent() {
+        let config = InferenceConfig {
+            strip_symbols: true,
+            ..Default::default()
+        };
+        assert_eq!(SqlType::infer_from_str_with("$1,000", &config), SqlType::Numeric);
+        assert_eq!(SqlType::infer_from_str_with("£9.99", &config), SqlType::Numeric);
+        assert_eq!(SqlType::infer_from_str_with("-€5", &config), SqlType::Numeric);
+        assert_eq!(SqlType::infer_from_str_with("45%", &config), SqlType::SmallInt);
+        assert_eq!(SqlType::infer_from_str_with("45", &config), SqlType::SmallInt);
+        assert_eq!(SqlType::infer_from_str_with("$", &config), SqlType::Text);
+
+        // Off by default
+        assert_eq!(SqlType::infer_from_str("$1,000"), SqlType::Text);
+        assert_eq!(SqlType::infer_from_str("45%"), SqlType::Text);
+
+        let fraction = InferenceConfig {
+            percent_as_fraction: true,
+            ..config
+        };
+        assert_eq!(SqlType::infer_from_str_with("45%", &fraction), SqlType::Real);
+
+        assert_eq!(SqlType::strip_symbols("$1,000", '.', None, false), Some(("1000".to_string(), '$')));
+        assert_eq!(SqlType::strip_symbols("1.234,5 €", ',', Some('.'), false), Some(("1234.5".to_string(), '€')));
+        assert_eq!(SqlType::strip_symbols("45%", '.', None, true), Some(("0.45".to_string(), '%')));
+        assert_eq!(SqlType::strip_symbols("-2.5%", '.', None, true), Some(("-0.025".to_string(), '%')));
+        assert_eq!(SqlType::strip_symbols("45", '.', None, false), None);
+    }
+
+    #[test]
+    fn test_infer_leading_zeros() {
+        assert_eq!(SqlType::infer_from_str("0"), SqlType::SmallInt);
+        assert_eq!(SqlType::infer_from_str("00"), SqlType::Text);
+        assert_eq!(SqlType::infer_from_str("0123"), SqlType::Text);
+        assert_eq!(SqlType::infer_from_str("123"), SqlType::SmallInt);
+
+        let config = InferenceConfig {
+            keep_leading_zeros: false,
+            ..Default::default()
+        };
+        assert_eq!(SqlType::infer_from_str_with("0123", &config), SqlType::SmallInt);
+    }
+
     #[test]
     fn test_infer_floats() {
         assert_eq!(SqlType::infer_from_str("3.14"), SqlType::Real);
         assert_eq!(SqlType::infer_from_str("3.14159265359"), SqlType::Real);
     }
 
+    #[test]
+    fn test_infer_locale_numbers() {
+        let german = InferenceConfig {
+            decimal_separator: ',',
+            thousands_separator: Some('.'),
+            ..Default::default()
+        };
+        let us = InferenceConfig {
+            thousands_separator: Some(','),
+            ..Default::default()
+        };
+
+        assert_eq!(SqlType::infer_from_str_with("1.234,56", &german), SqlType::Real);
+        assert_eq!(SqlType::infer_from_str_with("1,234.56", &us), SqlType::Real);
+
+        // Ambiguous: grouping under US rules, decimal under German rules
+        assert_eq!(SqlType::infer_from_str_with("1,234", &us), SqlType::SmallInt);
+        assert_eq!(SqlType::infer_from_str_with("1,234", &german), SqlType::Real);
+        assert_eq!(SqlType::infer_from_str("1,234"), SqlType::Text);
+
+        // Bad grouping is not a number
+        assert_eq!(SqlType::infer_from_str_with("12,34", &us), SqlType::Text);
+
+        assert_eq!(SqlType::normalize_number("1.234,56", ',', Some('.')), "1234.56");
+        assert_eq!(SqlType::normalize_number("-1,234,567", '.', Some(',')), "-1234567");
+    }
+
     #[test]
     fn test_infer_dates() {
         assert_eq!(SqlType::infer_from_str("2024-01-15"), SqlType::Date);
@@ -213,12 +977,187 @@ mod tests {
         assert_eq!(SqlType::infer_from_str("2024-01-15T10:30:00"), SqlType::Timestamp);
     }
 
+    #[test]
+    fn test_infer_custom_formats() {
+        let config = InferenceConfig {
+            date_formats: vec!["%b %d, %Y".to_string(), "%d.%m.%Y".to_string()],
+            timestamp_formats: vec!["%d.%m.%Y %H:%M".to_string()],
+            ..Default::default()
+        };
+
+        assert_eq!(SqlType::infer_from_str_with("Jan 15, 2024", &config), SqlType::Date);
+        assert_eq!(SqlType::infer_from_str_with("15.01.2024", &config), SqlType::Date);
+        assert_eq!(SqlType::infer_from_str_with("15.01.2024 10:30", &config), SqlType::Timestamp);
+
+        // Built-in formats still apply alongside custom ones
+        assert_eq!(SqlType::infer_from_str_with("2024-01-15", &config), SqlType::Date);
+        assert_eq!(SqlType::infer_from_str("15.01.2024"), SqlType::Text);
+    }
+
+    #[test]
+    fn test_infer_timestamptz() {
+        assert_eq!(SqlType::infer_from_str("2024-01-15T10:30:00Z"), SqlType::TimestampTz);
+        assert_eq!(SqlType::infer_from_str("2024-01-15 10:30:00Z"), SqlType::TimestampTz);
+        assert_eq!(SqlType::infer_from_str("2024-01-15T10:30:00+00:00"), SqlType::TimestampTz);
+        assert_eq!(SqlType::infer_from_str("2024-01-15 10:30:00-08:00"), SqlType::TimestampTz);
+        assert_eq!(SqlType::infer_from_str("2024-01-15T10:30:00.123+05:00"), SqlType::TimestampTz);
+
+        assert_eq!(SqlType::Timestamp.merge(&SqlType::TimestampTz), SqlType::TimestampTz);
+        assert_eq!(SqlType::TimestampTz.merge(&SqlType::Date), SqlType::TimestampTz);
+    }
+
+    #[test]
+    fn test_parse_widened_columns() {
+        let dates = vec!["%d.%m.%Y".to_string()];
+        let timestamp = |value| SqlType::parse_timestamp_column(value, &dates, &[]).map(|t| t.to_string());
+        let timestamptz = |value| SqlType::parse_timestamptz_column(value, &dates, &[]).map(|t| t.to_rfc3339());
+
+        assert_eq!(timestamp("2024-01-15 10:30:00").as_deref(), Some("2024-01-15 10:30:00"));
+        assert_eq!(timestamp("2024-01-16").as_deref(), Some("2024-01-16 00:00:00"));
+        assert_eq!(timestamp("16.01.2024").as_deref(), Some("2024-01-16 00:00:00"));
+        assert_eq!(timestamp("10:30"), None);
+
+        assert_eq!(timestamptz("2024-01-15T10:30:00+02:00").as_deref(), Some("2024-01-15T10:30:00+02:00"));
+        assert_eq!(timestamptz("2024-01-15 10:30:00").as_deref(), Some("2024-01-15T10:30:00+00:00"));
+        assert_eq!(timestamptz("2024-01-16").as_deref(), Some("2024-01-16T00:00:00+00:00"));
+    }
+
+    #[test]
+    fn test_infer_time() {
+        assert_eq!(SqlType::infer_from_str("14:30:00"), SqlType::Time);
+        assert_eq!(SqlType::infer_from_str("09:15"), SqlType::Time);
+        assert_eq!(SqlType::infer_from_str("23:59:59.250"), SqlType::Time);
+        assert_eq!(SqlType::infer_from_str("30:00"), SqlType::Text);
+
+        assert_eq!(SqlType::Time.merge(&SqlType::Time), SqlType::Time);
+        assert_eq!(SqlType::Time.merge(&SqlType::Null), SqlType::Time);
+        assert_eq!(SqlType::Time.merge(&SqlType::Timestamp), SqlType::Text);
+        assert_eq!(SqlType::Integer.merge(&SqlType::Time), SqlType::Text);
+    }
+
     #[test]
     fn test_infer_text() {
         assert_eq!(SqlType::infer_from_str("hello world"), SqlType::Text);
         assert_eq!(SqlType::infer_from_str("abc123"), SqlType::Text);
     }
 
+    #[test]
+    fn test_from_sql() {
+        assert_eq!(SqlType::from_sql("TEXT"), Some(SqlType::Text));
+        assert_eq!(SqlType::from_sql("integer"), Some(SqlType::Integer));
+        assert_eq!(SqlType::from_sql("int8"), Some(SqlType::BigInt));
+        assert_eq!(SqlType::from_sql("NUMERIC"), Some(SqlType::Numeric));
+        assert_eq!(SqlType::from_sql("double   precision"), Some(SqlType::DoublePrecision));
+        assert_eq!(SqlType::from_sql("money"), None);
+
+        // Every emitted spelling parses back
+        for t in [SqlType::Boolean, SqlType::SmallInt, SqlType::Integer, SqlType::BigInt,
+                  SqlType::Real, SqlType::DoublePrecision, SqlType::Numeric,
+                  SqlType::Timestamp, SqlType::TimestampTz, SqlType::Date, SqlType::Time,
+                  SqlType::Inet, SqlType::Cidr, SqlType::Varchar { len: 32 }, SqlType::Text] {
+            assert_eq!(SqlType::from_sql(&t.to_sql()), Some(t));
+        }
+        assert_eq!(SqlType::from_sql("varchar( 8 )"), Some(SqlType::Varchar { len: 8 }));
+        assert_eq!(SqlType::from_sql("varchar(0)"), None);
+
+        let int_array = SqlType::Array(Box::new(SqlType::Integer));
+        assert_eq!(SqlType::from_sql(&int_array.to_sql()), Some(int_array.clone()));
+        assert_eq!(SqlType::from_sql("_int4"), Some(int_array));
+        assert_eq!(SqlType::from_sql("INTEGER[][]"), None);
+    }
+
+    #[test]
+    fn test_infer_inet() {
+        assert_eq!(SqlType::infer_from_str("192.168.0.1"), SqlType::Inet);
+        assert_eq!(SqlType::infer_from_str("::1"), SqlType::Inet);
+        assert_eq!(SqlType::infer_from_str("10.0.0.0/8"), SqlType::Cidr);
+        assert_eq!(SqlType::infer_from_str("10.0.0.1/8"), SqlType::Inet);
+        assert_eq!(SqlType::infer_from_str("2001:db8::/32"), SqlType::Cidr);
+
+        // Near misses stay TEXT
+        assert_eq!(SqlType::infer_from_str("999.1.1.1"), SqlType::Text);
+        assert_eq!(SqlType::infer_from_str("10.0.0.0/33"), SqlType::Text);
+        assert_eq!(SqlType::infer_from_str("10.0.0.0/"), SqlType::Text);
+
+        let config = InferenceConfig {
+            inet: false,
+            ..Default::default()
+        };
+        assert_eq!(SqlType::infer_from_str_with("192.168.0.1", &config), SqlType::Text);
+
+        assert_eq!(SqlType::Inet.merge(&SqlType::Cidr), SqlType::Inet);
+        assert_eq!(SqlType::Cidr.merge(&SqlType::Cidr), SqlType::Cidr);
+        assert_eq!(SqlType::Inet.merge(&SqlType::Integer), SqlType::Text);
+    }
+
+    #[test]
+    fn test_infer_arrays() {
+        let array = |t: SqlType| SqlType::Array(Box::new(t));
+
+        assert_eq!(SqlType::infer_from_str("{1,2,3}"), array(SqlType::SmallInt));
+        assert_eq!(SqlType::infer_from_str("[1, NULL, 70000]"), array(SqlType::Integer));
+        assert_eq!(SqlType::infer_from_str("[a,b,c]"), array(SqlType::Text));
+        assert_eq!(SqlType::infer_from_str(r#"{"x, y","z"}"#), array(SqlType::Text));
+        assert_eq!(SqlType::infer_from_str("{}"), array(SqlType::Null));
+        assert_eq!(array(SqlType::Integer).to_sql(), "INTEGER[]");
+
+        // Nested and malformed arrays stay TEXT
+        assert_eq!(SqlType::infer_from_str("{{1,2},{3,4}}"), SqlType::Text);
+        assert_eq!(SqlType::infer_from_str(r#"[{"a":1}]"#), SqlType::Text);
+        assert_eq!(SqlType::infer_from_str(r#"{"1",2"}"#), SqlType::Text);
+    }
+
+    #[test]
+    fn test_parse_array() {
+        assert_eq!(
+            SqlType::parse_array(r#"{ "a\"b" , NULL, "", c }"#),
+            Some(vec![Some("a\"b".to_string()), None, Some(String::new()), Some("c".to_string())])
+        );
+        assert_eq!(SqlType::parse_array("[]"), Some(vec![]));
+        assert_eq!(SqlType::parse_array("{1,2]"), None);
+        assert_eq!(SqlType::parse_array(r#"{"unterminated}"#), None);
+    }
+
+    #[test]
+    fn test_varchar_merge() {
+        let short = SqlType::Varchar { len: 8 };
+        let long = SqlType::Varchar { len: 64 };
+
+        assert_eq!(short.merge(&long), long);
+        assert_eq!(SqlType::Null.merge(&short), short);
+        assert_eq!(short.merge(&SqlType::Integer), SqlType::Text);
+        assert_eq!(short.merge(&SqlType::Text), SqlType::Text);
+    }
+
+    #[test]
+    fn test_exceeds_integer_cap() {
+        assert!(SqlType::BigInt.exceeds(IntegerCap::Integer));
+        assert!(SqlType::Integer.exceeds(IntegerCap::SmallInt));
+        assert!(!SqlType::Integer.exceeds(IntegerCap::Integer));
+        assert!(!SqlType::Numeric.exceeds(IntegerCap::SmallInt));
+        assert!(!SqlType::BigInt.exceeds(IntegerCap::default()));
+    }
+
+    #[test]
+    fn test_loads_into() {
+        use SqlType::*;
+
+        assert!(SmallInt.loads_into(&BigInt));
+        assert!(BigInt.loads_into(&Real));
+        assert!(Null.loads_into(&Date));
+        assert!(Date.loads_into(&TimestampTz));
+        assert!(Cidr.loads_into(&Inet));
+        assert!(Integer.loads_into(&Varchar { len: 3 }));
+        assert!(Array(Box::new(SmallInt)).loads_into(&Array(Box::new(Integer))));
+
+        assert!(!Text.loads_into(&Integer));
+        assert!(!BigInt.loads_into(&Integer));
+        assert!(!Real.loads_into(&BigInt));
+        assert!(!Boolean.loads_into(&SmallInt));
+        assert!(!TimestampTz.loads_into(&Timestamp));
+        assert!(!Varchar { len: 10 }.loads_into(&Varchar { len: 5 }));
+    }
+
     #[test]
     fn test_type_merge() {
         assert_eq!(SqlType::SmallInt.merge(&SqlType::Integer), SqlType::Integer);
@@ -226,5 +1165,13 @@ mod tests {
         assert_eq!(SqlType::SmallInt.merge(&SqlType::Real), SqlType::DoublePrecision);
         assert_eq!(SqlType::Integer.merge(&SqlType::Text), SqlType::Text);
         assert_eq!(SqlType::Null.merge(&SqlType::Integer), SqlType::Integer);
+        assert_eq!(SqlType::Numeric.merge(&SqlType::Real), SqlType::Numeric);
+        assert_eq!(SqlType::Date.merge(&SqlType::Numeric), SqlType::Text);
+
+        let small = SqlType::Array(Box::new(SqlType::SmallInt));
+        let big = SqlType::Array(Box::new(SqlType::BigInt));
+        assert_eq!(small.merge(&big), big);
+        assert_eq!(small.merge(&SqlType::SmallInt), SqlType::Text);
+        assert_eq!(SqlType::Null.merge(&small), small);
     }
 }